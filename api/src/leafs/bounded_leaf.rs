@@ -1,4 +1,5 @@
 use std::mem;
+use std::slice;
 use std::ops::RangeInclusive;
 use std::cmp::PartialOrd;
 
@@ -8,8 +9,9 @@ use rand::distributions::uniform::SampleUniform;
 use rand::seq::SliceRandom;
 
 extern crate core;
-use self::core::generator::leaf::IArgLeaf;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
 use self::core::generator::serialize::ISerializableArg;
+use self::core::rng;
 
 /// arg generator for bounded values - ranges ( 1..22, 0..1, 66..888, ..)
 pub struct Bounded<T> {
@@ -42,6 +44,23 @@ impl<T> Bounded<T>
     }
 }
 
+/// value +- 1, as little endian integer of its size ( wrapping )
+fn step<T>(val: &T, up: bool) -> Vec<u8> {
+    let mut data = unsafe { slice::from_raw_parts(val as *const T as *const u8, mem::size_of::<T>()) }.to_vec();
+    for byte in data.iter_mut() {
+        let (next, carry) = if up { byte.overflowing_add(1) } else { byte.overflowing_sub(1) };
+        *byte = next;
+        if !carry {
+            break
+        }
+    }
+    data
+}
+
+fn bytes<T>(val: &T) -> Vec<u8> {
+    unsafe { slice::from_raw_parts(val as *const T as *const u8, mem::size_of::<T>()) }.to_vec()
+}
+
 impl<T> ISerializableArg for Bounded<T> { }
 
 impl<T: Copy + PartialOrd + SampleUniform + std::fmt::Debug> IArgLeaf for Bounded<T>
@@ -51,9 +70,90 @@ impl<T: Copy + PartialOrd + SampleUniform + std::fmt::Debug> IArgLeaf for Bounde
     fn name(&self) -> &'static str { "Bounded" }
 
     fn generate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) {
-        *generic::data_mut_unsafe::<T>(mem) = match self.bounds.clone().choose(&mut rand::thread_rng()) {
-            Some(bounds) => rand::thread_rng().gen_range(bounds.clone()),
+        *generic::data_mut_unsafe::<T>(mem) = match self.bounds.clone().choose(&mut rng::thread_rng()) {
+            Some(bounds) => rng::thread_rng().gen_range(bounds.clone()),
             None => panic!("nothing in bound array ?"),
         };
     }
+
+    /// edge of range current value belongs to ( or of random one ), off by one around it, or other
+    /// value of that range
+    fn mutate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) -> bool {
+        let val = *generic::data_const_unsafe::<T>(mem);
+        let mut rng = rng::thread_rng();
+        let bounds = match self.bounds
+            .iter()
+            .find(|bounds| bounds.contains(&val))
+            .or_else(|| self.bounds.choose(&mut rng))
+        {
+            Some(bounds) => bounds.clone(),
+            None => return false,
+        };
+        match rng.gen_range(0u8..6) {
+            0 => *generic::data_mut_unsafe::<T>(mem) = *bounds.start(),
+            1 => *generic::data_mut_unsafe::<T>(mem) = *bounds.end(),
+            2 => mem.copy_from_slice(&step(bounds.start(), false)),
+            3 => mem.copy_from_slice(&step(bounds.end(), true)),
+            _ => *generic::data_mut_unsafe::<T>(mem) = rng.gen_range(bounds),
+        };
+        true
+    }
+
+    /// edges of every range, and off by one at both sides of them
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        self.bounds
+            .iter()
+            .flat_map(|bounds| vec![
+                bytes(bounds.start()),
+                bytes(bounds.end()),
+                step(bounds.start(), false),
+                step(bounds.start(), true),
+                step(bounds.end(), false),
+                step(bounds.end(), true),
+            ])
+            .map(|data| InterestingValue { offset : 0, data })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interesting<T>(leaf: &Bounded<T>) -> Vec< Vec<u8> >
+        where T: Copy + PartialOrd + SampleUniform + std::fmt::Debug
+    {
+        leaf.interesting_values().into_iter().map(|value| value.data).collect()
+    }
+
+    #[test]
+    fn edges_and_off_by_one() {
+        let leaf = Bounded::ranges(vec![10u16..=20, 0x100..=0x1ff]);
+        let values = interesting(&leaf);
+        for val in [9u16, 10, 11, 19, 20, 21, 0xff, 0x100, 0x101, 0x1fe, 0x1ff, 0x200].iter() {
+            assert!(values.contains(&val.to_le_bytes().to_vec()), "missing {:#x}", val);
+        }
+    }
+
+    #[test]
+    fn off_by_one_wraps() {
+        let values = interesting(&Bounded::one(0u8..=0xff));
+        assert!(values.contains(&vec![0xff]));
+        assert!(values.contains(&vec![0]));
+
+        let values = interesting(&Bounded::one(-128i8..=-1));
+        assert!(values.contains(&127i8.to_le_bytes().to_vec()));
+        assert!(values.contains(&0i8.to_le_bytes().to_vec()));
+    }
+
+    #[test]
+    fn mutate_stays_around_range() {
+        let mut leaf = Bounded::one(-5i32..=5);
+        let mut mem = 3i32.to_le_bytes();
+        for _ in 0..1000 {
+            assert!(leaf.mutate(&mut mem, &[]));
+            let val = i32::from_le_bytes(mem);
+            assert!((-6..=6).contains(&val), "{} out of range +- 1", val);
+        }
+    }
 }
@@ -2,7 +2,7 @@ extern crate byteorder;
 use self::byteorder::{LittleEndian, WriteBytesExt};
 
 extern crate core;
-use self::core::generator::leaf::IArgLeaf;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
 use self::core::generator::serialize::ISerializableArg;
 
 /// arg generator for constant values ( primitive types u8..u64, up to arrays of u8 )
@@ -54,4 +54,13 @@ impl IArgLeaf for Const {
           .as_slice()); // LITTLE ENDIAN
         */
     }
+
+    /// constant stays constant
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        self.generate_unsafe(mem, fd);
+        false
+    }
+
+    /// nothing, mutation would only write constant back over itself
+    fn interesting_values(&self) -> Vec<InterestingValue> { Vec::new() }
 }
@@ -47,4 +47,16 @@ impl IArgLeaf for DeRef {
     fn generate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) {
       mem.copy_from_slice(&fd);
     }
+
+    /// fd of our state is not subject of mutation, just make sure it is live one
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        self.generate_unsafe(mem, fd);
+        false
+    }
+
+    /// recorded fd is dead, we always refer to live fd of our state
+    fn load_unsafe(&mut self, mem: &mut[u8], _: &[u8], _: &[u8], fd: &[u8]) -> usize {
+        self.generate_unsafe(mem, fd);
+        0
+    }
 }
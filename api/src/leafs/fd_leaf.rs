@@ -9,6 +9,8 @@ use self::core::generator::leaf::IArgLeaf;
 use self::core::generator::serialize::ISerializableArg;
 use self::core::generator::serialize::SerializationInfo;
 use self::core::banana::bananaq;
+use self::core::poc::replay;
+use self::core::rng;
 use self::core::state::id::StateTableId;
 
 use self::core::exec::fd_info::Fd;
@@ -61,10 +63,22 @@ impl IArgLeaf for FdHolder {
 
     fn generate_unsafe(&mut self, mem: &mut [u8], fd: &[u8]) {
         self.fds
-            .choose_mut(&mut rand::thread_rng())
+            .choose_mut(&mut rng::thread_rng())
             .unwrap()
             .generate(mem, fd);
     }
+
+    /// fd loaded from pool is already remaped, connection between states is kept as it is
+    fn mutate_unsafe(&mut self, _: &mut [u8], _: &[u8]) -> bool {
+        false
+    }
+
+    /// recorded fd ( own dup or foreign state ) is remaped to fd of live state recorded one
+    /// stands for
+    fn load_unsafe(&mut self, mem: &mut [u8], data: &[u8], _: &[u8], _: &[u8]) -> usize {
+        mem.copy_from_slice(&replay::remap(data));
+        0
+    }
 }
 
 pub struct RndFd {
@@ -91,7 +105,7 @@ impl IArgLeaf for RndFd {
     ///
     /// other time we provide NULL or invalid one
     fn generate_unsafe(&mut self, mem: &mut [u8], _: &[u8]) {
-        match rand::thread_rng().gen_range(0u8..=7) {
+        match rng::thread_rng().gen_range(0u8..=7) {
             0 => mem.clone_from_slice(&Fd::dummy(self.size()).data()),
             1 => mem.clone_from_slice(&Fd::invalid(self.size()).data()),
             _ => {
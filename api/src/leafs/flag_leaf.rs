@@ -1,4 +1,5 @@
 use std::mem;
+use std::slice;
 use std::ops::BitAnd;
 use std::ops::BitOr;
 
@@ -7,8 +8,9 @@ use rand::Rng;
 use rand::distributions::{Standard, Distribution};
 
 extern crate core;
-use self::core::generator::leaf::IArgLeaf;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
 use self::core::generator::serialize::ISerializableArg;
+use self::core::rng;
 
 extern crate generic;
 
@@ -29,6 +31,18 @@ impl<T> Flag<T> {
     }
 }
 
+/// bit indexes set in value, little endian
+fn bits<T>(val: &T) -> Vec<usize> {
+    let data = unsafe { slice::from_raw_parts(val as *const T as *const u8, mem::size_of::<T>()) };
+    (0..data.len() * 8)
+        .filter(|&bit| 0 != data[bit / 8] & (1 << (bit % 8)))
+        .collect()
+}
+
+fn bytes<T>(val: &T) -> Vec<u8> {
+    unsafe { slice::from_raw_parts(val as *const T as *const u8, mem::size_of::<T>()) }.to_vec()
+}
+
 impl<T> ISerializableArg for Flag<T> { }
 
 impl<T: Copy + BitAnd + BitOr> IArgLeaf for Flag<T>
@@ -46,9 +60,103 @@ impl<T: Copy + BitAnd + BitOr> IArgLeaf for Flag<T>
     fn generate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) {
         *generic::data_mut_unsafe::<T>(mem) = T::from(
             self.always | T::from(
-                rand::thread_rng().gen::<T>() & self.flag));
-        if rand::thread_rng().gen_bool(1./6.) {
-            *generic::data_mut_unsafe::<T>(mem) = rand::thread_rng().gen::<T>();
+                rng::thread_rng().gen::<T>() & self.flag));
+        if rng::thread_rng().gen_bool(1./6.) {
+            *generic::data_mut_unsafe::<T>(mem) = rng::thread_rng().gen::<T>();
+        }
+    }
+
+    /// flip one of volatile bits, always ones stay on
+    ///
+    /// - 1:8 we clear one of always bits instead, target should refuse it, but does it ?
+    fn mutate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) -> bool {
+        let mut rng = rng::thread_rng();
+        let always = bits(&self.always);
+        if !always.is_empty() && rng.gen_bool(1./8.) {
+            let bit = always[rng.gen_range(0..always.len())];
+            mem[bit / 8] &= !(1 << (bit % 8));
+            return true
+        }
+        let flag = bits(&self.flag);
+        if flag.is_empty() {
+            return false
+        }
+        let bit = flag[rng.gen_range(0..flag.len())];
+        mem[bit / 8] ^= 1 << (bit % 8);
+        *generic::data_mut_unsafe::<T>(mem) = T::from(self.always | *generic::data_const_unsafe::<T>(mem));
+        true
+    }
+
+    /// always alone, with all flags, with every single flag, and without always bits
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        let always = bytes(&self.always);
+        let mut values = vec![
+            always.clone(),
+            bytes(&T::from(self.always | self.flag)),
+            bytes(&self.flag),
+            vec![0; always.len()],
+        ];
+        for bit in bits(&self.flag) {
+            let mut value = always.clone();
+            value[bit / 8] |= 1 << (bit % 8);
+            values.push(value);
+        }
+        values
+            .into_iter()
+            .map(|data| InterestingValue { offset : 0, data })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALWAYS: u16 = 0x8001;
+    const FLAG: u16 = 0x0ff0;
+
+    #[test]
+    fn mutate_flips_one_flag_and_keeps_always() {
+        let mut leaf = Flag::<u16>::new(ALWAYS, FLAG);
+        let (mut flips, mut cleared) = (0, 0);
+        for _ in 0..1000 {
+            let before = ALWAYS | 0x0120;
+            let mut mem = before.to_le_bytes();
+            assert!(leaf.mutate(&mut mem, &[]));
+            let after = u16::from_le_bytes(mem);
+            let diff = before ^ after;
+            assert_eq!(1, diff.count_ones(), "{:#x} -> {:#x}", before, after);
+            if 0 != diff & FLAG {
+                assert_eq!(ALWAYS, after & ALWAYS);
+                flips += 1;
+            } else {
+                assert_eq!(0, diff & !ALWAYS);
+                cleared += 1;
+            }
+        }
+        assert!(flips > cleared && 0 != cleared);
+    }
+
+    #[test]
+    fn mutate_without_flags() {
+        let mut leaf = Flag::<u8>::new(0, 0);
+        let mut mem = [0x42];
+        assert!(!leaf.mutate(&mut mem, &[]));
+        assert_eq!([0x42], mem);
+    }
+
+    #[test]
+    fn interesting_values_of_single_flags() {
+        let values = Flag::<u16>::new(ALWAYS, FLAG)
+            .interesting_values()
+            .into_iter()
+            .map(|value| u16::from_le_bytes([value.data[0], value.data[1]]))
+            .collect::< Vec<u16> >();
+        assert!(values.contains(&ALWAYS));
+        assert!(values.contains(&(ALWAYS | FLAG)));
+        assert!(values.contains(&0));
+        for bit in 4..12 {
+            assert!(values.contains(&(ALWAYS | (1 << bit))));
         }
     }
 }
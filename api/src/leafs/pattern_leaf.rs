@@ -1,5 +1,5 @@
 extern crate core;
-use self::core::generator::leaf::IArgLeaf;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
 use self::core::generator::serialize::ISerializableArg;
 
 /// arg generator for patterned data ( nullptr, or others )
@@ -27,4 +27,14 @@ impl IArgLeaf for Pattern {
     fn generate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) {
       mem.fill(self.pattern)
     }
+
+    /// padding / terminators stay as they are
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        self.generate_unsafe(mem, fd);
+        false
+    }
+
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        vec![InterestingValue { offset : 0, data : vec![self.pattern; self.size] }]
+    }
 }
@@ -23,4 +23,6 @@ impl IArgLeaf for Phantom {
     fn name(&self) -> &'static str { "Phantom" }
 
     fn generate_unsafe(&mut self, _: &mut[u8], _: &[u8]) { }
+
+    fn mutate_unsafe(&mut self, _: &mut[u8], _: &[u8]) -> bool { false }
 }
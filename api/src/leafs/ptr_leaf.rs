@@ -49,4 +49,34 @@ impl IArgLeaf for Ptr {
     fn generate_unsafe(&mut self, mem: &mut [u8], fd: &[u8]) {
         *generic::data_mut_unsafe::<*const u8>(mem) = self.arg.borrow_mut().do_generate(fd).data_const_unsafe();
     }
+
+    /// pointed memory is mutated, pointer stays
+    fn mutate_unsafe(&mut self, mem: &mut [u8], fd: &[u8]) -> bool {
+        let mut arg = self.arg.borrow_mut();
+        let mutated = arg.do_mutate(fd);
+        *generic::data_mut_unsafe::<*const u8>(mem) = arg.data_const_unsafe();
+        mutated
+    }
+
+    /// pointer itself is meaningless for PoC, pointed memory is what matters
+    fn dump(&self, _: &[u8]) -> Vec<u8> {
+        let arg = self.arg.borrow();
+        let mut extra = arg.data().to_vec();
+        extra.extend(arg.dump_extra());
+        extra
+    }
+
+    /// load pointed memory from extra, and point to our live memory instead of recorded pointer
+    fn load_unsafe(&mut self, mem: &mut [u8], _: &[u8], extra: &[u8], fd: &[u8]) -> usize {
+        let mut arg = self.arg.borrow_mut();
+        let size = arg.size();
+        let consumed = if extra.len() < size {
+            arg.do_generate(fd);
+            0
+        } else {
+            size + arg.do_load(&extra[..size], &extra[size..], fd)
+        };
+        *generic::data_mut_unsafe::<*const u8>(mem) = arg.data_const_unsafe();
+        consumed
+    }
 }
@@ -2,8 +2,9 @@ extern crate rand;
 use rand::Rng;
 
 extern crate core;
-use self::core::generator::leaf::IArgLeaf;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
 use self::core::generator::serialize::ISerializableArg;
+use self::core::rng;
 
 /// arg generator for random data
 /// Note : as good practice RndData leaf should be always used with 
@@ -33,6 +34,36 @@ impl IArgLeaf for RndData {
 
     fn generate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) {
         assert!(mem.len() == self.size);//check in debug is OK
-        rand::thread_rng().fill(mem);
+        rng::thread_rng().fill(mem);
+    }
+
+    /// flip bits of few random bytes, rest of data stays
+    fn mutate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) -> bool {
+        if mem.is_empty() {
+            return false
+        }
+        let mut rng = rng::thread_rng();
+        for _ in 0..rng.gen_range(1..=4) {
+            let ind = rng.gen_range(0..mem.len());
+            mem[ind] ^= 1 << rng.gen_range(0..8);
+        }
+        true
+    }
+
+    /// zeros, ones, and signed edges ( little endian ) when data are sized as integer
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        let mut values = vec![vec![0; self.size], vec![0xff; self.size]];
+        if [1, 2, 4, 8].contains(&self.size) {
+            let mut max = vec![0xff; self.size];
+            max[self.size - 1] = 0x7f;
+            let mut min = vec![0; self.size];
+            min[self.size - 1] = 0x80;
+            values.push(max);
+            values.push(min);
+        }
+        values
+            .into_iter()
+            .map(|data| InterestingValue { offset : 0, data })
+            .collect()
     }
 }
@@ -2,9 +2,16 @@ use std::ops::{
     RangeInclusive,
 };
 
+extern crate rand;
+use rand::Rng;
+
 extern crate core;
 
 use self::core::generator::composite::ArgComposite;
+use self::core::generator::leaf::{IArgLeaf, InterestingValue};
+use self::core::generator::serialize::ISerializableArg;
+use self::core::generator::serialize::SerializationInfo;
+use self::core::rng;
 
 use super::const_leaf::Const;
 use super::bounded_leaf::Bounded;
@@ -12,6 +19,81 @@ use super::pattern_leaf::Pattern;
 use super::tuple_leaf::TupleComposite;
 use super::array_comp::ArrayComposite;
 
+/// characters of string, as array of chars but mutation knows it is string
+///
+/// - mutation tweaks length : terminates string sooner, or extends it over its terminator
+/// - otherwise mutates one of chars, as array would
+pub struct StrChars {
+    chars: ArgComposite,
+    /// size of one char, 1 for ascii, 2 for wide ones
+    unit: usize,
+}
+
+impl StrChars {
+    pub fn new(chars: ArgComposite, unit: usize) -> StrChars {
+        StrChars {
+            chars,
+            unit,
+        }
+    }
+}
+
+impl ISerializableArg for StrChars {
+    fn serialize(&self, mem: &[u8], fd: &[u8]) -> Vec<SerializationInfo> {
+        self.chars.serialize(mem, fd)
+    }
+}
+
+impl IArgLeaf for StrChars {
+    fn size(&self) -> usize { self.chars.size() }
+
+    fn name(&self) -> &'static str { self.chars.name() }
+
+    fn generate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) {
+        self.chars.generate(mem, fd)
+    }
+
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        let count = mem.len() / self.unit;
+        let mut rng = rng::thread_rng();
+        if 0 == count || rng.gen_bool(0.5) {
+            return self.chars.mutate(mem, fd)
+        }
+        let len = (0..count)
+            .position(|i| mem[i * self.unit..(i + 1) * self.unit].iter().all(|&c| 0 == c))
+            .unwrap_or(count);
+        let new_len = rng.gen_range(0..=count);
+        if new_len < len {
+            mem[new_len * self.unit..].fill(0);
+        } else if new_len > len {
+            let mut fresh = vec![0u8; mem.len()];
+            self.chars.generate(&mut fresh, fd);
+            mem[len * self.unit..new_len * self.unit].copy_from_slice(&fresh[len * self.unit..new_len * self.unit]);
+        }
+        new_len != len
+    }
+
+    /// empty string, one char long string, and values of chars
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        let mut values = vec![InterestingValue { offset : 0, data : vec![0; self.size()] }];
+        if self.size() > self.unit {
+            let mut one = vec![0; self.size()];
+            one[0] = b'a';
+            values.push(InterestingValue { offset : 0, data : one });
+        }
+        values.extend(self.chars.interesting_values());
+        values
+    }
+
+    fn dump(&self, mem: &[u8]) -> Vec<u8> {
+        self.chars.dump(mem)
+    }
+
+    fn load_unsafe(&mut self, mem: &mut[u8], data: &[u8], extra: &[u8], fd: &[u8]) -> usize {
+        self.chars.load(mem, data, extra, fd)
+    }
+}
+
 /// typical argument, string, you can provide prefix and count in element wise type of string
 /// character, windows typical stuff
 pub trait StrLeaf {
@@ -30,10 +112,10 @@ impl StrLeaf for ArgComposite {
 			count,
 			"AStrLeaf_of_",
 			vec![
-				(0, Box::new(ArgComposite::array_leaf(
+				(0, Box::new(StrChars::new(ArgComposite::array_leaf(
                         "char",
                         count,
-                        || { Box::new(Bounded::one(bounds.clone())) }))),
+                        || { Box::new(Bounded::one(bounds.clone())) }), 1))),
 			])
 	}
 	fn astr_leaf_nz(prefix: &str, bounds: RangeInclusive<u8>, count: usize) -> ArgComposite {
@@ -46,10 +128,10 @@ impl StrLeaf for ArgComposite {
 			"AStrLeaf_of_",
 			vec![
 				(0, Box::new(Const::new(prefix))),
-				(prefix.len(), Box::new(ArgComposite::array_leaf(
+				(prefix.len(), Box::new(StrChars::new(ArgComposite::array_leaf(
                         "char",
                         count,
-                        || { Box::new(Bounded::one(bounds.clone())) }))),
+                        || { Box::new(Bounded::one(bounds.clone())) }), 1))),
 			])
 	}
 	fn astr_leaf(prefix: &str, count: usize) -> ArgComposite {
@@ -62,10 +144,10 @@ impl StrLeaf for ArgComposite {
 			"AStrLeaf_of_",
 			vec![
 				(0, Box::new(Const::new(prefix))),
-				(prefix.len(), Box::new(ArgComposite::array_leaf(
+				(prefix.len(), Box::new(StrChars::new(ArgComposite::array_leaf(
                         "char",
                         count,
-                        || { Box::new(Bounded::one(b'a'..=b'c')) }))),
+                        || { Box::new(Bounded::one(b'a'..=b'c')) }), 1))),
 				(prefix.len() + count, Box::new(Pattern::new(0, 1))),
 			])
 	}
@@ -96,14 +178,14 @@ impl StrLeaf for ArgComposite {
                     .fold(Vec::new(), |mut b, c| {
                         b.extend_from_slice(&[c as u8, 0u8]);
                         b } )))),
-				(2 * prefix.len(), Box::new(ArgComposite::array_leaf(
+				(2 * prefix.len(), Box::new(StrChars::new(ArgComposite::array_leaf(
                         "wcomp",
                         count,
                         || { Box::new(ArgComposite::tuple_leaf(
                                 "wchar_t",
                                 Box::new(Bounded::one(b'a'..=b'c')),
                                 Box::new(Const::new8(0))))
-                        }))),
+                        }), 2))),
 				(2 * (prefix.len() + count), Box::new(Pattern::new(0, 2))),
 			])
 	}
@@ -135,14 +217,75 @@ impl StrLeaf for ArgComposite {
                     .fold(Vec::new(), |mut b, c| {
                         b.extend_from_slice(&[c as u8, 0u8]);
                         b } )))),
-				(2 * prefix.len(), Box::new(ArgComposite::array_leaf(
+				(2 * prefix.len(), Box::new(StrChars::new(ArgComposite::array_leaf(
                         "wcomp",
                         count,
                         || { Box::new(ArgComposite::tuple_leaf(
                                 "wchar_t",
                                 Box::new(Bounded::one(b'a'..=b'c')),
                                 Box::new(Const::new8(0))))
-                        }))),
+                        }), 2))),
 			])
 	}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNT: usize = 8;
+
+    fn chars() -> StrChars {
+        StrChars::new(ArgComposite::array_leaf("char", COUNT, || Box::new(Bounded::one(b'a'..=b'c'))), 1)
+    }
+
+    fn len(mem: &[u8]) -> usize {
+        mem.iter().position(|&c| 0 == c).unwrap_or(mem.len())
+    }
+
+    #[test]
+    fn mutate_terminates_sooner() {
+        let mut leaf = chars();
+        let full = *b"abcabcab";
+        let mut shorter = 0;
+        for _ in 0..1000 {
+            let mut mem = full;
+            leaf.mutate(&mut mem, &[]);
+            let diff = (0..COUNT).filter(|&i| mem[i] != full[i]).count();
+            let len = len(&mem);
+            if diff > 1 {
+                assert_eq!(full[..len], mem[..len]);
+                assert!(mem[len..].iter().all(|&c| 0 == c), "{:?}", mem);
+                shorter += 1;
+            }
+        }
+        assert!(0 != shorter);
+    }
+
+    #[test]
+    fn mutate_extends_over_terminator() {
+        let mut leaf = chars();
+        let short = *b"ab\0\0\0\0\0\0";
+        let mut longer = 0;
+        for _ in 0..1000 {
+            let mut mem = short;
+            leaf.mutate(&mut mem, &[]);
+            let len = len(&mem);
+            // one char can be changed by mutation of chars, more only by extending string
+            if len > 3 {
+                assert_eq!(short[..2], mem[..2]);
+                assert!(mem[2..len].iter().all(|c| (b'a'..=b'c').contains(c)), "{:?}", mem);
+                assert!(mem[len..].iter().all(|&c| 0 == c), "{:?}", mem);
+                longer += 1;
+            }
+        }
+        assert!(0 != longer);
+    }
+
+    #[test]
+    fn interesting_values_of_empty_and_one_char() {
+        let values = chars().interesting_values();
+        assert!(values.iter().any(|value| value.data == vec![0; COUNT]));
+        assert!(values.iter().any(|value| 1 == len(&value.data) && COUNT == value.data.len()));
+    }
+}
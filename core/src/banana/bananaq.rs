@@ -1,73 +1,200 @@
-use std::sync::RwLock;
+use std::thread;
+use std::thread::ThreadId;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use super::observer::{
     ICallObserver,
     IStateObserver,
 };
 use exec::call::Call;
-use exec::fd_info::Fd;
+use exec::coverage::CallCoverage;
+use exec::fd_info::{
+    CallInfo,
+    Fd,
+};
+use exec::watchdog::Hang;
 use state::id::StateTableId;
-use state::state::IFuzzyObj;
-use super::queue::FuzzyQ;
+use state::state::{
+    IFuzzyObj,
+    StateInfo,
+};
+use context;
+pub use super::queue::Occupancy;
 
-lazy_static! {
-    /// sync primitive around Queue for fuzzing, singleton concept - for more better to check
-    /// queue.rs instead
-    static ref FUZZY_QUEUE: RwLock<FuzzyQ> = RwLock::new(FuzzyQ::new());
-}
+// sync primitive around Queue for fuzzing is owned by context of current thread, see context.rs
+// and queue.rs
 
 pub fn attach_call_observer(obs: Box<dyn ICallObserver>) {
-    match FUZZY_QUEUE.write() {
+    match context::current().queue().write() {
         Ok(mut banana) => banana.observers_call.push(obs),
         Err(_) => (),
     };
 }
 pub fn attach_state_observer(obs: Box<dyn IStateObserver>) {
-    match FUZZY_QUEUE.write() {
+    match context::current().queue().write() {
         Ok(mut banana) => banana.observers_state.push(obs),
         Err(_) => (),
     };
 }
 
 pub fn push(fuzzy_obj: &Box<dyn IFuzzyObj>) -> bool {
-    match FUZZY_QUEUE.write() {
+    if stopping() {
+        return false
+    }
+    match context::current().queue().write() {
         Ok(mut banana) => banana.push_safe(fuzzy_obj.state().info()),
         Err(_) => false,
     }
 }
 pub fn pop() {
-    match FUZZY_QUEUE.read() {
+    match context::current().queue().read() {
         Ok(banana) => banana.dtor_notify_safe(),
         Err(_) => (),
     };
-    match FUZZY_QUEUE.write() {
+    match context::current().queue().write() {
         Ok(mut banana) => banana.pop_safe(),
         Err(e) => panic!("FuzzyQ: pop fail, syscall excepted .. no more to do here {}", e)
     };
 }
 pub fn update(fuzzy_obj: &Box<dyn IFuzzyObj>) {
-    match FUZZY_QUEUE.write() {
+    match context::current().queue().write() {
         Ok(mut banana) => banana.update_safe(fuzzy_obj.state().info()),
         Err(e) => panic!("FuzzyQ: update fail, syscall excepted .. no more to do here {}", e)
     };
 }
 
 pub fn ctor_notify(fuzzy_obj: &Box<dyn IFuzzyObj>) -> bool {
-    match FUZZY_QUEUE.read() {
+    match context::current().queue().read() {
         Ok(banana) => banana.ctor_notify_safe(fuzzy_obj.state().info()),
         Err(_) => false,
     }
 }
 pub fn call_notify<'a>(call: &'a Call) -> bool {
-    match FUZZY_QUEUE.read() {
+    match context::current().queue().read() {
         Ok(banana) => banana.call_notify_safe(call),
         Err(_) => false,
     }
 }
+pub fn call_post_notify(call: &Call, result: &CallInfo, elapsed: Duration) {
+    if let Ok(banana) = context::current().queue().read() {
+        banana.call_post_notify_safe(call, result, elapsed)
+    }
+}
+
+/// call of thread hangs, let observers know and optionaly free its slot in queue
+///
+/// - returns info of hanged state, None if it is not in queue ( anymore )
+pub fn hang_notify(thread: ThreadId, hang: &Hang, reclaim: bool) -> Option<StateInfo> {
+    let info = match context::current().queue().read() {
+        Ok(banana) => banana.hang_notify_safe(thread, hang),
+        Err(_) => return None,
+    };
+    if reclaim && info.is_some() {
+        if let Ok(mut banana) = context::current().queue().write() {
+            banana.reclaim_safe(thread);
+        }
+    }
+    info
+}
+
+/// call of current thread found new coverage, let observers know
+///
+/// - returns info of state doing call, None if it is not in queue ( replay tools f.e. )
+pub fn coverage_notify(call: &Call, cov: &CallCoverage) -> Option<StateInfo> {
+    match context::current().queue().read() {
+        Ok(banana) => banana.coverage_notify_safe(call, cov),
+        Err(_) => None,
+    }
+}
+
+pub fn occupancy() -> Occupancy {
+    match context::current().queue().read() {
+        Ok(banana) => banana.occupancy_safe(),
+        Err(_) => Occupancy::default(),
+    }
+}
 
 pub fn get_rnd_fd(id: StateTableId) -> Fd {
-    match FUZZY_QUEUE.read() {
+    match context::current().queue().read() {
         Ok(banana) => banana.get_rnd_fd_safe(id),
         Err(_) => Fd::empty(),
     }
 }
+
+/// ask states of current context to stop, see shutdown
+pub fn stop() {
+    context::current().stop()
+}
+/// fuzzing of current context is over, main loops should stop pushing states and shutdown
+pub fn stopping() -> bool {
+    context::current().stopping()
+}
+
+/// thread of current context ( state, racer, server of module, .. ) to be joined at shutdown
+///
+/// - finished ones are dropped on the way, so only live ones are kept
+/// - thread should end once bananaq::stopping, otherwise it is left behind at shutdown
+pub fn adopt(handle: thread::JoinHandle<()>) {
+    if let Ok(mut threads) = context::current().threads().lock() {
+        threads.retain(|handle| !handle.is_finished());
+        threads.push(handle);
+    }
+}
+
+/// join adopted threads till deadline, returns how many of them are still running
+fn join(ctx: &context::FuzzerContext, start: Instant, deadline: Duration) -> usize {
+    loop {
+        let (done, left) = match ctx.threads().lock() {
+            Ok(mut threads) => {
+                let (done, running): (Vec<_>, Vec<_>) = threads
+                    .drain(..)
+                    .partition(|handle| handle.is_finished());
+                *threads = running;
+                (done, threads.len())
+            },
+            Err(_) => return 0,
+        };
+        for handle in done {
+            let _ = handle.join();
+        }
+        if 0 == left || start.elapsed() >= deadline {
+            return left
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// graceful end of fuzzing in current context
+///
+/// 1. stop : no new states, live ones close themselves by dtor at their next call
+/// 2. wait for states to leave queue, at most config.shutdown_timeout ms
+///     - hanged ones ( stuck in call ) are left behind, there is nothing we can do about them
+/// 3. observers are notified ( on_shutdown ), so modules flush their output
+/// 4. adopted threads are joined, till the same deadline ( see adopt )
+/// - once per context, concurrent callers wait for the first one
+/// - must not be called from observer callback ( queue is locked there ), spawn thread for it
+/// - returns how many states did not make it in time, None if shutdown was done by someone else
+pub fn shutdown() -> Option<usize> {
+    let ctx = context::current();
+    ctx.stop();
+    let deadline = Duration::from_millis(ctx.config().shutdown_timeout);
+    let start = Instant::now();
+    let mut left = None;
+    ctx.closing().call_once(|| {
+        while 0 != occupancy().states && start.elapsed() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if let Ok(banana) = ctx.queue().read() {
+            banana.shutdown_notify_safe()
+        }
+        left = Some(occupancy().states);
+        let threads = join(&ctx, start, deadline);
+        if 0 != threads {
+            println!("[bananaq] {} threads did not finish in time, left behind", threads);
+        }
+    });
+    left
+}
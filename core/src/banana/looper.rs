@@ -1,6 +1,7 @@
 use super::bananaq;
 use state::state::IFuzzyObj;
-use config::FZZCONFIG;
+use context;
+use rng;
 
 extern crate rand;
 use rand::Rng;
@@ -28,8 +29,16 @@ impl FuzzyState {
 /// - invoke fuzzy method
 /// - yield to allow other threads and fuzzing more shuffling ( better to swap exec time between threads a lot )
 /// - check for end-conditions of fuzz and quit
+///     - at shutdown ( bananaq::stopping ) state closes itself by dtor at its next do_fuzz_one
+/// - thread draws randomness from its own stream, by order of spawn ( see rng )
+/// - thread fuzzes in context of spawning thread ( see context )
     pub fn fuzz(istate: Box<dyn IFuzzyObj>) -> thread::JoinHandle<()> {
+        let stream = rng::spawn();
+        let ctx = context::current();
         thread::spawn(move || {
+            let config = ctx.config();
+            context::enter(ctx);
+            rng::init(stream);
             if !bananaq::push(&istate) {
                 return
             }
@@ -45,13 +54,13 @@ impl FuzzyState {
 
             bananaq::update(&fuzzy_state.istate);
 
-            if !racer {
+            if !racer && !bananaq::stopping() {
                 thread::sleep(time::Duration::from_millis(
-                    rand::thread_rng().gen_range(0..=FZZCONFIG.after_creation_sleep)));
+                    rng::thread_rng().gen_range(0..=config.after_creation_sleep)));
             }
 
             for i in 0u16.. {//ok we want panic if we overdo it, as 0xFFFF is not reasonable fuzzing for any object ..
-                if 0 == (i % FZZCONFIG.state_update_freq) {
+                if 0 == (i % config.state_update_freq) {
                     bananaq::update(&fuzzy_state.istate);
                 }
                 if !fuzzy_state.istate.fuzzy_loop() {
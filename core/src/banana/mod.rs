@@ -1,4 +1,4 @@
-mod queue;
+pub(crate) mod queue;
 pub mod bananaq;
 pub mod looper;
 pub mod observer;
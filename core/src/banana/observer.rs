@@ -1,4 +1,9 @@
+use std::time::Duration;
+
 use exec::call::Call;
+use exec::coverage::CallCoverage;
+use exec::fd_info::CallInfo;
+use exec::watchdog::Hang;
 use state::state::StateInfo;
 
 /// (pre) callback per (sys)-call
@@ -24,6 +29,20 @@ pub trait ICallObserver {
 ///         can solve it - manage signals, and sync at this level
 ///     - ...
     fn notify(&self, info: &StateInfo, call: &Call) -> bool;
+    /// (post) callback, call was executed and here is what it returned
+    ///
+    /// - result is actual outcome of this call ( success + extra_info ), unlike call.ok() at notify
+    /// - elapsed is time spent in ccall only, observers excluded
+    /// - invoked also for replayed calls
+    fn notify_post(&self, _info: &StateInfo, _call: &Call, _result: &CallInfo, _elapsed: Duration) {}
+    /// call did not return in time ( see exec::watchdog ), invoked from watchdog thread
+    ///
+    /// - hanged thread is still inside of call, therefore only its StateInfo and call id here
+    fn notify_hang(&self, _info: &StateInfo, _hang: &Hang) {}
+    /// call hit coverage nobody hit before ( see exec::coverage ), invoked right before notify_post
+    fn notify_coverage(&self, _info: &StateInfo, _call: &Call, _cov: &CallCoverage) {}
+    /// fuzzing is over, states are closed ( or left behind in hanged call ), flush what you have
+    fn on_shutdown(&self) {}
 }
 /// (pre) callback per state creation
 ///
@@ -36,5 +55,7 @@ pub trait IStateObserver {
     fn notify_ctor(&self, info: &StateInfo) -> bool;
     /// just to notify you, that fuzzing for this state is over
     fn notify_dtor(&self, info: &StateInfo);
+    /// fuzzing is over, states are closed ( or left behind in hanged call ), flush what you have
+    fn on_shutdown(&self) {}
 }
 
@@ -1,5 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 extern crate rand;
 use rand::seq::SliceRandom;
@@ -9,11 +14,30 @@ use super::observer::{
     IStateObserver,
 };
 use exec::call::Call;
-use exec::fd_info::Fd;
+use exec::coverage::CallCoverage;
+use exec::fd_info::{
+    CallInfo,
+    Fd,
+};
+use exec::watchdog::Hang;
 use state::id::StateTableId;
 use state::state::StateInfo;
 
-use config::FZZCONFIG;
+use config::FuzzyConfig;
+use rng;
+
+/// how full the queue is, for monitoring
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Occupancy {
+    /// states fuzzed right now, one thread per state
+    pub states: usize,
+    /// config.max_queue_size
+    pub max: usize,
+    /// states sharing fd with other state ( dups ), racing each other
+    pub racers: usize,
+    /// hanged states which slot was reclaimed, see exec::watchdog
+    pub reclaimed: usize,
+}
 
 /// central structure(queue) for fuzzing - internal fuzzer manager/banana
 ///
@@ -28,19 +52,27 @@ pub struct FuzzyQ {
     /// - duplicate resolving
     /// - callback forwarding
     states: HashMap< thread::ThreadId, StateInfo >,
+    /// threads of hanged states we freed slot for, see exec::watchdog
+    ///
+    /// - once such call returns, thread only closes its state and quit
+    reclaimed: HashSet< thread::ThreadId >,
     pub observers_state: Vec< Box<dyn IStateObserver> >,
     pub observers_call: Vec< Box<dyn ICallObserver> >,
+    /// config of context queue belongs to
+    config: Arc<FuzzyConfig>,
 }
 
 unsafe impl Send for FuzzyQ {}
 unsafe impl Sync for FuzzyQ {}
 
 impl FuzzyQ {
-    pub fn new() -> FuzzyQ {
+    pub fn new(config: Arc<FuzzyConfig>) -> FuzzyQ {
         FuzzyQ {
             states : HashMap::new(),
+            reclaimed : HashSet::new(),
             observers_state : Vec::new(),
             observers_call : Vec::new(),
+            config,
         }
     }
     /// certain calls want to intercorporate foreign state
@@ -54,27 +86,96 @@ impl FuzzyQ {
             .filter(|info| !info.1.fd.is_invalid())
 //            .inspect(|info| println!(".............{:?} -> {:X}", id, info.1.fd))
             .collect::<Vec<_>>()
-            .choose(&mut rand::thread_rng())
+            .choose(&mut rng::thread_rng())
         {
             Some(info) => info.1.fd.clone(),
             None => Fd::empty(),
         }
     }
 
+    pub fn occupancy_safe(&self) -> Occupancy {
+        Occupancy {
+            states : self.states.len(),
+            max : self.config.max_queue_size,
+            racers : self.states
+                .values()
+                .filter(|info| !info.fd.is_invalid())
+                .filter(|info| self.states
+                    .values()
+                    .filter(|other| other.fd.equals(&info.fd))
+                    .count() > 1)
+                .count(),
+            reclaimed : self.reclaimed.len(),
+        }
+    }
+
     /// call callback
+    ///
+    /// - reclaimed state is allowed only to close itself ( dtor ), modules does not know it anymore
     pub fn call_notify_safe<'a>(&self, call: &'a Call) -> bool {
-        let info = &self.states[&thread::current().id()];
+        let info = match self.states.get(&thread::current().id()) {
+            Some(info) => info,
+            None => return self.reclaimed.contains(&thread::current().id()) && call.is_dtor(),
+        };
         self.observers_call
             .iter()
             .all(|obs| obs.notify(info, call))
     }
+    /// call post-callback, with result of call
+    pub fn call_post_notify_safe(&self, call: &Call, result: &CallInfo, elapsed: Duration) {
+        let info = match self.states.get(&thread::current().id()) {
+            Some(info) => info,
+            None => return,//reclaimed
+        };
+        for obs in self.observers_call.iter() {
+            obs.notify_post(info, call, result, elapsed);
+        }
+    }
     /// state destruction callback
     pub fn dtor_notify_safe(&self) {
-        let info = &self.states[&thread::current().id()];
+        let info = match self.states.get(&thread::current().id()) {
+            Some(info) => info,
+            None => return,//reclaimed, notified already
+        };
         for obs in self.observers_state.iter() {
             obs.notify_dtor(info);
         }
     }
+    /// hang callback, from watchdog thread
+    pub fn hang_notify_safe(&self, thread: thread::ThreadId, hang: &Hang) -> Option<StateInfo> {
+        let info = self.states.get(&thread)?;
+        for obs in self.observers_call.iter() {
+            obs.notify_hang(info, hang);
+        }
+        Some(info.clone())
+    }
+    /// new coverage callback, in thread which did call
+    pub fn coverage_notify_safe(&self, call: &Call, cov: &CallCoverage) -> Option<StateInfo> {
+        let info = self.states.get(&thread::current().id())?;
+        for obs in self.observers_call.iter() {
+            obs.notify_coverage(info, call, cov);
+        }
+        Some(info.clone())
+    }
+    /// shutdown callback, state observers first as they saw states closing
+    pub fn shutdown_notify_safe(&self) {
+        for obs in self.observers_state.iter() {
+            obs.on_shutdown();
+        }
+        for obs in self.observers_call.iter() {
+            obs.on_shutdown();
+        }
+    }
+    /// free slot of hanged state for others, as if it was closed
+    pub fn reclaim_safe(&mut self, thread: thread::ThreadId) {
+        if let Some(info) = self.states.remove(&thread) {
+            for obs in self.observers_state.iter() {
+                obs.notify_dtor(&info);
+            }
+            self.reclaimed.insert(thread);
+        }
+    }
+
     /// state creation callback
     ///
     /// - checking dups ( same state already in queue - limit from config -> how many to allow )
@@ -83,7 +184,7 @@ impl FuzzyQ {
             .iter()
             .filter(|&(_, ref state)| (state.fd.equals(&info.fd) && (state.id.clone() & info.id.clone())))
             .count();
-        if dups > FZZCONFIG.max_racers_count {
+        if dups > self.config.max_racers_count {
             return false
         }
         self.observers_state
@@ -99,7 +200,7 @@ impl FuzzyQ {
             .count();
 
         // forcing at least 1 object of its kind in queue is not necessary what we want, limit config expresivness
-        if self.states.len() > FZZCONFIG.max_queue_size {
+        if self.states.len() > self.config.max_queue_size {
             return false//0 != same_kind
         }
 
@@ -110,7 +211,7 @@ impl FuzzyQ {
         //ok seems strict check on all siblings is preferable!!
        
         // well rust, overflows are handled, kind of overkill geting here overlow checks - implmenting fuzzer not OS
-        if same_kind * FZZCONFIG.ratio > FZZCONFIG.max_queue_size * 1 {
+        if same_kind * self.config.ratio > self.config.max_queue_size * 1 {
             return false
         }
 
@@ -122,6 +223,9 @@ impl FuzzyQ {
         true
     }
     pub fn pop_safe(&mut self) {
+        if self.reclaimed.remove(&thread::current().id()) {
+            return
+        }
         if !self.states.contains_key(&thread::current().id()) {
             panic!("trying to pop from same thread twice++ or from different thread at all");
         }
@@ -130,6 +234,9 @@ impl FuzzyQ {
     pub fn update_safe(&mut self, fuzzy_info: StateInfo) {
         // here we maybe want to double check how many same "fd" are in queue, and limit it by config
         // but i did not encounter issue with this, so i am letting this pass void
+        if self.reclaimed.contains(&thread::current().id()) {
+            return
+        }
         assert!(self.states.contains_key(&thread::current().id()));
         if let Some(info) = self.states.get_mut(&thread::current().id()) {
             *info = fuzzy_info;
@@ -0,0 +1,499 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use toml;
+use toml::Value;
+use toml::value::Table;
+
+use super::{
+    FuzzyConfig,
+    CONFIG_VERSION,
+};
+use super::validate;
+
+/// environment variable with path to config.toml, when it is not given on command line
+pub const CONFIG_ENV: &str = "BANANA_CONFIG";
+/// config used when neither command line nor environment says otherwise; optional, defaults are
+/// used without it
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// field out of range, or not making sense together with other one
+#[derive(Clone, Debug)]
+pub struct Invalid {
+    /// dotted path of field : max_queue_size, coverage.map_size, ..
+    pub key: String,
+    pub message: String,
+    /// layer which set key : file, override, defaults; filled by load
+    pub source: String,
+}
+
+impl Invalid {
+    pub fn new(key: &str, message: String) -> Invalid {
+        Invalid {
+            key : key.to_string(),
+            message,
+            source : String::new(),
+        }
+    }
+}
+
+/// why config can not be used, key is dotted path of field
+#[derive(Debug)]
+pub enum ConfigError {
+    /// file can not be read
+    Io { path: String, error: io::Error },
+    /// not toml, or value of wrong type / missing field ( message says for which key )
+    Parse { source: String, message: String },
+    /// field config does not know, likely typo; source is layer which set it
+    Unknown { key: String, source: String },
+    /// version there is no migration from ( newer one, or garbage )
+    Version { found: String, supported: String },
+    /// fields out of range or not making sense together, all of them
+    Invalid(Vec<Invalid>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io { ref path, ref error } => write!(f, "{} : {}", path, error),
+            ConfigError::Parse { ref source, ref message } => write!(f, "{} : {}", source, message),
+            ConfigError::Unknown { ref key, ref source } => write!(f, "{} : unknown field {}", source, key),
+            ConfigError::Version { ref found, ref supported } =>
+                write!(f, "version {} is not supported, nor can be migrated to {}", found, supported),
+            ConfigError::Invalid(ref invalid) => {
+                for (ind, invalid) in invalid.iter().enumerate() {
+                    if 0 != ind {
+                        writeln!(f)?;
+                    }
+                    if !invalid.source.is_empty() {
+                        write!(f, "{} : ", invalid.source)?;
+                    }
+                    write!(f, "{} : {}", invalid.key, invalid.message)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// for callers living in io::Error world, as rest of fuzzer does
+impl From<ConfigError> for io::Error {
+    fn from(e: ConfigError) -> io::Error {
+        match e {
+            ConfigError::Io { error, .. } => error,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+/// field of config set from command line ( or wherever ), applied after file
+#[derive(Clone, Debug)]
+pub struct Override {
+    /// dotted path of field : max_queue_size, coverage.source, ..
+    pub path: String,
+    /// toml value, strings can go without quotes : 20, true, kcov, "kcov"
+    pub value: String,
+}
+
+impl Override {
+    /// path=value
+    pub fn parse(arg: &str) -> Result<Override, ConfigError> {
+        match arg.find('=') {
+            Some(ind) => Ok(Override::new(&arg[..ind], &arg[ind + 1..])),
+            None => Err(ConfigError::Parse {
+                source : String::from("override"),
+                message : format!("{} is not path=value", arg),
+            }),
+        }
+    }
+    /// path can be written as command line flag : max-queue-size == max_queue_size
+    pub fn new(path: &str, value: &str) -> Override {
+        Override {
+            path : path.trim().replace('-', "_"),
+            value : value.trim().to_string(),
+        }
+    }
+
+    fn value(&self) -> Value {
+        match toml::from_str::<Value>(&format!("value = {}", self.value)) {
+            Ok(Value::Table(mut table)) => table.remove("value").unwrap_or_else(|| Value::String(self.value.clone())),
+            _ => Value::String(self.value.clone()),
+        }
+    }
+
+    /// set value in config table, tables on the way are created ( [coverage] missing in file f.e. )
+    fn apply(&self, root: &mut Value) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError::Parse {
+            source : String::from("override"),
+            message : format!("{} : {}", self.path, message),
+        };
+        let mut keys = self.path.split('.').collect::< Vec<&str> >();
+        let last = match keys.pop() {
+            Some(last) if !last.is_empty() => last,
+            _ => return Err(invalid(String::from("empty path"))),
+        };
+        let mut table = root;
+        for key in keys {
+            let next = match table {
+                Value::Table(table) => table
+                    .entry(key.to_string())
+                    .or_insert_with(|| Value::Table(Table::new())),
+                _ => return Err(invalid(format!("{} is not a table", key))),
+            };
+            table = next;
+        }
+        match table {
+            Value::Table(table) => { table.insert(last.to_string(), self.value()); },
+            _ => return Err(invalid(format!("parent of {} is not a table", last))),
+        }
+        Ok(())
+    }
+}
+
+/// upgrade of config table from one version to next one
+struct Migration {
+    from: &'static str,
+    to: &'static str,
+    migrate: fn(&mut Table),
+}
+
+/// 0.2 : optional tables ( forkserver, watchdog, coverage, .. ), seed and shutdown_timeout came in,
+/// all of them with defaults, so there is nothing to move
+fn v0_2(_: &mut Table) {}
+
+/// in order, every version config.toml had is from of one of them
+const MIGRATIONS: &[Migration] = &[
+    Migration { from : "0.1", to : "0.2", migrate : v0_2 },
+];
+
+/// dotted version older than other one, garbage is never older
+fn older(version: &str, than: &str) -> bool {
+    let parse = |version: &str| version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::< Result<Vec<u64>, _> >();
+    match (parse(version), parse(than)) {
+        (Ok(version), Ok(than)) => version < than,
+        _ => false,
+    }
+}
+
+/// bring config table of older version up to CONFIG_VERSION, missing version == current one
+///
+/// - version older than any we know ( 0.0.x, 0.1.x, .. ) is taken as oldest one, there was nothing
+///   before 0.1 to differ from it
+/// - newer version or garbage is refused
+fn migrate(table: &mut Table) -> Result<(), ConfigError> {
+    let mut version = match table.get("version") {
+        Some(Value::String(version)) => version.clone(),
+        Some(version) => return Err(ConfigError::Version {
+            found : version.to_string(),
+            supported : CONFIG_VERSION.to_string(),
+        }),
+        None => CONFIG_VERSION.to_string(),
+    };
+    if older(&version, CONFIG_VERSION) && !MIGRATIONS.iter().any(|migration| migration.from == version) {
+        version = MIGRATIONS[0].from.to_string();
+    }
+    while CONFIG_VERSION != version {
+        let migration = match MIGRATIONS.iter().find(|migration| migration.from == version) {
+            Some(migration) => migration,
+            None => return Err(ConfigError::Version {
+                found : version,
+                supported : CONFIG_VERSION.to_string(),
+            }),
+        };
+        (migration.migrate)(table);
+        version = migration.to.to_string();
+    }
+    table.insert(String::from("version"), Value::String(version));
+    Ok(())
+}
+
+/// upper layer over lower one, tables are merged key by key, anything else is replaced
+fn merge(lower: &mut Value, upper: Value) {
+    match (lower, upper) {
+        (Value::Table(lower), Value::Table(upper)) => {
+            for (key, value) in upper {
+                match lower.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => { lower.insert(key, value); },
+                }
+            }
+        },
+        (lower, upper) => *lower = upper,
+    }
+}
+
+/// first key of given table which config did not take, so it is not silently ignored
+fn unknown(given: &Value, taken: &Value, path: &str) -> Option<String> {
+    let key = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (given, taken) {
+        (Value::Table(given), Value::Table(taken)) => given
+            .iter()
+            .filter_map(|(name, value)| match taken.get(name) {
+                Some(taken) => unknown(value, taken, &key(name)),
+                None => Some(key(name)),
+            })
+            .next(),
+        (Value::Array(given), Value::Array(taken)) => given
+            .iter()
+            .zip(taken.iter())
+            .enumerate()
+            .filter_map(|(ind, (given, taken))| unknown(given, taken, &key(&ind.to_string())))
+            .next(),
+        _ => None,
+    }
+}
+
+/// toml file as table, or straight as its type
+fn read<T: DeserializeOwned>(path: &str) -> Result<T, ConfigError> {
+    let data = fs::read_to_string(path).map_err(|error| ConfigError::Io { path : path.to_string(), error })?;
+    toml::from_str(&data).map_err(|e| ConfigError::Parse { source : path.to_string(), message : e.to_string() })
+}
+
+/// any toml file ( modules.toml, .. ) to its type, without panic on typo
+///
+/// - parsed straight from text, toml::Value can not hold enums with data ( oracle = { Log = .. } f.e. )
+pub fn parse_file<T: DeserializeOwned>(path: &str) -> Result<T, ConfigError> {
+    read(path)
+}
+
+/// config file to use : given one, or $BANANA_CONFIG, or config.toml if there is one
+///
+/// - given and environment one must exist, implicit config.toml not
+pub fn locate(path: Option<&str>) -> Option<String> {
+    match path {
+        Some(path) => Some(path.to_string()),
+        None => match env::var(CONFIG_ENV) {
+            Ok(path) => Some(path),
+            Err(_) if Path::new(CONFIG_FILE).exists() => Some(CONFIG_FILE.to_string()),
+            Err(_) => None,
+        },
+    }
+}
+
+/// value at dotted path of table, if it is there
+fn lookup<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(root, |value, key| match value {
+        Value::Table(table) => table.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// layer which supplied key ( dotted path ) : last override setting it, file, override creating it, or defaults
+fn blame(key: &str, file: &Value, source: &str, overrides: &[Override]) -> String {
+    let over = |set: &dyn Fn(&str) -> bool| overrides
+        .iter()
+        .rev()
+        .find(|over| set(&over.path))
+        .map(|over| format!("override {}={}", over.path, over.value));
+    if let Some(over) = over(&|path| path == key || key.starts_with(&(path.to_string() + "."))) {
+        return over
+    }
+    if lookup(file, key).is_some() {
+        return source.to_string()
+    }
+    over(&|path| path.starts_with(&(key.to_string() + "."))).unwrap_or_else(|| String::from("defaults"))
+}
+
+/// key serde error is about, toml puts it at the end : .. for key `coverage.map_size`
+fn error_key(message: &str) -> Option<&str> {
+    let from = message.rfind("for key `")? + "for key `".len();
+    let len = message[from..].find('`')?;
+    Some(&message[from..from + len])
+}
+
+/// defaults with given layer over them, as config
+fn layered(defaults: &Value, given: &Value) -> Result<FuzzyConfig, String> {
+    let mut root = defaults.clone();
+    merge(&mut root, given.clone());
+    root
+        .try_into()
+        .map_err(|e: toml::de::Error| e.to_string())
+}
+
+/// config by layers : defaults < file < overrides
+///
+/// - file is migrated to current version first
+/// - overrides are applied before anything is parsed, so they can fix bad value of file
+/// - every problem is blamed on layer which supplied offending key ( see blame )
+/// - unknown fields are refused, not ignored, so typo does not go unnoticed
+/// - validated at the end ( see config::validate ), all problems reported at once
+pub fn load(path: Option<&str>, overrides: &[Override]) -> Result<FuzzyConfig, ConfigError> {
+    let defaults = Value::try_from(FuzzyConfig::default())
+        .map_err(|e| ConfigError::Parse { source : String::from("defaults"), message : e.to_string() })?;
+    let mut file = Value::Table(Table::new());
+
+    let path = locate(path);
+    if let Some(ref path) = path {
+        let mut table = match read::<Value>(path)? {
+            Value::Table(table) => table,
+            _ => return Err(ConfigError::Parse { source : path.clone(), message : String::from("not a table") }),
+        };
+        migrate(&mut table)?;
+        merge(&mut file, Value::Table(table));
+    }
+    let source = path.unwrap_or_else(|| String::from("defaults"));
+    let mut given = file.clone();
+    for over in overrides.iter() {
+        over.apply(&mut given)?;
+    }
+
+    let config = layered(&defaults, &given).map_err(|message| {
+        let source = match error_key(&message) {
+            Some(key) => blame(key, &file, &source, overrides),
+            // no key to follow, file alone is either fine ( so override broke it ) or it is to blame
+            None if !overrides.is_empty() && layered(&defaults, &file).is_ok() => String::from("override"),
+            None => source.clone(),
+        };
+        ConfigError::Parse { source, message }
+    })?;
+    let taken = Value::try_from(&config)
+        .map_err(|e| ConfigError::Parse { source : source.clone(), message : e.to_string() })?;
+    if let Some(key) = unknown(&given, &taken, "") {
+        let source = blame(&key, &file, &source, overrides);
+        return Err(ConfigError::Unknown { key, source })
+    }
+
+    let mut invalid = validate::check(&config);
+    if !invalid.is_empty() {
+        for invalid in invalid.iter_mut() {
+            invalid.source = blame(&invalid.key, &file, &source, overrides);
+        }
+        return Err(ConfigError::Invalid(invalid))
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    use config::CoverageSource;
+
+    /// config file of test, unique per process + name so tests can run in parallel
+    fn file(name: &str, data: &str) -> String {
+        let path = env::temp_dir().join(format!("banana-config-{}-{}.toml", process::id(), name));
+        fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn table(data: &str) -> Table {
+        match toml::from_str::<Value>(data).unwrap() {
+            Value::Table(table) => table,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn migrate_older_versions() {
+        for version in ["0.1", "0.0.3", "0.1.7"] {
+            let mut old = table(&format!("version = \"{}\"\nseed = 1", version));
+            migrate(&mut old).unwrap();
+            assert_eq!(Some(&Value::String(CONFIG_VERSION.to_string())), old.get("version"), "{}", version);
+            assert_eq!(Some(&Value::Integer(1)), old.get("seed"));
+        }
+        let mut none = Table::new();
+        migrate(&mut none).unwrap();
+        assert_eq!(Some(&Value::String(CONFIG_VERSION.to_string())), none.get("version"));
+
+        for version in ["version = \"9.0\"", "version = \"garbage\"", "version = 2"] {
+            match migrate(&mut table(version)) {
+                Err(ConfigError::Version { .. }) => (),
+                other => panic!("{} : {:?}", version, other),
+            }
+        }
+
+        let path = file("migrate", "version = \"0.1\"\nmax_queue_size = 20\n");
+        let config = load(Some(&path), &[]).unwrap();
+        assert_eq!((CONFIG_VERSION, 20), (config.version.as_str(), config.max_queue_size));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        let path = file("unknown", "max_queue_size = 20\n[coverage]\nsource = \"kcov\"\nmap_sise = 64\n");
+        match load(Some(&path), &[]) {
+            Err(ConfigError::Unknown { ref key, ref source }) => assert_eq!(("coverage.map_sise", &path), (key.as_str(), source)),
+            other => panic!("{:?}", other),
+        }
+        let config = load(Some(&path), &[Override::new("coverage.map_sise", "1")]);
+        assert!(matches!(config, Err(ConfigError::Unknown { ref source, .. }) if "override coverage.map_sise=1" == source));
+        let _ = fs::remove_file(path);
+
+        let path = file("unknown-override", "max_queue_size = 20\n");
+        match load(Some(&path), &[Override::new("max-queue-sise", "3")]) {
+            Err(ConfigError::Unknown { ref key, ref source }) => assert_eq!(("max_queue_sise", "override max_queue_sise=3"), (key.as_str(), source.as_str())),
+            other => panic!("{:?}", other),
+        }
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn override_rescues_file_and_takes_blame() {
+        let path = file("blame", "max_queue_size = \"many\"\n[coverage]\nsource = \"kcov\"\n");
+        match load(Some(&path), &[]) {
+            Err(ConfigError::Parse { ref source, ref message }) => {
+                assert_eq!(&path, source);
+                assert!(message.contains("max_queue_size"), "{}", message);
+            },
+            other => panic!("{:?}", other),
+        }
+
+        let config = load(Some(&path), &[Override::new("max-queue-size", "20"), Override::new("coverage.map_size", "64")]).unwrap();
+        assert_eq!(20, config.max_queue_size);
+        let coverage = config.coverage.unwrap();
+        assert_eq!((CoverageSource::Kcov, 64), (coverage.source, coverage.map_size));
+
+        match load(Some(&path), &[Override::new("max_queue_size", "20"), Override::new("coverage.map_size", "x")]) {
+            Err(ConfigError::Parse { ref source, .. }) => assert_eq!("override coverage.map_size=x", source),
+            other => panic!("{:?}", other),
+        }
+        match load(Some(&path), &[Override::new("max_queue_size", "0")]) {
+            Err(ConfigError::Invalid(ref invalid)) => {
+                let blamed = invalid.iter().find(|invalid| "max_queue_size" == invalid.key).unwrap();
+                assert_eq!("override max_queue_size=0", blamed.source);
+            },
+            other => panic!("{:?}", other),
+        }
+        let _ = fs::remove_file(path);
+
+        let path = file("blame-file", "[corpus]\ndir = \"\"\n");
+        match load(Some(&path), &[Override::new("seed", "1")]) {
+            Err(ConfigError::Invalid(ref invalid)) => assert_eq!(("corpus.dir", &path), (invalid[0].key.as_str(), &invalid[0].source)),
+            other => panic!("{:?}", other),
+        }
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn overrides() {
+        let over = Override::parse(" max-queue-size = 20 ").unwrap();
+        assert_eq!(("max_queue_size", "20"), (over.path.as_str(), over.value.as_str()));
+        assert!(Override::parse("no value").is_err());
+
+        let mut root = Value::Table(Table::new());
+        Override::new("coverage.source", "kcov").apply(&mut root).unwrap();
+        Override::new("coverage.edges", "true").apply(&mut root).unwrap();
+        Override::new("noisy", "\"quoted\"").apply(&mut root).unwrap();
+        assert_eq!(Some(&Value::String(String::from("kcov"))), lookup(&root, "coverage.source"));
+        assert_eq!(Some(&Value::Boolean(true)), lookup(&root, "coverage.edges"));
+        assert_eq!(Some(&Value::String(String::from("quoted"))), lookup(&root, "noisy"));
+        assert!(Override::new("noisy.deeper", "1").apply(&mut root).is_err());
+        assert!(Override::new("coverage.", "1").apply(&mut root).is_err());
+
+        assert_eq!(Some("coverage.map_size"), error_key("invalid type: string \"x\", expected usize for key `coverage.map_size`"));
+        assert_eq!(None, error_key("missing field `source`"));
+    }
+}
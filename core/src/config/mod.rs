@@ -0,0 +1,291 @@
+use std::io;
+
+pub mod load;
+pub mod validate;
+
+pub use self::load::{
+    load,
+    locate,
+    parse_file,
+    ConfigError,
+    Invalid,
+    Override,
+};
+
+/// version of config.toml this core understands, older ones are migrated at load
+pub const CONFIG_VERSION: &str = "0.2";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FuzzyConfig {
+    pub version: String,
+    pub noisy: bool,
+    pub dead_call: usize,
+    pub state_update_freq: u16,
+    pub max_racers_count: usize,
+    pub max_queue_size: usize,
+    pub singlethread: bool,
+    pub active_seconds: u64,
+    pub push_sleep: u64,
+    pub new_limit: usize,
+    pub dup_limit: usize,
+    pub ratio: usize,
+    pub after_creation_sleep: u64,
+    pub push_count: u64,
+    pub rnd_data_to_pattern: bool,
+    /// ms for live states to close themselves ( dtor ) at shutdown, see bananaq::shutdown
+    #[serde(default = "shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    /// master seed of all random decisions, see rng; missing == random one, recorded to traces anyway
+    ///
+    /// - written as hex string ( seed = "0x.." ), see seed
+    #[serde(default, with = "seed")]
+    pub seed: Option<u64>,
+    /// run fuzzing in forked worker, see exec::forkserver; missing table == in-process fuzzing
+    #[serde(default)]
+    pub forkserver: Option<ForkServerConfig>,
+    /// hang detection of calls, see exec::watchdog; missing table == no watchdog
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfig>,
+    /// coverage feedback of calls, see exec::coverage; missing table == no coverage
+    #[serde(default)]
+    pub coverage: Option<CoverageConfig>,
+    /// how State picks call from current group, see state::select; missing table == uniform
+    #[serde(default)]
+    pub selection: Option<SelectionConfig>,
+    /// interesting states saved across runs + replayed as prefix, see poc::corpus; missing table == no corpus
+    #[serde(default)]
+    pub corpus: Option<CorpusConfig>,
+    /// mutate arguments which worked before instead of generating new ones, see Call::do_call; missing table == always generate
+    #[serde(default)]
+    pub mutation: Option<MutationConfig>,
+}
+
+fn shutdown_timeout() -> u64 { 5000 }
+
+/// seed is u64, but toml integers are i64, so half of seeds would not fit in
+///
+/// - written as hex string : seed = "0xdeadbeef"
+/// - read also as decimal string ( as traces record it ), or integer ( negative one as its u64 bits )
+mod seed {
+    use std::fmt;
+
+    use serde::{Deserializer, Serializer};
+    use serde::de::{self, Visitor};
+
+    pub fn serialize<S: Serializer>(seed: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *seed {
+            Some(seed) => serializer.serialize_str(&format!("{:#x}", seed)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        deserializer.deserialize_option(Seed)
+    }
+
+    struct Seed;
+
+    impl<'de> Visitor<'de> for Seed {
+        type Value = Option<u64>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "seed as \"0x..\" hex string, decimal string or integer")
+        }
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> { Ok(None) }
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+        fn visit_i64<E: de::Error>(self, seed: i64) -> Result<Self::Value, E> { Ok(Some(seed as u64)) }
+        fn visit_u64<E: de::Error>(self, seed: u64) -> Result<Self::Value, E> { Ok(Some(seed)) }
+        fn visit_str<E: de::Error>(self, seed: &str) -> Result<Self::Value, E> {
+            let seed = seed.trim();
+            let parsed = match seed.strip_prefix("0x").or_else(|| seed.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => seed.parse::<u64>(),
+            };
+            parsed
+                .map(Some)
+                .map_err(|e| E::custom(format!("seed {} : {}", seed, e)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ForkServerConfig {
+    /// ms without any call finished, after that worker is considered hanged and killed; 0 = no limit
+    ///
+    /// - count with sleeps ( after_creation_sleep, sleeper module, .. ) when setting it
+    #[serde(default)]
+    pub hang_timeout: u64,
+    /// where to store crashes, one folder per crash
+    pub crash_dir: String,
+    /// trace worker records ( poclog module ), context of crash is taken from it
+    #[serde(default)]
+    pub trace: String,
+    /// how many last recorded calls per thread to keep with crash
+    pub context: usize,
+    /// stop after so many crashes, 0 = never
+    #[serde(default)]
+    pub max_restarts: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WatchdogConfig {
+    /// ms for any call to finish, 0 = watch only calls listed in calls
+    pub timeout: u64,
+    /// per call timeouts, override global one
+    #[serde(default)]
+    pub calls: Vec<CallTimeout>,
+    /// where hangs are recorded ( json lines ), empty = observers are only notified
+    #[serde(default)]
+    pub findings: String,
+    /// free queue slot of hanged state, so zombies do not block fuzzing of new states
+    #[serde(default)]
+    pub reclaim: bool,
+    /// exit forkserver worker on hang, controller will restart it fresh
+    #[serde(default)]
+    pub restart: bool,
+}
+
+/// where blocks hit by calls come from
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageSource {
+    /// linux kernel, per thread /sys/kernel/debug/kcov
+    Kcov,
+    /// in-process library built with -fsanitize-coverage=trace-pc-guard, counters in shared memory
+    Sancov,
+    /// external tracer writing blocks to file
+    File,
+}
+
+fn map_size() -> usize { 1 << 16 }
+fn edges() -> bool { true }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoverageConfig {
+    pub source: CoverageSource,
+    /// bitmap size, in bits; for sancov also number of guard counters
+    #[serde(default = "map_size")]
+    pub map_size: usize,
+    /// edges ( block + its predecessor ) instead of blocks, makes sense only for ordered sources
+    #[serde(default = "edges")]
+    pub edges: bool,
+    /// sancov : shared memory file of counters ( /dev/shm/.. ), file : file tracer writes to
+    #[serde(default)]
+    pub path: String,
+    /// where new coverage is recorded ( json lines ), empty = observers are only notified
+    #[serde(default)]
+    pub findings: String,
+}
+
+/// feedback used to bias selection of calls in group
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// every alive call same chance
+    Uniform,
+    /// weighted by success ratio of call
+    Success,
+    /// weighted by new coverage call found, needs [coverage]
+    Novelty,
+    /// UCB1, rewarded by success + new coverage
+    Bandit,
+}
+
+fn explore() -> f64 { 0.1 }
+fn factor() -> f64 { 2f64.sqrt() }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SelectionConfig {
+    pub strategy: SelectionStrategy,
+    /// probability of uniform pick instead of strategy one, so rarely picked calls are not starved
+    #[serde(default = "explore")]
+    pub explore: f64,
+    /// bandit : exploration factor of UCB1
+    #[serde(default = "factor")]
+    pub factor: f64,
+}
+
+fn replay_ratio() -> f64 { 0.5 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CorpusConfig {
+    /// folder of entries : trace ( .jsonl ) + metadata ( .json ) per entry
+    pub dir: String,
+    /// fraction of new states which replay corpus entry of same state as prefix, before fuzzing
+    #[serde(default = "replay_ratio")]
+    pub replay_ratio: f64,
+}
+
+fn mutate() -> f64 { 0.5 }
+fn splice() -> f64 { 0.1 }
+fn pool_size() -> usize { 32 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MutationConfig {
+    /// probability of mutating pooled arguments of call instead of generating them
+    #[serde(default = "mutate")]
+    pub probability: f64,
+    /// probability of splicing two pool entries before mutation
+    #[serde(default = "splice")]
+    pub splice: f64,
+    /// arguments which led to success or new coverage kept per call, random one replaced once full
+    #[serde(default = "pool_size")]
+    pub pool_size: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CallTimeout {
+    /// CallTableId::Id
+    pub id: u64,
+    /// ms
+    pub timeout: u64,
+}
+
+/// bottom layer of config::load, file needs to set only what differs
+impl Default for FuzzyConfig {
+    fn default() -> FuzzyConfig {
+        FuzzyConfig {
+            version : CONFIG_VERSION.to_string(),
+            noisy : false,
+            dead_call : 100,
+            state_update_freq : 10,
+            max_racers_count : 4,
+            max_queue_size : 10,
+            singlethread : false,
+            active_seconds : 0,
+            push_sleep : 100,
+            new_limit : 1000,
+            dup_limit : 1000,
+            ratio : 1,
+            after_creation_sleep : 0,
+            push_count : 1,
+            rnd_data_to_pattern : false,
+            shutdown_timeout : shutdown_timeout(),
+            seed : None,
+            forkserver : None,
+            watchdog : None,
+            coverage : None,
+            selection : None,
+            corpus : None,
+            mutation : None,
+        }
+    }
+}
+
+lazy_static! {
+    /// config.toml, compatibility default of context::FuzzerContext - prefer context::config()
+    static ref FZZCONFIG: Result<FuzzyConfig, String> = load(None, &[]).map_err(|e| e.to_string());
+}
+
+/// $BANANA_CONFIG or config.toml, loaded once for default context
+///
+/// - fuzzing from VM reading config from .iso : BANANA_CONFIG=e:/config.toml
+/// - broken one is error, not panic : entry points report it, default context does not fuzz with it
+pub fn fzzconfig() -> Result<&'static FuzzyConfig, io::Error> {
+    match *FZZCONFIG {
+        Ok(ref config) => Ok(config),
+        Err(ref e) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("config problem! {}", e))),
+    }
+}
@@ -0,0 +1,73 @@
+use super::{
+    CoverageSource,
+    FuzzyConfig,
+};
+use super::load::Invalid;
+
+fn probability(invalid: &mut Vec<Invalid>, key: &str, value: f64) {
+    if !(0.0..=1.0).contains(&value) {
+        invalid.push(Invalid::new(key, format!("{} is not probability ( 0.0 .. 1.0 )", value)))
+    }
+}
+
+fn positive(invalid: &mut Vec<Invalid>, key: &str, value: u64, why: &str) {
+    if 0 == value {
+        invalid.push(Invalid::new(key, format!("must be > 0, {}", why)))
+    }
+}
+
+/// ranges + cross-field invariants core relies on, empty if config is fine
+///
+/// - checked by config::load, so what reaches core does not divide by zero or lock queue out
+pub fn check(config: &FuzzyConfig) -> Vec<Invalid> {
+    let mut invalid = Vec::new();
+
+    positive(&mut invalid, "state_update_freq", config.state_update_freq as u64, "queue is updated every n-th loop");
+    positive(&mut invalid, "max_queue_size", config.max_queue_size as u64, "no state would be fuzzed");
+    positive(&mut invalid, "push_count", config.push_count, "no state would be pushed");
+    positive(&mut invalid, "new_limit", config.new_limit as u64, "new states would not make any call");
+    positive(&mut invalid, "dup_limit", config.dup_limit as u64, "duped states would not make any call");
+    positive(&mut invalid, "dead_call", config.dead_call as u64, "every call would be dead after first fail");
+    if config.ratio > config.max_queue_size {
+        invalid.push(Invalid::new("ratio", format!(
+            "{} > max_queue_size {}, second state of same kind would never make it to queue",
+            config.ratio, config.max_queue_size)))
+    }
+
+    if let Some(ref cfg) = config.forkserver {
+        if cfg.crash_dir.is_empty() {
+            invalid.push(Invalid::new("forkserver.crash_dir", String::from("empty, crashes would be lost")))
+        }
+    }
+    if let Some(ref cfg) = config.watchdog {
+        if 0 == cfg.timeout && cfg.calls.is_empty() {
+            invalid.push(Invalid::new("watchdog.timeout", String::from("0 and no calls listed, nothing would be watched")))
+        }
+        if cfg.restart && config.forkserver.is_none() {
+            invalid.push(Invalid::new("watchdog.restart", String::from("needs [forkserver], there is nothing to restart otherwise")))
+        }
+    }
+    if let Some(ref cfg) = config.coverage {
+        positive(&mut invalid, "coverage.map_size", cfg.map_size as u64, "bitmap would be empty");
+        if CoverageSource::Kcov != cfg.source && cfg.path.is_empty() {
+            invalid.push(Invalid::new("coverage.path", format!("needed by {:?} source", cfg.source)))
+        }
+    }
+    if let Some(ref cfg) = config.selection {
+        probability(&mut invalid, "selection.explore", cfg.explore);
+        if cfg.factor < 0.0 {
+            invalid.push(Invalid::new("selection.factor", format!("{} < 0, bandit would avoid exploring", cfg.factor)))
+        }
+    }
+    if let Some(ref cfg) = config.corpus {
+        if cfg.dir.is_empty() {
+            invalid.push(Invalid::new("corpus.dir", String::from("empty")))
+        }
+        probability(&mut invalid, "corpus.replay_ratio", cfg.replay_ratio);
+    }
+    if let Some(ref cfg) = config.mutation {
+        probability(&mut invalid, "mutation.probability", cfg.probability);
+        probability(&mut invalid, "mutation.splice", cfg.splice);
+    }
+    invalid
+}
@@ -0,0 +1,257 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::thread::{
+    JoinHandle,
+    ThreadId,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    Once,
+    RwLock,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    AtomicU64,
+    Ordering,
+};
+
+extern crate rand;
+
+use banana::queue::FuzzyQ;
+use config::{
+    self,
+    FuzzyConfig,
+};
+use exec::coverage;
+use exec::id::CallTableId;
+use exec::watchdog;
+use poc::corpus;
+use poc::replay;
+
+/// one fuzzing session : its config, queue of states ( + observers attached to it ) and
+/// single-thread lock of calls
+///
+/// - current context is per thread, FuzzyState::fuzz hands it over to thread of state
+/// - threads without context use default one, built from config.toml ( FZZCONFIG ), as before
+/// - more sessions ( tests, tools embedding fuzzer ) can live in one process, each with its own
+///   context : master seed of rng, replay plans, corpus, coverage bitmap and watchdog included
+pub struct FuzzerContext {
+    /// identity of context, for thread locals which need to know if they belong to it ( rng )
+    id: u64,
+    config: Arc<FuzzyConfig>,
+    queue: RwLock<FuzzyQ>,
+    sync: Mutex<CallTableId>,
+    /// fuzzing is over, states should close themselves
+    stop: AtomicBool,
+    /// shutdown sequence, once per context
+    closing: Once,
+    /// threads of session ( states, racers, servers of modules, .. ), joined at shutdown
+    threads: Mutex< Vec< JoinHandle<()> > >,
+    /// master seed of session, by config or random one ( recorded to traces, so run can be repeated )
+    seed: u64,
+    /// rng streams handed out so far, main thread has 0
+    streams: AtomicU64,
+    /// plans + fd map + turnstile of replayed trace, see poc::replay
+    replay: replay::Session,
+    /// entries of corpus, loaded once first state asks for it, see poc::corpus
+    corpus: Mutex< Option<corpus::Corpus> >,
+    /// bitmap of everything hit so far + who found it, see exec::coverage
+    coverage: Mutex<coverage::Map>,
+    /// calls watched by watchdog, see exec::watchdog
+    inflight: Mutex< HashMap<ThreadId, watchdog::Inflight> >,
+    /// watchdog thread of session, started by first watched call
+    scanner: Once,
+}
+
+/// contexts created so far, source of FuzzerContext::id
+static CONTEXTS: AtomicU64 = AtomicU64::new(0);
+
+impl FuzzerContext {
+    pub fn new(config: FuzzyConfig) -> Arc<FuzzerContext> {
+        let config = Arc::new(config);
+        Arc::new(FuzzerContext {
+            id : CONTEXTS.fetch_add(1, Ordering::SeqCst),
+            queue : RwLock::new(FuzzyQ::new(config.clone())),
+            sync : Mutex::new(CallTableId::Id(0)),
+            stop : AtomicBool::new(false),
+            closing : Once::new(),
+            threads : Mutex::new(Vec::new()),
+            seed : config.seed.unwrap_or_else(rand::random),
+            streams : AtomicU64::new(1),
+            replay : replay::Session::new(),
+            corpus : Mutex::new(None),
+            coverage : Mutex::new(coverage::Map::new(config.coverage.as_ref().map_or(0, |cfg| cfg.map_size.max(1)))),
+            inflight : Mutex::new(HashMap::new()),
+            scanner : Once::new(),
+            config,
+        })
+    }
+
+    pub fn config(&self) -> Arc<FuzzyConfig> {
+        self.config.clone()
+    }
+    /// queue of states fuzzed in this session, see banana::bananaq
+    pub(crate) fn queue(&self) -> &RwLock<FuzzyQ> {
+        &self.queue
+    }
+    /// sync primitive for single threading - POC generation, and Code Coverage gathering
+    pub(crate) fn sync(&self) -> &Mutex<CallTableId> {
+        &self.sync
+    }
+    pub(crate) fn closing(&self) -> &Once {
+        &self.closing
+    }
+    pub(crate) fn threads(&self) -> &Mutex< Vec< JoinHandle<()> > > {
+        &self.threads
+    }
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+    /// master seed of this session, see rng
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// next free rng stream of this session
+    pub(crate) fn stream(&self) -> u64 {
+        self.streams.fetch_add(1, Ordering::SeqCst)
+    }
+    pub(crate) fn replay(&self) -> &replay::Session {
+        &self.replay
+    }
+    pub(crate) fn corpus(&self) -> &Mutex< Option<corpus::Corpus> > {
+        &self.corpus
+    }
+    pub(crate) fn coverage(&self) -> &Mutex<coverage::Map> {
+        &self.coverage
+    }
+    pub(crate) fn inflight(&self) -> &Mutex< HashMap<ThreadId, watchdog::Inflight> > {
+        &self.inflight
+    }
+    pub(crate) fn scanner(&self) -> &Once {
+        &self.scanner
+    }
+
+    /// ask states to stop, no new ones are fuzzed and live ones close at their next call
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst)
+    }
+    pub fn stopping(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+}
+
+lazy_static! {
+    /// compatibility default, for threads which did not enter any context
+    ///
+    /// - broken config.toml is reported, context goes with defaults but is stopped right away
+    static ref DEFAULT: Arc<FuzzerContext> = match config::fzzconfig() {
+        Ok(config) => FuzzerContext::new(config.clone()),
+        Err(e) => {
+            println!("[context] {}, default context does not fuzz", e);
+            let ctx = FuzzerContext::new(FuzzyConfig::default());
+            ctx.stop();
+            ctx
+        },
+    };
+}
+
+thread_local! {
+    static CURRENT: RefCell< Option<Arc<FuzzerContext>> > = const { RefCell::new(None) };
+}
+
+/// make ctx current context of this thread
+///
+/// - call it before attaching observers / FuzzyState::fuzz, states spawned afterwards inherit it
+pub fn enter(ctx: Arc<FuzzerContext>) {
+    CURRENT.with(|cur| *cur.borrow_mut() = Some(ctx));
+}
+
+/// back to default context
+pub fn leave() {
+    CURRENT.with(|cur| *cur.borrow_mut() = None);
+}
+
+/// context of current thread, default one if thread did not enter any
+pub fn current() -> Arc<FuzzerContext> {
+    CURRENT.with(|cur| match *cur.borrow() {
+        Some(ref ctx) => ctx.clone(),
+        None => DEFAULT.clone(),
+    })
+}
+
+/// config of current context, what FZZCONFIG was before
+pub fn config() -> Arc<FuzzyConfig> {
+    current().config()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    use exec::id::CallTableId;
+    use poc::trace::{
+        CallRecord,
+        Trace,
+        TraceHeader,
+    };
+    use state::id::StateTableId;
+    use rng;
+
+    fn session(seed: u64) -> Arc<FuzzerContext> {
+        FuzzerContext::new(FuzzyConfig { seed : Some(seed), ..FuzzyConfig::default() })
+    }
+
+    fn draw(ctx: &Arc<FuzzerContext>) -> u64 {
+        enter(ctx.clone());
+        rng::init(1);
+        rng::thread_rng().gen()
+    }
+
+    #[test]
+    fn sessions_have_own_seed() {
+        let (one, two) = (session(1), session(2));
+        enter(one.clone());
+        assert_eq!(1, rng::seed());
+        enter(two.clone());
+        assert_eq!(2, rng::seed());
+        assert_ne!(draw(&one), draw(&two));
+        assert_eq!(draw(&one), draw(&session(1)));
+        leave();
+    }
+
+    #[test]
+    fn sessions_have_own_replay() {
+        let (one, two) = (session(1), session(1));
+        let mut trace = Trace::new(TraceHeader { version : 0, created : 0, seed : 1 });
+        trace.calls.push(CallRecord {
+            seq : 0,
+            tid : 1,
+            stream : 1,
+            state : String::from("state"),
+            sid : StateTableId::Id(1),
+            fd : Vec::new(),
+            level : 0,
+            index : 0,
+            cid : CallTableId::Id(1),
+            call : String::from("ctor"),
+            args : Vec::new(),
+            poc : String::new(),
+        });
+        enter(one.clone());
+        replay::schedule(&trace, false, None);
+        replay::learn(&[1, 0, 0, 0], &[2, 0, 0, 0]);
+        assert_eq!(1, replay::pending());
+        assert!(replay::live_fd(&[1, 0, 0, 0]).is_some());
+
+        enter(two);
+        assert_eq!(0, replay::pending());
+        assert!(replay::live_fd(&[1, 0, 0, 0]).is_none());
+        assert!(replay::take(&StateTableId::Id(1), None).is_none());
+
+        enter(one);
+        assert!(replay::take(&StateTableId::Id(1), None).is_some());
+        leave();
+    }
+}
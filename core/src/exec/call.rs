@@ -1,14 +1,23 @@
-use std::sync::Mutex;
+use std::io;
+use std::time::Instant;
+
+extern crate rand;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
 use generator::arg::Arg;
 use banana::bananaq;
 use super::id::CallTableId;
+use super::coverage;
 use super::fd_info::CallInfo;
-use config::FZZCONFIG;
+use super::forkserver;
+use super::watchdog;
+use context;
+use rng;
+use poc::trace::ArgRecord;
 
-lazy_static! {
-    /// sync primitive for single threading - POC generation, and Code Coverage gathering
-    static ref SYNC: Mutex<CallTableId> = Mutex::new(CallTableId::Id(0));
-}
+/// function invoking call on its arguments, see Call::dynamic
+pub type CCall = Box<dyn Fn(&mut[Arg]) -> CallInfo + Send + Sync>;
 
 /// will describle (sys)call ( or other mechanism api/io .. )
 pub struct Call {
@@ -16,14 +25,29 @@ pub struct Call {
     id: CallTableId,
     /// static name of call - for PoC and for debug purposes
     name: &'static str,
+    /// position in State : (level, index in group), dtor is out of groups therefore (!0, !0)
+    ///
+    /// - stamped by State, as call itself does not know where it belongs
+    /// - PoC recording need it, to replay exactly same call from same level
+    slot: (usize, usize),
+    /// dtor of State, marked by State itself ( calls kept outside of State, f.e. by executors, are not placed either )
+    dtor: bool,
     /// extra information comming from call -> namely FD returned is most common case
     einfo: CallInfo,
+    /// errno right after last ccall returned, before anything else ( observers, coverage, .. ) touched it
+    ///
+    /// - 0 if call was not invoked ( denied by observers )
+    errno: i32,
     /// number of total invoked calls in current thread(fuzzy_obj)
     total: usize,
     /// number of successfull calls executed so far for current thread(state/fuzzyobj)
     success: usize,
     /// defined arguments for this call : holders <- generators
     args: Vec<Arg>,
+    /// arguments as they were handed over to ccall last time, kept only with corpus or mutation configured
+    record: Vec<ArgRecord>,
+    /// arguments which led to success or new coverage, mutated instead of generated ( FuzzyConfig.mutation )
+    pool: Vec< Vec<ArgRecord> >,
     /// function which executes particular call / action
     ///
     /// - on generated args
@@ -39,7 +63,7 @@ pub struct Call {
     ///         ..
     ///     }}
     /// ```
-    ccall: fn(args: &mut[Arg]) -> CallInfo,
+    ccall: CCall,
 }
 
 impl Call {
@@ -86,49 +110,178 @@ impl Call {
         Call {
             id : id,
             name : name,
+            slot : (!0, !0),
+            dtor : false,
             einfo : CallInfo::fail(),
+            errno : 0,
             total : 0,
             success : 0,
             args : args,
-            ccall : ccall,
+            record : Vec::new(),
+            pool : Vec::new(),
+            ccall : Box::new(ccall),
         }
     }
 
+    /// same as new, but call is not known at compile time
+    ///
+    /// - call tables loaded at runtime ( desc crate ) resolve symbol and calling convention only once loaded
+    /// - therefore ccall is closure capturing those, instead of plain fn
+    pub fn dynamic(
+        id: CallTableId,
+        name: &'static str,
+        args: Vec<Arg>,
+        ccall: CCall
+        ) -> Call
+    {
+        let mut call = Call::new(id, name, args, |_| CallInfo::fail());
+        call.ccall = ccall;
+        call
+    }
+
     /// trigger particular call
     ///
     /// 1. update all # {total, skiped, success}
-    /// 2. prepare all arguments for syscall
+    /// 2. prepare all arguments for syscall : generate, or mutate ones which worked before ( do_mutate )
     /// 3. (do_call_impl)invoke callbacks to all modules -> forward this job to Banana Internal Manager in fact ..
     /// 4. (do_call_impl)invoke function responsible to invoke targeted call
     /// 5. store results
     pub fn do_call(&mut self, fd: &[u8]) -> bool {
         self.total += 1;
 
-        for arg in self.args.iter_mut() {
-            arg.do_generate(fd);
+        if !self.do_mutate(fd) {
+            for arg in self.args.iter_mut() {
+                arg.do_generate(fd);
+            }
         }
 
-        if !self.do_call_safe() {
+        // dtor at shutdown is not up to observers anymore, state must be closed
+        let forced = self.is_dtor() && bananaq::stopping();
+        if !self.do_call_safe(forced) {
             return false
         }
 
-        if self.einfo.success() {
-            self.success += 1
-        }
+        self.do_pool();
         //(self.ret <= self.ok.end && self.ret >= self.ok.start) as usize;//self.ok.contains(self.ret);
         true
     }
 
+    /// take arguments from pool instead of generating them, and mutate one of them
+    ///
+    /// - with probability of splice, part of one argument is taken from other pool entry
+    /// - all arguments are loaded as at replay ( live fds + pointers ), only then mutated
+    /// - false : mutation is off, pool is empty, or dice said generate
+    fn do_mutate(&mut self, fd: &[u8]) -> bool {
+        let config = context::config();
+        let cfg = match config.mutation {
+            Some(ref cfg) => cfg,
+            None => return false,
+        };
+        let mut rng = rng::thread_rng();
+        if self.pool.is_empty() || self.args.is_empty() || !rng.gen_bool(cfg.probability.clamp(0.0, 1.0)) {
+            return false
+        }
+        let mut entry = self.pool[rng.gen_range(0..self.pool.len())].clone();
+        if self.pool.len() > 1 && rng.gen_bool(cfg.splice.clamp(0.0, 1.0)) {
+            let other = &self.pool[rng.gen_range(0..self.pool.len())];
+            let ind = rng.gen_range(0..self.args.len());
+            if let (Some(rec), Some(other)) = (entry.get_mut(ind), other.get(ind)) {
+                if rec.data.len() == other.data.len() && !rec.data.is_empty() {
+                    let cut = rng.gen_range(0..rec.data.len());
+                    rec.data[cut..].copy_from_slice(&other.data[cut..]);
+                }
+            }
+        }
+
+        for (ind, arg) in self.args.iter_mut().enumerate() {
+            match entry.get(ind) {
+                Some(rec) => { arg.do_load(&rec.data, &rec.extra, fd); },
+                None => { arg.do_generate(fd); },
+            }
+        }
+        let mut order = (0..self.args.len()).collect::< Vec<usize> >();
+        order.shuffle(&mut rng);
+        for ind in order {
+            if self.args[ind].do_mutate(fd) {
+                break
+            }
+        }
+        true
+    }
+
+    /// keep arguments of call which succeeded or found new coverage, for do_mutate
+    fn do_pool(&mut self) {
+        let config = context::config();
+        let cfg = match config.mutation {
+            Some(ref cfg) => cfg,
+            None => return,
+        };
+        if 0 == cfg.pool_size || (!self.einfo.success() && 0 == coverage::last().new) {
+            return
+        }
+        if self.pool.len() < cfg.pool_size {
+            self.pool.push(self.record.clone())
+        } else {
+            let ind = rng::thread_rng().gen_range(0..self.pool.len());
+            self.pool[ind] = self.record.clone()
+        }
+    }
+
+    /// replay recorded call, instead of generating arguments load recorded ones
+    ///
+    /// - recorded arguments are loaded trough leafs, so fd and pointers are fixed up to live ones
+    /// - argument with mismatched size ( changed call table since recording ) is generated again
+    /// - observers are notified, but can not deny execution - we want to repro what was executed
+    pub fn do_replay(&mut self, fd: &[u8], args: &[ArgRecord]) -> bool {
+        self.total += 1;
+
+        for (ind, arg) in self.args.iter_mut().enumerate() {
+            match args.get(ind) {
+                Some(rec) => { arg.do_load(&rec.data, &rec.extra, fd); },
+                None => { arg.do_generate(fd); },
+            }
+        }
+
+        self.do_call_safe(true)
+    }
+
 /// 1. notify observers and ask for aproval
-/// 2. if approved invoke syscall
-/// 3. have in mind that in case of single thread approach this need to be locked!
+/// 2. if approved ( or forced by replay ) invoke syscall, coverage of it collected if configured
+/// 3. store result, then notify observers about it ( success(), dead() are up to date for them )
+/// 4. have in mind that in case of single thread approach this need to be locked!
 ///     - therefore do_call_safe wrapper there..
-    fn do_call_impl(&mut self) -> bool {
-        if !bananaq::call_notify(self) {
+    fn do_call_impl(&mut self, replay: bool) -> bool {
+        self.errno = 0;
+        if !bananaq::call_notify(self) && !replay {
             return false
         }
 
+        let config = context::config();
+        if config.corpus.is_some() || config.mutation.is_some() {
+            self.record = self.args
+                .iter()
+                .map(|arg| ArgRecord {
+                    data : arg.data().to_vec(),
+                    extra : arg.dump_extra(),
+                })
+                .collect();
+        }
+
+        forkserver::enter(&self.id);
+        watchdog::enter(&self.id, self.name);
+        coverage::enter();
+        let start = Instant::now();
         self.einfo = (self.ccall)(&mut self.args);
+        self.errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        let elapsed = start.elapsed();
+        coverage::leave(self);
+        watchdog::leave();
+        forkserver::beat();
+
+        if self.einfo.success() {
+            self.success += 1
+        }
+        bananaq::call_post_notify(self, &self.einfo, elapsed);
         true
     }
 /// do sync in case of single thread config flag set
@@ -136,14 +289,16 @@ impl Call {
 /// - poc creation from fuzzing loops
 /// - code coverage ( because we need to repro fuzzed loops to benefit from code coverage .. )
 /// - ??
-    fn do_call_safe(&mut self) -> bool {
-        if !FZZCONFIG.singlethread {
-            return self.do_call_impl()
+    fn do_call_safe(&mut self, replay: bool) -> bool {
+        let ctx = context::current();
+        if !ctx.config().singlethread {
+            return self.do_call_impl(replay)
         }
-        match SYNC.lock() {
-            Ok(mut qcall) => { *qcall = self.id.clone(); self.do_call_impl() },
+        let ok = match ctx.sync().lock() {
+            Ok(mut qcall) => { *qcall = self.id.clone(); self.do_call_impl(replay) },
             Err(pois) => panic!("call-lock is poisoned, by this syscall : {:?}", *pois.into_inner()),
-        }
+        };
+        ok
     }
 
     /// print call to string that way we can reproduce it from PoC ( mini c++ program ) later
@@ -171,16 +326,27 @@ impl Call {
 
     pub fn name(&self) -> &str { self.name }
     pub fn id(&self) -> CallTableId { self.id.clone() }
+    pub fn level(&self) -> usize { self.slot.0 }
+    pub fn index(&self) -> usize { self.slot.1 }
+    pub fn is_dtor(&self) -> bool { self.dtor }
     pub fn total(&self) -> usize { self.total }
     pub fn success(&self) -> usize { self.success }
     pub fn ok(&self) -> bool { self.einfo.success() }
-    pub fn dead(&self) -> bool { self.total > FZZCONFIG.dead_call * (1 + self.success) }//from config!!
+    pub fn dead(&self) -> bool { self.total > context::config().dead_call * (1 + self.success) }//from config!!
     pub fn einfo(&self) -> &[u8] { &self.einfo.extra_info() }
+    /// errno of last invocation, taken right after ccall; meaningful only if call failed
+    pub fn errno(&self) -> i32 { self.errno }
 
     pub fn neg_ret(&mut self) { self.einfo.negate() }
 
     pub fn n_args(&self) -> usize { self.args.len() }
 
     pub fn args_view(&self, ind: usize) -> &Arg { &self.args[ind] }
+
+    /// arguments of last invocation, empty unless corpus or mutation is configured
+    pub fn record(&self) -> &[ArgRecord] { &self.record }
+
+    pub(crate) fn place(&mut self, level: usize, index: usize) { self.slot = (level, index) }
+    pub(crate) fn mark_dtor(&mut self) { self.dtor = true }
 }
 
@@ -0,0 +1,38 @@
+use std::fs::OpenOptions;
+use std::io::Read;
+
+use super::ICoverage;
+
+/// blocks written to file by external tracer ( hypervisor, dbi, debugger, .. ), hex address per line
+///
+/// - consumed after every call : read, then truncated, tracer appends blocks of next call
+/// - words which are not hex are skipped, so tracer can leave comments or module names there
+pub struct FileCov {
+    path: String,
+}
+
+impl FileCov {
+    pub fn new(path: &str) -> FileCov {
+        FileCov { path : path.to_string() }
+    }
+
+    fn truncate(&self) -> String {
+        let mut data = String::new();
+        if let Ok(mut file) = OpenOptions::new().read(true).write(true).open(&self.path) {
+            let _ = file.read_to_string(&mut data);
+            let _ = file.set_len(0);
+        }
+        data
+    }
+}
+
+impl ICoverage for FileCov {
+    fn reset(&mut self) {
+        self.truncate();
+    }
+    fn collect(&mut self, blocks: &mut Vec<u64>) {
+        blocks.extend(self.truncate()
+            .split_whitespace()
+            .filter_map(|word| u64::from_str_radix(word.trim_start_matches("0x"), 16).ok()));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use libc;
+
+use super::ICoverage;
+
+const KCOV_INIT_TRACE: libc::c_ulong = 0x8008_6301;
+const KCOV_ENABLE: libc::c_ulong = 0x6364;
+const KCOV_TRACE_PC: libc::c_ulong = 0;
+
+/// pcs per call we keep, as syz-executor does
+pub const COVER_SIZE: usize = 256 << 10;
+
+/// kcov of current thread, pcs of kernel code it executed since last reset
+///
+/// - enabled for thread which opened it, so open it from thread doing calls
+/// - first word of area is count of pcs, kernel appends behind it
+pub struct Kcov {
+    _file: File,
+    area: *mut u64,
+    size: usize,
+}
+
+impl Kcov {
+    pub fn open(size: usize) -> Result<Kcov, io::Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/sys/kernel/debug/kcov")?;
+        let fd = file.as_raw_fd();
+        if 0 != unsafe { libc::ioctl(fd, KCOV_INIT_TRACE, size as libc::c_ulong) } {
+            return Err(io::Error::last_os_error())
+        }
+        let area = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size * 8,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0)
+        };
+        if libc::MAP_FAILED == area {
+            return Err(io::Error::last_os_error())
+        }
+        let kcov = Kcov { _file : file, area : area as *mut u64, size };
+        if 0 != unsafe { libc::ioctl(fd, KCOV_ENABLE, KCOV_TRACE_PC) } {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(kcov)
+    }
+
+    fn count(&self) -> &AtomicU64 {
+        unsafe { &*(self.area as *const AtomicU64) }
+    }
+
+    pub fn reset(&self) {
+        self.count().store(0, Ordering::Relaxed)
+    }
+
+    pub fn pcs(&self) -> &[u64] {
+        let count = (self.count().load(Ordering::Relaxed) as usize).min(self.size - 1);
+        unsafe { slice::from_raw_parts(self.area.add(1), count) }
+    }
+}
+
+impl ICoverage for Kcov {
+    fn reset(&mut self) {
+        Kcov::reset(self)
+    }
+    fn collect(&mut self, blocks: &mut Vec<u64>) {
+        blocks.extend_from_slice(self.pcs())
+    }
+}
+
+impl Drop for Kcov {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.area as *mut libc::c_void, self.size * 8) };
+    }
+}
@@ -0,0 +1,281 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io;
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use serde_json;
+
+use banana::bananaq;
+use config::{
+    CoverageConfig,
+    CoverageSource,
+};
+use context;
+use exec::call::Call;
+use exec::id::CallTableId;
+use state::id::StateTableId;
+
+#[cfg(unix)]
+pub mod kcov;
+#[cfg(unix)]
+pub mod sancov;
+pub mod file;
+
+/// source of blocks hit by calls of current thread
+///
+/// - one instance per fuzzing thread, opened lazily by first call of thread
+pub trait ICoverage {
+    /// forget what was hit so far, right before call
+    fn reset(&mut self);
+    /// blocks ( pcs, guards, .. ) hit since reset, in order of execution where source knows it
+    fn collect(&mut self, blocks: &mut Vec<u64>);
+}
+
+/// coverage of one call
+#[derive(Clone, Debug, Default)]
+pub struct CallCoverage {
+    /// bitmap indexes of blocks ( or edges ) call hit, sorted + deduped
+    pub hits: Vec<u32>,
+    /// how many of hits were not hit by any call before
+    pub new: usize,
+}
+
+/// where new coverage came from, per call of state at its level
+#[derive(Clone, Debug, Serialize)]
+pub struct Attribution {
+    pub sid: StateTableId,
+    pub state: String,
+    pub level: usize,
+    pub cid: CallTableId,
+    pub call: String,
+    /// bitmap indexes this call found first
+    pub new: usize,
+    /// how many times it brought something new
+    pub times: usize,
+}
+
+/// one line of findings file
+#[derive(Debug, Serialize)]
+struct Finding<'a> {
+    kind: &'a str,
+    /// unix time in seconds
+    time: u64,
+    state: &'a str,
+    sid: StateTableId,
+    level: usize,
+    cid: CallTableId,
+    call: &'a str,
+    new: usize,
+    total: usize,
+}
+
+/// bitmap of everything hit so far + who found it, one per context ( FuzzerContext::coverage )
+pub(crate) struct Map {
+    seen: Vec<u64>,
+    total: usize,
+    /// (sid, cid, level) -> attribution
+    found: HashMap<(u64, u64, usize), Attribution>,
+}
+
+impl Map {
+    pub(crate) fn new(size: usize) -> Map {
+        Map {
+            seen : vec![0; size.div_ceil(64)],
+            total : 0,
+            found : HashMap::new(),
+        }
+    }
+
+    /// mark hits as seen, returns how many were not seen yet
+    fn merge(&mut self, hits: &[u32]) -> usize {
+        let mut new = 0;
+        for &hit in hits.iter() {
+            let (word, bit) = (hit as usize / 64, 1u64 << (hit % 64));
+            if 0 == self.seen[word] & bit {
+                self.seen[word] |= bit;
+                new += 1;
+            }
+        }
+        self.total += new;
+        new
+    }
+}
+
+thread_local! {
+    static SOURCE: RefCell< Option<Box<dyn ICoverage>> > = RefCell::new(open());
+    static LAST: RefCell<CallCoverage> = RefCell::new(CallCoverage::default());
+}
+
+fn open() -> Option<Box<dyn ICoverage>> {
+    let config = context::config();
+    let cfg = config.coverage.as_ref()?;
+    let source: Result<Box<dyn ICoverage>, io::Error> = match cfg.source {
+        #[cfg(unix)]
+        CoverageSource::Kcov => kcov::Kcov::open(kcov::COVER_SIZE).map(|kcov| Box::new(kcov) as Box<dyn ICoverage>),
+        #[cfg(unix)]
+        CoverageSource::Sancov => sancov::SanCov::open(&cfg.path, cfg.map_size).map(|sancov| Box::new(sancov) as Box<dyn ICoverage>),
+        #[cfg(not(unix))]
+        CoverageSource::Kcov | CoverageSource::Sancov => Err(io::Error::new(io::ErrorKind::Unsupported, "unix only source")),
+        CoverageSource::File => Ok(Box::new(file::FileCov::new(&cfg.path))),
+    };
+    match source {
+        Ok(source) => Some(source),
+        Err(e) => {
+            println!("[coverage] {:?} not available to this thread : {}", cfg.source, e);
+            None
+        },
+    }
+}
+
+/// syz-executor hash, spreads block addresses over bitmap
+fn hash(block: u64) -> u32 {
+    let mut a = (block ^ (block >> 32)) as u32;
+    a = (a ^ 61) ^ (a >> 16);
+    a = a.wrapping_add(a << 3);
+    a ^= a >> 4;
+    a = a.wrapping_mul(0x27d4_eb2d);
+    a ^ (a >> 15)
+}
+
+/// blocks -> bitmap indexes, edge is block with its predecessor ( AFL style, so A->B != B->A )
+fn bitmap(blocks: &[u64], cfg: &CoverageConfig) -> Vec<u32> {
+    let size = cfg.map_size.max(1) as u32;
+    let mut prev = 0;
+    let mut hits = blocks
+        .iter()
+        .map(|&block| {
+            let cur = hash(block);
+            let hit = if cfg.edges { cur ^ (prev >> 1) } else { cur };
+            prev = cur;
+            hit % size
+        })
+        .collect::< Vec<u32> >();
+    hits.sort_unstable();
+    hits.dedup();
+    hits
+}
+
+/// current thread is about to invoke call, no-op when coverage is off
+pub fn enter() {
+    if context::config().coverage.is_none() {
+        return
+    }
+    SOURCE.with(|source| {
+        if let Some(ref mut source) = *source.borrow_mut() {
+            source.reset()
+        }
+    });
+}
+
+/// call of current thread returned, collect its coverage and attribute what is new
+///
+/// - new coverage is attributed to call + state + level of state it was called at
+/// - observers are notified ( notify_coverage ) before notify_post of call
+/// - returns how many bitmap indexes call hit first
+pub fn leave(call: &Call) -> usize {
+    let config = context::config();
+    let cfg = match config.coverage {
+        Some(ref cfg) => cfg,
+        None => return 0,
+    };
+    let mut blocks = Vec::new();
+    SOURCE.with(|source| {
+        if let Some(ref mut source) = *source.borrow_mut() {
+            source.collect(&mut blocks)
+        }
+    });
+    let hits = bitmap(&blocks, cfg);
+    let ctx = context::current();
+    let new = match ctx.coverage().lock() {
+        Ok(mut map) => map.merge(&hits),
+        Err(_) => 0,
+    };
+    let cov = CallCoverage { hits, new };
+    if 0 != new {
+        attribute(call, &cov, cfg);
+    }
+    LAST.with(|last| *last.borrow_mut() = cov);
+    new
+}
+
+fn attribute(call: &Call, cov: &CallCoverage, cfg: &CoverageConfig) {
+    let info = bananaq::coverage_notify(call, cov);
+    let (state, sid) = match info {
+        Some(ref info) => (info.name.as_str(), info.id.clone()),
+        None => ("", StateTableId::Id(0)),
+    };
+    let (StateTableId::Id(sid_raw), CallTableId::Id(cid_raw)) = (sid.clone(), call.id());
+    let ctx = context::current();
+    let total = match ctx.coverage().lock() {
+        Ok(mut map) => {
+            let found = map.found
+                .entry((sid_raw, cid_raw, call.level()))
+                .or_insert_with(|| Attribution {
+                    sid : sid.clone(),
+                    state : state.to_string(),
+                    level : call.level(),
+                    cid : call.id(),
+                    call : call.name().to_string(),
+                    new : 0,
+                    times : 0,
+                });
+            found.new += cov.new;
+            found.times += 1;
+            map.total
+        },
+        Err(_) => return,
+    };
+    if context::config().noisy {
+        println!("[coverage] {} of {} at level {} : +{} ( {} total )", call.name(), state, call.level(), cov.new, total);
+    }
+    if cfg.findings.is_empty() {
+        return
+    }
+    let finding = Finding {
+        kind : "coverage",
+        time : match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.as_secs(),
+            Err(_) => 0,
+        },
+        state,
+        sid,
+        level : call.level(),
+        cid : call.id(),
+        call : call.name(),
+        new : cov.new,
+        total,
+    };
+    if let Ok(line) = serde_json::to_string(&finding) {
+        if let Err(e) = generic::append_file_raw(&cfg.findings, (line + "\n").as_bytes()) {
+            println!("[coverage] unable to record coverage to {} : {}", cfg.findings, e);
+        }
+    }
+}
+
+/// coverage of last call of current thread, for observers at notify_post
+pub fn last() -> CallCoverage {
+    LAST.with(|last| last.borrow().clone())
+}
+
+/// bitmap indexes hit so far, by all threads of current context
+pub fn total() -> usize {
+    match context::current().coverage().lock() {
+        Ok(map) => map.total,
+        Err(_) => 0,
+    }
+}
+
+/// who found what, most productive first
+pub fn attribution() -> Vec<Attribution> {
+    let ctx = context::current();
+    let mut found = match ctx.coverage().lock() {
+        Ok(map) => map.found.values().cloned().collect::< Vec<Attribution> >(),
+        Err(_) => return Vec::new(),
+    };
+    found.sort_by_key(|found| Reverse(found.new));
+    found
+}
@@ -0,0 +1,74 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::slice;
+
+use libc;
+
+use super::ICoverage;
+
+/// counters of SanitizerCoverage guards, in shared memory sancov runtime of target writes to
+///
+/// - for in-process library targets built with -fsanitize-coverage=trace-pc-guard, its runtime maps
+///   same file and counts hits per guard, AFL style :
+///     - __sanitizer_cov_trace_pc_guard_init : number guards 1.., modulo size of map
+///     - __sanitizer_cov_trace_pc_guard : area[*guard]++
+/// - memory is shared by whole process, calls of other threads mix their coverage in, therefore
+///   singlethread config for exact attribution
+pub struct SanCov {
+    _file: File,
+    area: *mut u8,
+    size: usize,
+}
+
+impl SanCov {
+    pub fn open(path: &str, size: usize) -> Result<SanCov, io::Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if file.metadata()?.len() < size as u64 {
+            file.set_len(size as u64)?;
+        }
+        let area = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0)
+        };
+        if libc::MAP_FAILED == area {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(SanCov { _file : file, area : area as *mut u8, size })
+    }
+
+    fn counters(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.area, self.size) }
+    }
+}
+
+impl ICoverage for SanCov {
+    fn reset(&mut self) {
+        unsafe { ptr::write_bytes(self.area, 0, self.size) }
+    }
+    /// guards hit, sancov does not keep order
+    fn collect(&mut self, blocks: &mut Vec<u64>) {
+        blocks.extend(self.counters()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| 0 != count)
+            .map(|(guard, _)| guard as u64));
+    }
+}
+
+impl Drop for SanCov {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.area as *mut libc::c_void, self.size) };
+    }
+}
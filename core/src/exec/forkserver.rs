@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{
+    AtomicBool,
+    AtomicPtr,
+    AtomicU64,
+    Ordering,
+};
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use config::ForkServerConfig;
+use context;
+use exec::id::CallTableId;
+use exec::watchdog;
+use poc::trace::Trace;
+
+/// why worker ended
+#[derive(Clone, Debug)]
+pub enum CrashReason {
+    /// killed by signal ( SIGSEGV, SIGABRT, .. )
+    Signal(i32),
+    /// exited with non zero code, panic in main thread f.e.
+    Exit(i32),
+    /// no call finished for hang_timeout ( killed by us ), or watchdog restarted worker
+    Hang,
+}
+
+/// one crash of worker, handed over to on_crash of serve
+#[derive(Clone, Debug)]
+pub struct Crash {
+    pub reason: CrashReason,
+    /// crashes so far, including this one
+    pub count: usize,
+    /// calls finished by worker before it crashed
+    pub calls: u64,
+    /// last call worker entered, likely the one which crashed ( or hanged )
+    pub last: CallTableId,
+    /// folder with context of crash ( crash.txt, trace.jsonl ), empty if we failed to save it
+    pub dir: String,
+}
+
+/// shared between controller and worker ( MAP_SHARED ), so it survives worker death
+struct Heartbeat {
+    calls: AtomicU64,
+    last: AtomicU64,
+    /// crashes so far, worker can report it ( monitoring )
+    crashes: AtomicU64,
+}
+
+static HEARTBEAT: AtomicPtr<Heartbeat> = AtomicPtr::new(ptr::null_mut());
+static WORKER: AtomicBool = AtomicBool::new(false);
+
+fn heartbeat() -> Option<&'static Heartbeat> {
+    unsafe { HEARTBEAT.load(Ordering::Relaxed).as_ref() }
+}
+
+/// call is about to be invoked, no-op when not in forkserver mode
+pub fn enter(id: &CallTableId) {
+    if let Some(heartbeat) = heartbeat() {
+        let CallTableId::Id(id) = *id;
+        heartbeat.last.store(id, Ordering::Relaxed);
+    }
+}
+/// call finished, worker is alive
+pub fn beat() {
+    if let Some(heartbeat) = heartbeat() {
+        heartbeat.calls.fetch_add(1, Ordering::Relaxed);
+    }
+}
+/// how many workers crashed before this one, 0 when not in forkserver mode
+pub fn crashes() -> u64 {
+    heartbeat().map_or(0, |heartbeat| heartbeat.crashes.load(Ordering::Relaxed))
+}
+/// are we fuzzing inside of forked worker
+pub fn is_worker() -> bool { WORKER.load(Ordering::Relaxed) }
+
+/// run fuzzing in forked worker, controller ( parent ) restarts it once it crashes or hangs
+///
+/// - worker starts fuzzing ( plug modules, FuzzyState::fuzz, .. ) and waits for it
+///     - forking of multithreaded process is no go, so serve must be first thing in main
+/// - crash context ( last recorded calls per thread ) is saved to crash_dir, then on_crash is called
+/// - without [forkserver] in config.toml worker runs in-process, as it was before
+/// - returns once worker finished ( exit code 0, f.e. stop_fuzzing ) or max_restarts was hit
+pub fn serve<W, C>(worker: W, on_crash: C) -> i32
+    where
+        W: Fn(),
+        C: FnMut(&Crash),
+{
+    let config = context::config();
+    match config.forkserver {
+        Some(ref cfg) => serve_impl(cfg, worker, on_crash),
+        None => {
+            worker();
+            0
+        },
+    }
+}
+
+#[cfg(unix)]
+fn serve_impl<W, C>(cfg: &ForkServerConfig, worker: W, mut on_crash: C) -> i32
+    where
+        W: Fn(),
+        C: FnMut(&Crash),
+{
+    let mem = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            std::mem::size_of::<Heartbeat>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1, 0)
+    };
+    if libc::MAP_FAILED == mem {
+        panic!("[forkserver] unable to map heartbeat");
+    }
+    //anonymous mapping is zeroed, what is valid state of atomics
+    HEARTBEAT.store(mem as *mut Heartbeat, Ordering::SeqCst);
+
+    let mut count = 0;
+    loop {
+        if let Some(heartbeat) = heartbeat() {
+            heartbeat.calls.store(0, Ordering::SeqCst);
+            heartbeat.last.store(0, Ordering::SeqCst);
+            heartbeat.crashes.store(count as u64, Ordering::SeqCst);
+        }
+
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            panic!("[forkserver] fork failed");
+        }
+        if 0 == pid {
+            WORKER.store(true, Ordering::SeqCst);
+            worker();
+            std::process::exit(0)
+        }
+
+        let reason = match watch(pid, cfg) {
+            Some(reason) => reason,
+            None => return 0,
+        };
+        count += 1;
+
+        let (calls, last) = match heartbeat() {
+            Some(heartbeat) => (
+                heartbeat.calls.load(Ordering::SeqCst),
+                heartbeat.last.load(Ordering::SeqCst)),
+            None => (0, 0),
+        };
+        let mut crash = Crash {
+            reason,
+            count,
+            calls,
+            last : CallTableId::Id(last),
+            dir : String::new(),
+        };
+        crash.dir = save(cfg, &crash);
+        println!("[forkserver] worker {} down : {:?} after {} calls, last {:?} -> {}",
+            pid, crash.reason, crash.calls, crash.last, crash.dir);
+
+        on_crash(&crash);
+        if 0 != cfg.max_restarts && count >= cfg.max_restarts {
+            return 1
+        }
+    }
+}
+
+/// wait for worker to end, None means it finished fine
+#[cfg(unix)]
+fn watch(pid: libc::pid_t, cfg: &ForkServerConfig) -> Option<CrashReason> {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let mut calls = heartbeat().map_or(0, |heartbeat| heartbeat.calls.load(Ordering::SeqCst));
+    let mut alive = Instant::now();
+    loop {
+        let mut status = 0;
+        match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+            0 => (),
+            ret if ret == pid => {
+                return if libc::WIFSIGNALED(status) {
+                    Some(CrashReason::Signal(libc::WTERMSIG(status)))
+                } else if watchdog::HANG_EXIT == libc::WEXITSTATUS(status) {
+                    Some(CrashReason::Hang)
+                } else if 0 != libc::WEXITSTATUS(status) {
+                    Some(CrashReason::Exit(libc::WEXITSTATUS(status)))
+                } else {
+                    None
+                }
+            },
+            _ => return Some(CrashReason::Exit(-1)),//lost our worker
+        }
+
+        let now = heartbeat().map_or(0, |heartbeat| heartbeat.calls.load(Ordering::SeqCst));
+        if now != calls {
+            calls = now;
+            alive = Instant::now();
+        } else if 0 != cfg.hang_timeout && alive.elapsed() > Duration::from_millis(cfg.hang_timeout) {
+            unsafe {
+                libc::kill(pid, libc::SIGKILL);
+                libc::waitpid(pid, &mut status, 0);
+            }
+            return Some(CrashReason::Hang)
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(not(unix))]
+fn serve_impl<W, C>(_: &ForkServerConfig, worker: W, _: C) -> i32
+    where
+        W: Fn(),
+        C: FnMut(&Crash),
+{
+    println!("[forkserver] not supported on this platform, fuzzing in-process");
+    worker();
+    0
+}
+
+/// store crash.txt + last recorded calls to its own folder in crash_dir
+fn save(cfg: &ForkServerConfig, crash: &Crash) -> String {
+    let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(time) => time.as_secs(),
+        Err(_) => 0,
+    };
+    let dir = Path::new(&cfg.crash_dir).join(format!("crash_{}_{}", secs, crash.count));
+    if let Err(e) = fs::create_dir_all(&dir) {
+        println!("[forkserver] unable to create {:?} : {}", dir, e);
+        return String::new()
+    }
+
+    let mut info = format!("reason : {:?}\ncalls : {}\nlast : {:?}\n", crash.reason, crash.calls, crash.last);
+    if !cfg.trace.is_empty() {
+        let saved = Trace::load(&cfg.trace)
+            .and_then(|trace| trace
+                .tail(cfg.context)
+                .save(&dir.join("trace.jsonl").to_string_lossy()));
+        if let Err(e) = saved {
+            info += &format!("trace : {} -> {}\n", cfg.trace, e);
+        }
+    }
+    if let Err(e) = generic::write_file_raw(&dir.join("crash.txt").to_string_lossy(), info.as_bytes()) {
+        println!("[forkserver] unable to save crash info : {}", e);
+    }
+    dir.to_string_lossy().to_string()
+}
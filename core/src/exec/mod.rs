@@ -1,3 +1,6 @@
 pub mod call;
 pub mod fd_info;
 pub mod id;
+pub mod forkserver;
+pub mod watchdog;
+pub mod coverage;
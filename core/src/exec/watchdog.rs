@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use serde_json;
+
+use banana::bananaq;
+use context::{
+    self,
+    FuzzerContext,
+};
+use exec::forkserver;
+use exec::id::CallTableId;
+use state::id::StateTableId;
+
+/// exit code of forkserver worker restarted because of hang
+pub const HANG_EXIT: i32 = 0x42;
+
+/// call which did not finish in time
+#[derive(Clone, Debug)]
+pub struct Hang {
+    /// generic::get_tid of hanged thread, same as in poc trace
+    pub tid: u64,
+    pub id: CallTableId,
+    pub name: &'static str,
+    pub elapsed: Duration,
+}
+
+/// one line of findings file
+#[derive(Debug, Serialize)]
+struct Finding<'a> {
+    kind: &'a str,
+    /// unix time in seconds
+    time: u64,
+    tid: u64,
+    state: &'a str,
+    sid: StateTableId,
+    fd: &'a [u8],
+    cid: CallTableId,
+    call: &'a str,
+    elapsed_ms: u64,
+}
+
+/// call thread is inside of, watched per context ( FuzzerContext::inflight )
+pub(crate) struct Inflight {
+    hang: Hang,
+    since: Instant,
+    timeout: Duration,
+    /// reported already, we report every hang once
+    reported: bool,
+}
+
+/// per call config first, global one as fallback
+fn timeout(ctx: &FuzzerContext, id: &CallTableId) -> Option<Duration> {
+    let config = ctx.config();
+    let cfg = config.watchdog.as_ref()?;
+    let CallTableId::Id(id) = *id;
+    match cfg.calls.iter().find(|call| id == call.id) {
+        Some(call) => Some(call.timeout),
+        None if 0 != cfg.timeout => Some(cfg.timeout),
+        None => None,
+    }.map(Duration::from_millis)
+}
+
+/// current thread is about to invoke call, no-op when watchdog is off ( or call is not watched )
+pub fn enter(id: &CallTableId, name: &'static str) {
+    let ctx = context::current();
+    let timeout = match timeout(&ctx, id) {
+        Some(timeout) => timeout,
+        None => return,
+    };
+    ctx.scanner().call_once(|| {
+        let scanner = ctx.clone();
+        bananaq::adopt(thread::spawn(move || scan(scanner)));
+    });
+
+    if let Ok(mut inflight) = ctx.inflight().lock() {
+        inflight.insert(thread::current().id(), Inflight {
+            hang : Hang {
+                tid : generic::get_tid(),
+                id : id.clone(),
+                name,
+                elapsed : Duration::from_secs(0),
+            },
+            since : Instant::now(),
+            timeout,
+            reported : false,
+        });
+    };
+}
+/// call of current thread returned
+pub fn leave() {
+    let ctx = context::current();
+    if ctx.config().watchdog.is_none() {
+        return
+    }
+    if let Ok(mut inflight) = ctx.inflight().lock() {
+        inflight.remove(&thread::current().id());
+    };
+}
+
+/// how many threads of current context are stuck in call right now
+pub fn hanged() -> usize {
+    match context::current().inflight().lock() {
+        Ok(inflight) => inflight.values().filter(|call| call.reported).count(),
+        Err(_) => 0,
+    }
+}
+
+/// watchdog thread, checks in-flight calls against their timeouts
+///
+/// - one per context, hang is handled in it ( queue + observers of hanged thread )
+/// - ends once fuzzing of context stops, joined at shutdown as any other adopted thread
+fn scan(ctx: Arc<FuzzerContext>) {
+    context::enter(ctx.clone());
+    while !ctx.stopping() {
+        thread::sleep(Duration::from_millis(100));
+
+        let hangs = match ctx.inflight().lock() {
+            Ok(mut inflight) => inflight
+                .iter_mut()
+                .filter(|(_, call)| !call.reported && call.since.elapsed() > call.timeout)
+                .map(|(&thread, call)| {
+                    call.reported = true;
+                    call.hang.elapsed = call.since.elapsed();
+                    (thread, call.hang.clone())
+                })
+                .collect::< Vec<(thread::ThreadId, Hang)> >(),
+            Err(_) => return,
+        };
+
+        let config = ctx.config();
+        let cfg = match config.watchdog {
+            Some(ref cfg) => cfg,
+            None => return,
+        };
+        for (thread, hang) in hangs {
+            let info = bananaq::hang_notify(thread, &hang, cfg.reclaim);
+            if config.noisy {
+                println!("[watchdog] {} {:?} hangs for {:?}", hang.name, hang.id, hang.elapsed);
+            }
+            if !cfg.findings.is_empty() {
+                let finding = Finding {
+                    kind : "hang",
+                    time : match SystemTime::now().duration_since(UNIX_EPOCH) {
+                        Ok(time) => time.as_secs(),
+                        Err(_) => 0,
+                    },
+                    tid : hang.tid,
+                    state : info.as_ref().map_or("", |info| &info.name),
+                    sid : info.as_ref().map_or(StateTableId::Id(0), |info| info.id.clone()),
+                    fd : info.as_ref().map_or(&[], |info| info.fd.data()),
+                    cid : hang.id.clone(),
+                    call : hang.name,
+                    elapsed_ms : hang.elapsed.as_millis() as u64,
+                };
+                if let Ok(line) = serde_json::to_string(&finding) {
+                    if let Err(e) = generic::append_file_raw(&cfg.findings, (line + "\n").as_bytes()) {
+                        println!("[watchdog] unable to record hang to {} : {}", cfg.findings, e);
+                    }
+                }
+            }
+            if cfg.restart && forkserver::is_worker() {
+                std::process::exit(HANG_EXIT)
+            }
+        }
+    }
+}
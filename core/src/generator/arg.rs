@@ -1,4 +1,11 @@
 use std::mem;
+
+extern crate rand;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use rng;
+
 use super::leaf::IArgLeaf;
 
 use generic::native_alloc::NativeAlloc;
@@ -109,6 +116,42 @@ impl Arg {
         self
     }
 
+    /// data of argument which are not in self.data(), see IArgLeaf::dump
+    pub fn dump_extra(&self) -> Vec<u8> {
+        self.generator.dump(self.data.data())
+    }
+
+    /// load recorded data instead of generating them, for replaying PoC
+    ///
+    /// - if recorded data does not fit ( call table changed since ), we generate instead
+    /// - returns how much of extra was consumed
+    pub fn do_load(&mut self, data: &[u8], extra: &[u8], fd: &[u8]) -> usize {
+        if data.len() != self.data.len() {
+            self.do_generate(fd);
+            return 0
+        }
+        self.generator.load(self.data.data_mut(), data, extra, fd)
+    }
+
+    /// mutate current data in place, instead of generating them again
+    ///
+    /// - data should be loaded by do_load first, so fds and pointers are live ones
+    /// - 1:4 one of interesting values of generator is placed, otherwise generator mutates, see IArgLeaf::mutate
+    /// - returns false if nothing was changed ( argument of consts, fds, .. )
+    pub fn do_mutate(&mut self, fd: &[u8]) -> bool {
+        let mut rng = rng::thread_rng();
+        if rng.gen_bool(0.25) {
+            if let Some(value) = self.generator.interesting_values().choose(&mut rng) {
+                let end = value.offset + value.data.len();
+                if end <= self.size() {
+                    self.data.data_mut()[value.offset..end].copy_from_slice(&value.data);
+                    return true
+                }
+            }
+        }
+        self.generator.mutate(self.data.data_mut(), fd)
+    }
+
     /// yep, little bit of unsafety, as we want to invoke calls which are basically C stuffs
     pub fn data_mut_unsafe<T>(&mut self) -> &mut T {
         if mem::size_of::<T>() > self.data.len() {
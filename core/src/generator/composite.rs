@@ -1,5 +1,14 @@
 use std::ops::Range;
-use super::leaf::IArgLeaf;
+
+extern crate rand;
+use rand::seq::SliceRandom;
+
+use rng;
+
+use super::leaf::{
+    IArgLeaf,
+    InterestingValue,
+};
 use super::serialize::ISerializableArg;
 use super::serialize::SerializationInfo;
 
@@ -108,6 +117,52 @@ impl IArgLeaf for ArgComposite {
             arg.generate(&mut mem[off..off+size], fd)
         }
     }
+
+    /// mutate one leaf, first of them in random order which does not keep its data intact
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        let mut order = (0..self.args.len()).collect::< Vec<usize> >();
+        order.shuffle(&mut rng::thread_rng());
+        order
+            .into_iter()
+            .any(|i| {
+                let (off, ref mut arg) = self.args[i];
+                let size = arg.size();
+                arg.mutate(&mut mem[off..off+size], fd)
+            })
+    }
+
+    /// values of all leafs, placed at offsets of their leafs
+    fn interesting_values(&self) -> Vec<InterestingValue> {
+        self.args
+            .iter()
+            .flat_map(|&(off, ref arg)| arg
+                .interesting_values()
+                .into_iter()
+                .map(move |mut value| {
+                    value.offset += off;
+                    value }))
+            .collect()
+    }
+
+    /// extra data of all leafs, in order of leafs
+    fn dump(&self, mem: &[u8]) -> Vec<u8> {
+        self.args
+            .iter()
+            .flat_map(|&(off, ref arg)| arg.dump(&mem[off..off+arg.size()]))
+            .collect()
+    }
+
+    /// copy whole recorded data first ( also not described gaps ), then let leafs fix up their part
+    fn load_unsafe(&mut self, mem: &mut[u8], data: &[u8], extra: &[u8], fd: &[u8]) -> usize {
+        mem.copy_from_slice(data);
+        let mut consumed = 0;
+        for i in 0..self.args.len() {
+            let (off, ref mut arg) = self.args[i];
+            let size = arg.size();
+            consumed += arg.load(&mut mem[off..off+size], &data[off..off+size], &extra[consumed..], fd);
+        }
+        consumed
+    }
 }
 
 /// default serialization provider
@@ -158,3 +213,49 @@ impl ISerializableArg for ArgComposite {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// one byte leaf, which knows 0xff is interesting and mutates to it
+    struct Byte;
+
+    impl ISerializableArg for Byte { }
+
+    impl IArgLeaf for Byte {
+        fn size(&self) -> usize { 1 }
+        fn name(&self) -> &'static str { "Byte" }
+        fn generate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) { mem[0] = 0 }
+        fn mutate_unsafe(&mut self, mem: &mut[u8], _: &[u8]) -> bool {
+            mem[0] = 0xff;
+            true
+        }
+        fn interesting_values(&self) -> Vec<InterestingValue> {
+            vec![InterestingValue { offset : 0, data : vec![0xff] }]
+        }
+    }
+
+    fn composite() -> ArgComposite {
+        ArgComposite::new(8, "test", vec![(1, Box::new(Byte)), (6, Box::new(Byte))])
+    }
+
+    #[test]
+    fn interesting_values_at_offsets_of_leafs() {
+        let offsets = composite()
+            .interesting_values()
+            .into_iter()
+            .map(|value| value.offset)
+            .collect::< Vec<usize> >();
+        assert_eq!(vec![1, 6], offsets);
+    }
+
+    #[test]
+    fn mutate_one_leaf_only() {
+        let mut arg = composite();
+        let mut mem = [0u8; 8];
+        assert!(arg.mutate(&mut mem, &[]));
+        assert_eq!(1, mem.iter().filter(|&&c| 0xff == c).count());
+        assert!(0xff == mem[1] || 0xff == mem[6]);
+    }
+}
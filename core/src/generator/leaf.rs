@@ -1,5 +1,12 @@
 use super::serialize::ISerializableArg;
 
+/// value worth to try for ( part of ) argument, bytes placed at offset of mem
+pub struct InterestingValue {
+    /// 0 for leafs, composite shifts values of its leafs by their offset
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
 /// abstraction for Argument of {sys/api/..}call
 ///
 /// - we force user to be serializable -> to ensure backward POC compatibility
@@ -46,4 +53,52 @@ pub trait IArgLeaf : ISerializableArg {
         }
         self.generate_unsafe(mem, fd);
     }
+
+    /// mutation counterpart of generate, mem holds data which worked before ( loaded trough load )
+    ///
+    /// - tweak them a bit instead of generating from scratch, struct which got trough validation is not thrown away
+    /// - returns false if leaf kept mem intact ( Const, fds, .. ), so caller can try other one
+    /// - default : generate again, as leaf does not know anything better
+    fn mutate_unsafe(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        self.generate_unsafe(mem, fd);
+        true
+    }
+
+    /// wrapping mutate_unsafe, to check slice length corectness!
+    fn mutate(&mut self, mem: &mut[u8], fd: &[u8]) -> bool {
+        if mem.len() != self.size() {
+            panic!("trying to mutate Argument with wrong size {} -> {} vs {}", self.name(), mem.len(), self.size());
+        }
+        self.mutate_unsafe(mem, fd)
+    }
+
+    /// edges, special bit combinations, empty strings, .. which leaf knows about its data
+    ///
+    /// - used by mutation, but can be used by any strategy without knowing concrete leaf
+    /// - default : nothing special known
+    fn interesting_values(&self) -> Vec<InterestingValue> { Vec::new() }
+
+    /// data which belongs to argument, but lives outside of mem ( memory behind Ptr f.e. )
+    ///
+    /// - mem itself is recorded as it is, this is only for what poc recording would miss
+    /// - default : leaf is whole in mem
+    fn dump(&self, _: &[u8]) -> Vec<u8> { Vec::new() }
+
+    /// replay counterpart of generate, instead of generating we load recorded data
+    ///
+    /// - data is recorded content of mem, extra is what was dumped by this ( and following ) leafs
+    /// - returns how much of extra was consumed by this leaf
+    /// - default : copy recorded data as they are, override once data are runtime specific ( fd, pointer, .. )
+    fn load_unsafe(&mut self, mem: &mut[u8], data: &[u8], _extra: &[u8], _fd: &[u8]) -> usize {
+        mem.copy_from_slice(data);
+        0
+    }
+
+    /// wrapping load_unsafe, to check slice length corectness!
+    fn load(&mut self, mem: &mut[u8], data: &[u8], extra: &[u8], fd: &[u8]) -> usize {
+        if mem.len() != self.size() || data.len() != self.size() {
+            panic!("trying to load Argument with wrong size {} -> {} / {} vs {}", self.name(), mem.len(), data.len(), self.size());
+        }
+        self.load_unsafe(mem, data, extra, fd)
+    }
 }
@@ -5,11 +5,18 @@ extern crate lazy_static;
 extern crate serde_derive;
 
 extern crate rand;
+extern crate serde;
+extern crate toml;
+extern crate serde_json;
+extern crate libc;
 
 pub mod generator;
 pub mod exec;
 pub mod state;
 pub mod banana;
 pub mod config;
+pub mod context;
+pub mod poc;
+pub mod rng;
 
 extern crate generic;
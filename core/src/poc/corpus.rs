@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+use std::io;
+use std::path::Path;
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+extern crate rand;
+use rand::Rng;
+
+use serde_json;
+
+use context;
+use rng;
+use exec::call::Call;
+use exec::coverage;
+use exec::id::CallTableId;
+use state::id::StateTableId;
+use state::state::StateInfo;
+use super::replay::ReplayPlan;
+use super::trace::{
+    CallRecord,
+    Trace,
+    TraceHeader,
+    TRACE_VERSION,
+};
+
+/// why state made it to corpus
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// some call hit bitmap indexes first
+    Coverage,
+    /// state reached level no state of its id reached before
+    Level,
+    /// call succeeded first time for state of its id
+    Success,
+}
+
+/// metadata of corpus entry, <name>.json next to its trace <name>.jsonl
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EntryMeta {
+    /// hash of calls + their arguments, entries with same calls are one entry
+    pub name: String,
+    pub state: String,
+    pub sid: StateTableId,
+    /// unix time in seconds
+    pub created: u64,
+    pub calls: usize,
+    pub reasons: Vec<Reason>,
+    /// levels state went trough
+    pub levels: Vec<usize>,
+    /// calls which succeeded
+    pub successes: Vec<CallTableId>,
+    /// bitmap indexes found first, by all its calls
+    pub coverage: usize,
+}
+
+struct Entry {
+    meta: EntryMeta,
+    calls: Vec<CallRecord>,
+}
+
+/// entries + what was already seen, so only novelty makes it in
+///
+/// - one per context ( FuzzerContext::corpus ), loaded from dir of its config
+pub(crate) struct Corpus {
+    entries: Vec<Entry>,
+    /// (sid, level)
+    levels: HashSet<(u64, usize)>,
+    /// (sid, cid)
+    successes: HashSet<(u64, u64)>,
+}
+
+impl Corpus {
+    /// entries of previous runs, broken ones are skipped
+    fn load(dir: &str) -> Corpus {
+        let mut corpus = Corpus {
+            entries : Vec::new(),
+            levels : HashSet::new(),
+            successes : HashSet::new(),
+        };
+        if let Err(e) = fs::create_dir_all(dir) {
+            println!("[corpus] unable to create {} : {}", dir, e);
+            return corpus
+        }
+        let files = match fs::read_dir(dir) {
+            Ok(files) => files,
+            Err(e) => {
+                println!("[corpus] unable to read {} : {}", dir, e);
+                return corpus
+            },
+        };
+        for file in files.filter_map(|file| file.ok()) {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| "json" != ext) {
+                continue
+            }
+            match Corpus::load_entry(&path) {
+                Ok(entry) => corpus.add(entry),
+                Err(e) => println!("[corpus] skipping {} : {}", path.display(), e),
+            }
+        }
+        corpus
+    }
+
+    fn load_entry(path: &Path) -> Result<Entry, io::Error> {
+        let meta: EntryMeta = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let trace = Trace::load(&path.with_extension("jsonl").to_string_lossy())?;
+        Ok(Entry { meta, calls : trace.calls })
+    }
+
+    fn add(&mut self, entry: Entry) {
+        let StateTableId::Id(sid) = entry.meta.sid;
+        self.levels.extend(entry.meta.levels.iter().map(|&level| (sid, level)));
+        self.successes.extend(entry.meta.successes.iter().map(|&CallTableId::Id(cid)| (sid, cid)));
+        self.entries.push(entry);
+    }
+
+    /// store entry, replacing previous one of same state ( prefix of this one )
+    fn save(&mut self, entry: Entry, previous: Option<&str>, dir: &str) -> Result<(), io::Error> {
+        let fname = Path::new(dir).join(&entry.meta.name);
+        let mut trace = Trace::new(TraceHeader {
+            version : TRACE_VERSION,
+            created : entry.meta.created,
+            seed : rng::seed(),
+        });
+        trace.calls = entry.calls.clone();
+        trace.save(&fname.with_extension("jsonl").to_string_lossy())?;
+        let meta = serde_json::to_string(&entry.meta)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        fs::write(fname.with_extension("json"), meta)?;
+
+        if let Some(previous) = previous {
+            if previous != entry.meta.name {
+                self.entries.retain(|old| old.meta.name != previous);
+                let fname = Path::new(dir).join(previous);
+                let _ = fs::remove_file(fname.with_extension("json"));
+                let _ = fs::remove_file(fname.with_extension("jsonl"));
+            }
+        }
+        self.entries.retain(|old| old.meta.name != entry.meta.name);
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+/// corpus of current context, loaded by first one asking; None without [corpus]
+fn with_corpus<F, R>(f: F) -> Option<R>
+    where F: FnOnce(&mut Corpus) -> R
+{
+    let ctx = context::current();
+    let config = ctx.config();
+    let cfg = config.corpus.as_ref()?;
+    let mut corpus = ctx.corpus().lock().ok()?;
+    Some(f(corpus.get_or_insert_with(|| Corpus::load(&cfg.dir))))
+}
+
+fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(time) => time.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+/// calls of one state from its creation, saved to corpus once state gets somewhere new
+///
+/// - owned by State, only states created by ctor have one ( dups do not have their creation )
+/// - calls replayed from corpus / seeds are recorded too, but are not novelty of this run
+pub struct Recorder {
+    calls: Vec<CallRecord>,
+    reasons: Vec<Reason>,
+    levels: Vec<usize>,
+    successes: Vec<CallTableId>,
+    coverage: usize,
+    /// name of entry saved so far by this state
+    saved: Option<String>,
+}
+
+impl Recorder {
+    /// None when corpus is not configured
+    pub fn new() -> Option<Recorder> {
+        context::config().corpus.as_ref()?;
+        Some(Recorder {
+            calls : Vec::new(),
+            reasons : Vec::new(),
+            levels : Vec::new(),
+            successes : Vec::new(),
+            coverage : 0,
+            saved : None,
+        })
+    }
+
+    /// call was just executed by state
+    pub fn executed(&mut self, info: &StateInfo, call: &Call, replaying: bool) {
+        self.calls.push(CallRecord {
+            seq : self.calls.len() as u64,
+            tid : 0,
+            stream : rng::stream(),
+            state : info.name.clone(),
+            sid : info.id.clone(),
+            fd : info.fd.data().to_vec(),
+            level : call.level(),
+            index : call.index(),
+            cid : call.id(),
+            call : call.name().to_string(),
+            args : call.record().to_vec(),
+            poc : String::new(),
+        });
+
+        let new = coverage::last().new;
+        self.coverage += new;
+        let mut reason = None;
+        if 0 != new && !replaying {
+            reason = Some(Reason::Coverage)
+        }
+        if call.ok() && !self.successes.contains(&call.id()) {
+            self.successes.push(call.id());
+            let (StateTableId::Id(sid), CallTableId::Id(cid)) = (info.id.clone(), call.id());
+            let first = with_corpus(|corpus| corpus.successes.insert((sid, cid))).unwrap_or(false);
+            if first && !replaying && reason.is_none() {
+                reason = Some(Reason::Success)
+            }
+        }
+        if let Some(reason) = reason {
+            self.save(info, reason)
+        }
+    }
+
+    /// state moved to level, after call recorded by executed
+    pub fn leveled(&mut self, info: &StateInfo, level: usize, replaying: bool) {
+        if self.levels.contains(&level) {
+            return
+        }
+        self.levels.push(level);
+        let StateTableId::Id(sid) = info.id.clone();
+        let first = with_corpus(|corpus| corpus.levels.insert((sid, level))).unwrap_or(false);
+        if first && !replaying {
+            self.save(info, Reason::Level)
+        }
+    }
+
+    fn name(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        for call in self.calls.iter() {
+            let CallTableId::Id(cid) = call.cid;
+            cid.hash(&mut hasher);
+            for arg in call.args.iter() {
+                arg.data.hash(&mut hasher);
+                arg.extra.hash(&mut hasher);
+            }
+        }
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn save(&mut self, info: &StateInfo, reason: Reason) {
+        let config = context::config();
+        let cfg = match config.corpus {
+            Some(ref cfg) => cfg,
+            None => return,
+        };
+        if !self.reasons.contains(&reason) {
+            self.reasons.push(reason);
+        }
+        let entry = Entry {
+            meta : EntryMeta {
+                name : self.name(),
+                state : info.name.clone(),
+                sid : info.id.clone(),
+                created : now(),
+                calls : self.calls.len(),
+                reasons : self.reasons.clone(),
+                levels : self.levels.clone(),
+                successes : self.successes.clone(),
+                coverage : self.coverage,
+            },
+            calls : self.calls.clone(),
+        };
+        let name = entry.meta.name.clone();
+        let saved = match with_corpus(|corpus| corpus.save(entry, self.saved.as_deref(), &cfg.dir)) {
+            Some(saved) => saved,
+            None => return,
+        };
+        match saved {
+            Ok(_) => {
+                if config.noisy {
+                    println!("[corpus] {} {:?} : {} calls saved as {}", info.name, reason, self.calls.len(), name);
+                }
+                self.saved = Some(name)
+            },
+            Err(e) => println!("[corpus] unable to save {} to {} : {}", name, cfg.dir, e),
+        }
+    }
+}
+
+/// State creation asks for prefix to replay, with probability of replay_ratio
+///
+/// - random entry of same state, replayed trough poc::replay, then state is fuzzed as usual
+/// - prefix is isolated from replay of trace ( turnstile + fd map ), both may run at once
+pub fn take(id: &StateTableId) -> Option<ReplayPlan> {
+    let config = context::config();
+    let cfg = config.corpus.as_ref()?;
+    if !rng::thread_rng().gen_bool(cfg.replay_ratio.clamp(0.0, 1.0)) {
+        return None
+    }
+    with_corpus(|corpus| {
+        let entries = corpus.entries
+            .iter()
+            .filter(|entry| entry.meta.sid == *id)
+            .collect::< Vec<&Entry> >();
+        if entries.is_empty() {
+            return None
+        }
+        let entry = entries[rng::thread_rng().gen_range(0..entries.len())];
+        Some(ReplayPlan::prefix(id.clone(), entry.calls.clone()))
+    })?
+}
+
+/// entries in corpus of current context so far, of all states
+pub fn entries() -> Vec<EntryMeta> {
+    with_corpus(|corpus| corpus.entries.iter().map(|entry| entry.meta.clone()).collect())
+        .unwrap_or_default()
+}
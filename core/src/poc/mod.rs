@@ -0,0 +1,3 @@
+pub mod trace;
+pub mod replay;
+pub mod corpus;
@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+use std::sync::{
+    Condvar,
+    Mutex,
+    RwLock,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use context;
+use exec::fd_info::Fd;
+use state::id::StateTableId;
+use super::trace::{
+    CallRecord,
+    Trace,
+};
+
+/// recorded calls of one state ( one fuzzing thread ) to be replayed instead of random fuzzing
+pub struct ReplayPlan {
+    pub sid: StateTableId,
+    /// fd of state at its first recorded call
+    ///
+    /// - invalid for states created by ctor call
+    /// - valid for duped ones ( racers, accept-like calls, .. )
+    pub fd: Fd,
+    pub calls: VecDeque<CallRecord>,
+    /// once plan is exhausted continue by regular fuzzing, otherwise close state
+    pub fuzz_after: bool,
+    /// dtor was replayed, state is done
+    pub closed: bool,
+    /// corpus prefix, not part of replayed trace : its seq and fds are not ours to share
+    ///
+    /// - skips turnstile, seq of prefix would clash with ordered replay
+    /// - skips fd map, otherwise stale recorded fds would remap args of replayed trace
+    pub isolated: bool,
+}
+
+impl ReplayPlan {
+    pub fn new(sid: StateTableId, fd: Fd, calls: Vec<CallRecord>, fuzz_after: bool) -> ReplayPlan {
+        ReplayPlan {
+            sid,
+            fd,
+            calls : calls.into_iter().collect(),
+            fuzz_after,
+            closed : false,
+            isolated : false,
+        }
+    }
+    /// plan replayed on its own, see isolated
+    pub fn prefix(sid: StateTableId, calls: Vec<CallRecord>) -> ReplayPlan {
+        ReplayPlan {
+            isolated : true,
+            ..ReplayPlan::new(sid, Fd::empty(), calls, true)
+        }
+    }
+    pub fn duped(&self) -> bool { !self.fd.is_invalid() }
+}
+
+/// keeps order of calls across threads as it was recorded ( seq )
+///
+/// - once some thread did not make it to its call in time, we dont wait for it anymore
+struct Turnstile {
+    pending: VecDeque<u64>,
+    timeout: Duration,
+}
+
+/// replay of one context ( FuzzerContext::replay ), other sessions in process replay their own
+pub(crate) struct Session {
+    /// recorded fd -> live fd, fds differ per run, but calls referencing them must connect
+    fdmap: RwLock< Vec<(Vec<u8>, Vec<u8>)> >,
+    /// plans waiting for State to be created
+    plans: Mutex< Vec<ReplayPlan> >,
+    turnstile: (Mutex<Turnstile>, Condvar),
+}
+
+impl Session {
+    pub(crate) fn new() -> Session {
+        Session {
+            fdmap : RwLock::new(Vec::new()),
+            plans : Mutex::new(Vec::new()),
+            turnstile : (
+                Mutex::new(Turnstile { pending : VecDeque::new(), timeout : Duration::from_secs(0) }),
+                Condvar::new()),
+        }
+    }
+}
+
+/// prepare trace for replay, in current context
+///
+/// - every recorded thread become one plan, picked up by first created State of same id
+///   ( and fd in case of dups )
+/// - ordered : keep recorded order of calls across threads, waiting at most given time per call
+/// - returns what states need to be created, in order of their first call : (id, recorded fd)
+///   - invalid fd means create new state, otherwise dup ( wait for live_fd of recorded one )
+pub fn schedule(trace: &Trace, fuzz_after: bool, ordered: Option<Duration>) -> Vec<(StateTableId, Fd)> {
+    let plans = trace.threads()
+        .into_iter()
+        .map(|(_, calls)| ReplayPlan::new(
+                calls[0].sid.clone(),
+                Fd::new(&calls[0].fd),
+                calls.into_iter().cloned().collect(),
+                fuzz_after))
+        .collect::< Vec<ReplayPlan> >();
+
+    let ctx = context::current();
+    let session = ctx.replay();
+    if let Some(timeout) = ordered {
+        if let Ok(mut turnstile) = session.turnstile.0.lock() {
+            turnstile.pending = trace.calls.iter().map(|call| call.seq).collect();
+            turnstile.timeout = timeout;
+        }
+    }
+
+    let spawns = plans
+        .iter()
+        .map(|plan| (plan.sid.clone(), plan.fd.clone()))
+        .collect();
+    match session.plans.lock() {
+        Ok(mut pending) => pending.extend(plans),
+        Err(e) => panic!("replay plans poisoned {}", e),
+    }
+    spawns
+}
+
+/// State creation asks if it should replay instead of fuzz
+///
+/// - fd : None for new state, Some(fd) for duped one
+pub fn take(id: &StateTableId, fd: Option<&Fd>) -> Option<ReplayPlan> {
+    let ctx = context::current();
+    let mut plans = match ctx.replay().plans.lock() {
+        Ok(plans) => plans,
+        Err(_) => return None,
+    };
+    if plans.is_empty() {
+        return None
+    }
+    let ind = match fd {
+        None => plans
+            .iter()
+            .position(|plan| plan.sid == *id && !plan.duped()),
+        Some(fd) => plans
+            .iter()
+            .position(|plan| plan.sid == *id && plan.duped() && remap(plan.fd.data()) == fd.data())
+            .or_else(|| plans
+                .iter()
+                .position(|plan| plan.sid == *id && plan.duped())),
+    };
+    ind.map(|ind| plans.remove(ind))
+}
+
+/// how many plans are still waiting for their State
+pub fn pending() -> usize {
+    match context::current().replay().plans.lock() {
+        Ok(plans) => plans.len(),
+        Err(_) => 0,
+    }
+}
+
+/// we know live fd of recorded one
+pub fn learn(recorded: &[u8], live: &[u8]) {
+    if Fd::new(recorded).is_invalid() || recorded.len() != live.len() {
+        return
+    }
+    if let Ok(mut map) = context::current().replay().fdmap.write() {
+        match map.iter().position(|(rec, _)| rec[..] == recorded[..]) {
+            Some(ind) => map[ind].1 = live.to_vec(),
+            None => map.push((recorded.to_vec(), live.to_vec())),
+        }
+    }
+}
+
+/// translate recorded fd to live one, unknown ones are kept as they are
+pub fn remap(fd: &[u8]) -> Vec<u8> {
+    match live_fd(fd) {
+        Some(live) => live.data().to_vec(),
+        None => fd.to_vec(),
+    }
+}
+
+pub fn live_fd(recorded: &[u8]) -> Option<Fd> {
+    match context::current().replay().fdmap.read() {
+        Ok(map) => map
+            .iter()
+            .find(|&(rec, _)| rec[..] == recorded[..])
+            .map(|(_, live)| Fd::new(live)),
+        Err(_) => None,
+    }
+}
+
+/// block until all calls recorded before seq are replayed ( or timeout )
+pub fn wait(seq: u64) {
+    let ctx = context::current();
+    let (lock, cvar) = &ctx.replay().turnstile;
+    let mut turnstile = match lock.lock() {
+        Ok(turnstile) => turnstile,
+        Err(_) => return,
+    };
+    let start = Instant::now();
+    loop {
+        match turnstile.pending.front() {
+            Some(&first) if first < seq => (),
+            _ => return,
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= turnstile.timeout {
+            // someone before us did not make it, dont wait for them anymore
+            turnstile.pending.retain(|&pending| pending >= seq);
+            return
+        }
+        let timeout = turnstile.timeout - elapsed;
+        turnstile = match cvar.wait_timeout(turnstile, timeout) {
+            Ok((turnstile, _)) => turnstile,
+            Err(_) => return,
+        };
+    }
+}
+
+/// call recorded as seq was replayed, let others go
+pub fn done(seq: u64) {
+    let ctx = context::current();
+    let (lock, cvar) = &ctx.replay().turnstile;
+    if let Ok(mut turnstile) = lock.lock() {
+        turnstile.pending.retain(|&pending| pending != seq);
+    }
+    cvar.notify_all();
+}
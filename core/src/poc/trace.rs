@@ -0,0 +1,198 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::fs::File;
+
+use serde_json;
+
+use exec::id::CallTableId;
+use state::id::StateTableId;
+
+/// bump once TraceEntry layout change, older traces must stay readable ( new fields with default )
+///
+/// - 2 : call slot ( level, index ) + extra data of arguments
+/// - 3 : serialized call ( Call::serialize ) for PoC program
+/// - 4 : master seed + rng stream of thread, see rng
+pub const TRACE_VERSION: u32 = 4;
+
+/// first line of every trace
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TraceHeader {
+    pub version: u32,
+    /// unix time in seconds, when recording started
+    pub created: u64,
+    /// master seed of recorded run, set it as config seed ( + singlethread ) to repeat run
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// raw bytes of one argument, exactly as they were handed over to ccall
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArgRecord {
+    pub data: Vec<u8>,
+    /// memory living outside of argument ( behind Ptr f.e. ), see IArgLeaf::dump
+    #[serde(default)]
+    pub extra: Vec<u8>,
+}
+
+/// one approved call, snapshot took right before ccall
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CallRecord {
+    /// global order of calls, across all threads
+    pub seq: u64,
+    /// thread which invoked call, one thread ~ one fuzzed state
+    pub tid: u64,
+    /// rng stream of thread, derived from seed of header
+    #[serde(default)]
+    pub stream: u64,
+    pub state: String,
+    pub sid: StateTableId,
+    /// fd of state at time of call, empty or invalid for ctors
+    pub fd: Vec<u8>,
+    /// level of state at time of call, !0 for dtor
+    #[serde(default)]
+    pub level: usize,
+    /// index of call in group of its level
+    #[serde(default)]
+    pub index: usize,
+    pub cid: CallTableId,
+    pub call: String,
+    pub args: Vec<ArgRecord>,
+    /// Call::serialize output, c++ statement reproducing this call in PoC
+    #[serde(default)]
+    pub poc: String,
+}
+
+/// free form line from modules ( ModuleCallbacks::log_call ), keeps its place in sequence
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NoteRecord {
+    pub seq: u64,
+    pub tid: u64,
+    pub cmd: String,
+    pub info: String,
+}
+
+/// one line of trace file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind")]
+pub enum TraceEntry {
+    Header(TraceHeader),
+    Call(CallRecord),
+    Note(NoteRecord),
+}
+
+impl TraceEntry {
+    /// one json object per line, therefore crash in middle of write can cost us at most last line
+    pub fn to_line(&self) -> String {
+        match serde_json::to_string(self) {
+            Ok(line) => line + "\n",
+            Err(e) => panic!("trace entry is not serializable : {}", e),
+        }
+    }
+}
+
+/// recorded run, in memory
+pub struct Trace {
+    pub header: TraceHeader,
+    pub calls: Vec<CallRecord>,
+    pub notes: Vec<NoteRecord>,
+}
+
+impl Trace {
+    pub fn new(header: TraceHeader) -> Trace {
+        Trace {
+            header,
+            calls : Vec::new(),
+            notes : Vec::new(),
+        }
+    }
+
+    /// load trace from disk
+    ///
+    /// - trace likely ends in the middle of line ( target crashed us ), such lines are skipped
+    /// - newer version than we understand is refused
+    pub fn load(fname: &str) -> Result<Trace, io::Error> {
+        let mut trace: Option<Trace> = None;
+        for line in BufReader::new(File::open(fname)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let entry: TraceEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(_) => continue,//torn write
+            };
+            match (entry, trace.as_mut()) {
+                (TraceEntry::Header(header), None) => {
+                    if header.version > TRACE_VERSION {
+                        return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("trace {} version {} is newer than supported {}", fname, header.version, TRACE_VERSION)))
+                    }
+                    trace = Some(Trace::new(header))
+                },
+                (TraceEntry::Call(call), Some(trace)) => trace.calls.push(call),
+                (TraceEntry::Note(note), Some(trace)) => trace.notes.push(note),
+                _ => return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("trace {} is missing header", fname))),
+            }
+        }
+        match trace {
+            Some(mut trace) => {
+                trace.calls.sort_by_key(|call| call.seq);
+                Ok(trace)
+            },
+            None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("trace {} is empty", fname))),
+        }
+    }
+
+    pub fn save(&self, fname: &str) -> Result<(), io::Error> {
+        let mut file = File::create(fname)?;
+        file.write_all(TraceEntry::Header(self.header.clone()).to_line().as_bytes())?;
+        for call in self.calls.iter() {
+            file.write_all(TraceEntry::Call(call.clone()).to_line().as_bytes())?;
+        }
+        for note in self.notes.iter() {
+            file.write_all(TraceEntry::Note(note.clone()).to_line().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// last n calls of every thread ( with notes of that time ), context of crash
+    pub fn tail(&self, n: usize) -> Trace {
+        let since = self.threads()
+            .into_iter()
+            .map(|(tid, calls)| (tid, calls[calls.len().saturating_sub(n.max(1))].seq))
+            .collect::< Vec<(u64, u64)> >();
+        let keep = |tid: u64, seq: u64| since
+            .iter()
+            .any(|&(since_tid, since_seq)| since_tid == tid && seq >= since_seq);
+
+        let mut tail = Trace::new(self.header.clone());
+        tail.calls = self.calls
+            .iter()
+            .filter(|call| keep(call.tid, call.seq))
+            .cloned()
+            .collect();
+        tail.notes = self.notes
+            .iter()
+            .filter(|note| keep(note.tid, note.seq))
+            .cloned()
+            .collect();
+        tail
+    }
+
+    /// split calls per thread, threads ordered by their first call
+    pub fn threads(&self) -> Vec< (u64, Vec<&CallRecord>) > {
+        let mut threads: Vec< (u64, Vec<&CallRecord>) > = Vec::new();
+        for call in self.calls.iter() {
+            match threads.iter().position(|&(tid, _)| tid == call.tid) {
+                Some(ind) => threads[ind].1.push(call),
+                None => threads.push((call.tid, vec![call])),
+            }
+        }
+        threads
+    }
+}
@@ -0,0 +1,162 @@
+use std::cell::{
+    Cell,
+    RefCell,
+};
+use std::thread;
+
+extern crate rand;
+use rand::{
+    Error,
+    RngCore,
+    SeedableRng,
+};
+use rand::rngs::StdRng;
+
+use context::{
+    self,
+    FuzzerContext,
+};
+
+// master seed + streams handed out are per context ( FuzzerContext::seed ), so sessions in one
+// process do not share randomness; thread draws from context it was seeded in
+
+thread_local! {
+    static STREAM: Cell<u64> = const { Cell::new(0) };
+    /// (context id, rng), reseeded once thread enters other context
+    static RNG: RefCell< Option<(u64, StdRng)> > = const { RefCell::new(None) };
+}
+
+/// splitmix64 finalizer, close seeds ( streams 1, 2, .. ) become unrelated ones
+fn mix(val: u64) -> u64 {
+    let mut val = val.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    val = (val ^ (val >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    val = (val ^ (val >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    val ^ (val >> 31)
+}
+
+/// rng of stream, derived from master seed
+fn stream_rng(seed: u64, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(mix(seed ^ mix(stream)))
+}
+
+fn seeded(ctx: &FuzzerContext, stream: u64) -> (u64, StdRng) {
+    STREAM.with(|cur| cur.set(stream));
+    (ctx.id(), stream_rng(ctx.seed(), stream))
+}
+
+/// master seed of current context
+pub fn seed() -> u64 { context::current().seed() }
+
+/// stream of current thread, seed of its rng is derived from it + master seed
+pub fn stream() -> u64 {
+    with(|_| ());
+    STREAM.with(|cur| cur.get())
+}
+
+/// reserve stream for thread about to be spawned
+///
+/// - call it in spawning thread, so streams follow order of spawns and not of thread scheduling
+/// - spawned thread then starts with init(stream), once it entered context of spawning thread
+pub fn spawn() -> u64 {
+    context::current().stream()
+}
+
+/// (re)seed rng of current thread, by master seed of its current context
+pub fn init(stream: u64) {
+    let ctx = context::current();
+    RNG.with(|rng| *rng.borrow_mut() = Some(seeded(&ctx, stream)));
+}
+
+/// rng of current thread, seeded lazily if thread did not init it
+///
+/// - main thread gets stream 0, other threads next free stream ( order of first use, not reproducible )
+/// - thread which entered other context since is reseeded by that one
+pub fn with<F, R>(f: F) -> R
+    where F: FnOnce(&mut StdRng) -> R
+{
+    RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        let ctx = context::current();
+        if rng.as_ref().is_none_or(|&(id, _)| id != ctx.id()) {
+            let stream = match thread::current().name() {
+                Some("main") => 0,
+                _ => ctx.stream(),
+            };
+            *rng = Some(seeded(&ctx, stream));
+        }
+        match *rng {
+            Some((_, ref mut rng)) => f(rng),
+            None => unreachable!(),
+        }
+    })
+}
+
+/// handle to rng of current thread, drop-in for rand::thread_rng()
+///
+/// - every random decision of fuzzing ( leafs, selection, sleeps, modules ) should draw from it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FuzzRng;
+
+impl RngCore for FuzzRng {
+    fn next_u32(&mut self) -> u32 { with(|rng| rng.next_u32()) }
+    fn next_u64(&mut self) -> u64 { with(|rng| rng.next_u64()) }
+    fn fill_bytes(&mut self, dest: &mut [u8]) { with(|rng| rng.fill_bytes(dest)) }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> { with(|rng| rng.try_fill_bytes(dest)) }
+}
+
+pub fn thread_rng() -> FuzzRng { FuzzRng }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    use config::FuzzyConfig;
+
+    fn values(count: usize) -> Vec<u64> {
+        (0..count).map(|_| thread_rng().gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_stream() {
+        let (mut one, mut two) = (stream_rng(7, 3), stream_rng(7, 3));
+        for _ in 0..16 {
+            assert_eq!(one.next_u64(), two.next_u64());
+        }
+        assert_ne!(stream_rng(7, 3).next_u64(), stream_rng(7, 4).next_u64());
+        assert_ne!(stream_rng(7, 3).next_u64(), stream_rng(8, 3).next_u64());
+        // seed and stream are not interchangeable
+        assert_ne!(stream_rng(0, 1).next_u64(), stream_rng(1, 0).next_u64());
+    }
+
+    #[test]
+    fn init_replays_stream() {
+        let ctx = FuzzerContext::new(FuzzyConfig { seed : Some(0xdead), ..FuzzyConfig::default() });
+        context::enter(ctx);
+        init(5);
+        let first = values(8);
+        assert_eq!(5, stream());
+        init(5);
+        assert_eq!(first, values(8));
+        init(6);
+        assert_ne!(first, values(8));
+        context::leave();
+    }
+
+    #[test]
+    fn other_context_reseeds() {
+        let one = FuzzerContext::new(FuzzyConfig { seed : Some(1), ..FuzzyConfig::default() });
+        let two = FuzzerContext::new(FuzzyConfig { seed : Some(1), ..FuzzyConfig::default() });
+        context::enter(one.clone());
+        init(2);
+        let first = values(4);
+        // same seed + stream in other context draws same values, not continuation of one
+        context::enter(two);
+        init(2);
+        assert_eq!(first, values(4));
+        context::enter(one);
+        assert_eq!(1, seed());
+        assert_ne!(first, values(4));
+        context::leave();
+    }
+}
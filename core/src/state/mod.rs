@@ -1,3 +1,3 @@
 pub mod state;
 pub mod id;
-
+pub mod select;
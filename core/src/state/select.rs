@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+
+extern crate rand;
+use rand::Rng;
+
+use config::SelectionStrategy;
+use context;
+use exec::call::Call;
+use rng;
+
+/// which call of current group should be tried next
+///
+/// - one instance per State, so feedback is per object + its lifetime, as Call::{total, success} are
+/// - dead calls are never offered, State retries while picked call is denied by observers
+///     - every denial is reported back by denied, so strategy does not offer the same call again and again
+pub trait ISelect : Send + Sync {
+    /// index of call in group at level, None if there is nothing alive to pick from
+    fn pick(&mut self, level: usize, group: &[Call]) -> Option<usize>;
+    /// picked call was executed, new is how many bitmap indexes it hit first ( 0 without coverage )
+    fn feedback(&mut self, _call: &Call, _new: usize) {}
+    /// picked call was denied by observers ( or failed to be called ), no result to learn from
+    fn denied(&mut self, _call: &Call) {}
+}
+
+fn alive(group: &[Call]) -> Vec<usize> {
+    group
+        .iter()
+        .enumerate()
+        .filter(|&(_, call)| !call.dead())
+        .map(|(ind, _)| ind)
+        .collect()
+}
+
+fn uniform(group: &[Call]) -> Option<usize> {
+    let alive = alive(group);
+    if alive.is_empty() {
+        return None
+    }
+    Some(alive[rng::thread_rng().gen_range(0..alive.len())])
+}
+
+/// original behaviour, every alive call of group has same chance
+pub struct Uniform;
+
+impl ISelect for Uniform {
+    fn pick(&mut self, _level: usize, group: &[Call]) -> Option<usize> {
+        uniform(group)
+    }
+}
+
+/// roulette by weight of call, laplace smoothed so calls without history are not starved
+///
+/// - success : (1 + ok) / (1 + total), prefers calls target accepts
+/// - novelty : (1 + new) / (1 + total), prefers calls which found new coverage, without coverage
+///   it prefers rarely picked ones
+pub struct Weighted {
+    strategy: SelectionStrategy,
+    /// new coverage per call, [level][index]
+    novelty: Vec< Vec<usize> >,
+}
+
+impl Weighted {
+    pub fn new(strategy: SelectionStrategy, groups: &[Vec<Call>]) -> Weighted {
+        Weighted {
+            strategy,
+            novelty : groups.iter().map(|group| vec![0; group.len()]).collect(),
+        }
+    }
+
+    fn weight(&self, call: &Call) -> f64 {
+        let hits = match self.strategy {
+            SelectionStrategy::Success => call.success(),
+            SelectionStrategy::Novelty => self.novelty[call.level()][call.index()],
+            _ => 0,
+        };
+        (1 + hits) as f64 / (1 + call.total()) as f64
+    }
+}
+
+impl ISelect for Weighted {
+    fn pick(&mut self, _level: usize, group: &[Call]) -> Option<usize> {
+        let alive = alive(group);
+        let weights = alive
+            .iter()
+            .map(|&ind| self.weight(&group[ind]))
+            .collect::< Vec<f64> >();
+        let sum = weights.iter().sum::<f64>();
+        if alive.is_empty() || sum <= 0.0 {
+            return None
+        }
+        let mut roll = rng::thread_rng().gen_range(0.0..sum);
+        for (&ind, weight) in alive.iter().zip(weights) {
+            if roll < weight {
+                return Some(ind)
+            }
+            roll -= weight;
+        }
+        alive.last().cloned()
+    }
+    fn feedback(&mut self, call: &Call, new: usize) {
+        if call.is_dtor() {
+            return
+        }
+        self.novelty[call.level()][call.index()] += new;
+    }
+}
+
+/// multi-armed bandit, UCB1 per level
+///
+/// - reward of call : half for success, half for new coverage
+/// - calls never picked go first, then mean reward + exploration bonus of rarely picked ones
+/// - denied call counts as pull with zero reward, otherwise never picked one would be offered forever
+pub struct Bandit {
+    /// exploration factor, sqrt(2) by the book
+    factor: f64,
+    /// (picked, reward sum) per call, [level][index]
+    arms: Vec< Vec<(usize, f64)> >,
+}
+
+impl Bandit {
+    pub fn new(factor: f64, groups: &[Vec<Call>]) -> Bandit {
+        Bandit {
+            factor,
+            arms : groups.iter().map(|group| vec![(0, 0.0); group.len()]).collect(),
+        }
+    }
+}
+
+impl ISelect for Bandit {
+    fn pick(&mut self, level: usize, group: &[Call]) -> Option<usize> {
+        let arms = &self.arms[level];
+        let alive = alive(group);
+        if let Some(&ind) = alive.iter().find(|&&ind| 0 == arms[ind].0) {
+            return Some(ind)
+        }
+        let rounds = alive.iter().map(|&ind| arms[ind].0).sum::<usize>() as f64;
+        let score = |ind: usize| {
+            let (picked, reward) = arms[ind];
+            reward / picked as f64 + self.factor * (rounds.ln() / picked as f64).sqrt()
+        };
+        alive
+            .iter()
+            .cloned()
+            .max_by(|&a, &b| score(a).partial_cmp(&score(b)).unwrap_or(Ordering::Equal))
+    }
+    fn feedback(&mut self, call: &Call, new: usize) {
+        if call.is_dtor() {
+            return
+        }
+        let arm = &mut self.arms[call.level()][call.index()];
+        arm.0 += 1;
+        arm.1 += (call.ok() as usize as f64 + (0 != new) as usize as f64) / 2.0;
+    }
+    fn denied(&mut self, call: &Call) {
+        if call.is_dtor() {
+            return
+        }
+        self.arms[call.level()][call.index()].0 += 1;
+    }
+}
+
+/// strategy by config, missing [selection] table == uniform
+pub fn open(groups: &[Vec<Call>]) -> Box<dyn ISelect> {
+    let config = context::config();
+    let cfg = match config.selection {
+        Some(ref cfg) => cfg,
+        None => return Box::new(Uniform),
+    };
+    match cfg.strategy {
+        SelectionStrategy::Uniform => Box::new(Uniform),
+        SelectionStrategy::Success | SelectionStrategy::Novelty => Box::new(Weighted::new(cfg.strategy, groups)),
+        SelectionStrategy::Bandit => Box::new(Bandit::new(cfg.factor, groups)),
+    }
+}
+
+/// pick by strategy, but with probability of config.explore uniformly, so rarely picked calls get
+/// their chance even if strategy locks on favourites
+pub fn pick(select: &mut dyn ISelect, level: usize, group: &[Call]) -> Option<usize> {
+    let explore = context::config().selection.as_ref().map_or(0.0, |cfg| cfg.explore);
+    if explore > 0.0 && rng::thread_rng().gen_bool(explore.min(1.0)) {
+        return uniform(group)
+    }
+    select.pick(level, group)
+}
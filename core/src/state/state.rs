@@ -1,13 +1,17 @@
 use std::cmp::min;
 
-extern crate rand;
-use rand::Rng;
-
-use config::FZZCONFIG;
+use banana::bananaq;
+use context;
 
 use exec::call::Call;
+use exec::coverage;
 use exec::fd_info::Fd;
+use poc::corpus::{self, Recorder};
+use poc::replay;
+use poc::replay::ReplayPlan;
+use poc::trace::CallRecord;
 use super::id::StateTableId;
+use super::select::{self, ISelect};
 
 pub trait IFdState {
     fn invalid(&self) -> bool;
@@ -107,6 +111,22 @@ pub struct State {
     /// - i experienced that state has always only one dtor - or can be dtored by only one call / packet
     ///     - if in future will get knowledge about breaking this rule, then vec![dtors] should be provided!
     dtor: Call,
+    /// recorded calls to replay instead of random selection, see poc::replay
+    replay: Option<ReplayPlan>,
+    /// which call of group to try next, by FuzzyConfig.selection or set by holder of State
+    select: Box<dyn ISelect>,
+    /// calls since creation, saved to corpus once they get somewhere new; None without corpus or for dups
+    corpus: Option<Recorder>,
+}
+
+/// stamp every call with its position, so recorded call can be found again at replay
+fn placed(mut groups: Vec< Vec<Call> >) -> Vec< Vec<Call> > {
+    for (level, group) in groups.iter_mut().enumerate() {
+        for (index, call) in group.iter_mut().enumerate() {
+            call.place(level, index);
+        }
+    }
+    groups
 }
 
 impl State {
@@ -131,35 +151,141 @@ impl State {
     /// generation legacy, you do something too fancy you are likely not able to repro in poc ...
     pub fn call_mut(&mut self) -> &mut Call { &mut self.groups[self.ccache.0][self.ccache.1] }
 
-    /// select random one from current level
+    /// override strategy of config by own one, knowledge of state can guide it better
+    pub fn set_select(&mut self, select: Box<dyn ISelect>) { self.select = select }
+
+    /// select one from current level, and invoke it
     ///
-    /// - maybe intercorporate some ranking of OK syscalls - aka prefer some syscalls at current level
-    ///     - heatmaps, reinforcement learning, other algorithm
-    ///     - currently is opt-ed to do it via modules, but it is perf overkill
-    ///     - therefore if once decide for particular algorithm should be implemented here and
-    ///     switched by config
+    /// - selection strategy is set by config ( uniform, success, novelty, bandit ), see state::select
+    ///     - with probability of explore is picked uniformly anyway, rarely picked calls are not starved
+    ///     - strategy learns from result of call + coverage it found
     /// - also some blacklisting of too often rejected call - very OK by modules
-    /// - and maybe tracking uniformity of selection globaly per State - forbid prefering one syscall by thread_rng ?
-    ///     - in general this can be crucial part of fuzzer which i neglected to tinker with yet..
+    ///     - dead calls are not offered at all
+    /// - at shutdown only dtor is called, and fuzzing of state is over
     pub fn do_fuzz_one(&mut self) -> bool {
         if self.info.total > self.limit {
             return false
         }
+        if bananaq::stopping() {
+            self.call_dtor();
+            return false
+        }
         self.info.total += 1;
+        if let Some(done) = self.do_replay_one() {
+            return done
+        }
         for _ in 0..(self.groups[self.ccache.0].len() * 2) {
-            self.ccache.1 = rand::thread_rng().gen_range(0..self.groups[self.ccache.0].len());
-            if self.call_view().dead() {
-                continue
-            }
+            self.ccache.1 = match select::pick(self.select.as_mut(), self.ccache.0, &self.groups[self.ccache.0]) {
+                Some(index) => index,
+                None => break,
+            };
             let fd = self.info.fd.clone();
             if self.call_mut().do_call(&fd.data()) {
+                self.select.feedback(&self.groups[self.ccache.0][self.ccache.1], coverage::last().new);
+                self.remember(false);
                 return true
             }
+            self.select.denied(&self.groups[self.ccache.0][self.ccache.1]);
         }
         self.call_dtor();
         false
     }
 
+    /// replaying recorded calls instead of fuzzing, until plan is exhausted
+    ///
+    /// - None : nothing to replay, fuzz as usual
+    /// - recorded fd of our state is mapped to live one, so arguments of others can be remapped
+    fn do_replay_one(&mut self) -> Option<bool> {
+        let rec = self.replay.as_mut()?.calls.pop_front();
+        let rec = match rec {
+            Some(rec) => rec,
+            None => {
+                let plan = self.replay.take()?;
+                if plan.closed {
+                    return Some(false)
+                }
+                if plan.fuzz_after {
+                    return None
+                }
+                self.call_dtor();
+                return Some(false)
+            }
+        };
+        // corpus prefix does not share seq nor fds with replayed trace
+        let shared = !self.replay.as_ref().is_some_and(|plan| plan.isolated);
+        if shared && !self.info.fd.is_invalid() {
+            replay::learn(&rec.fd, self.info.fd.data());
+        }
+
+        let fd = self.info.fd.clone();
+        if shared {
+            replay::wait(rec.seq);
+        }
+        let done = match self.find_slot(&rec) {
+            Some(slot) => {
+                self.ccache = slot;
+                let done = self.call_mut().do_replay(fd.data(), &rec.args);
+                if done {
+                    self.remember(true);
+                }
+                done
+            },
+            None => {
+                // args of call we dont know anymore are of other layout, dtor gets fresh ones then
+                if self.dtor.id() == rec.cid {
+                    if !fd.is_invalid() {
+                        self.dtor.do_replay(fd.data(), &rec.args);
+                    }
+                } else {
+                    self.call_dtor();
+                }
+                if let Some(ref mut plan) = self.replay {
+                    plan.calls.clear();
+                    plan.closed = true;
+                }
+                false
+            },
+        };
+        if shared {
+            replay::done(rec.seq);
+        }
+        Some(done)
+    }
+    /// forward call just executed to corpus recorder
+    fn remember(&mut self, replaying: bool) {
+        if let Some(ref mut corpus) = self.corpus {
+            corpus.executed(&self.info, &self.groups[self.ccache.0][self.ccache.1], replaying)
+        }
+    }
+    /// locate recorded call, by recorded slot or by id if call table changed in between
+    ///
+    /// - None means dtor ( or call we dont know anymore, then we end as well )
+    fn find_slot(&self, rec: &CallRecord) -> Option<(usize, usize)> {
+        if rec.level < self.groups.len() &&
+            rec.index < self.groups[rec.level].len() &&
+            self.groups[rec.level][rec.index].id() == rec.cid
+        {
+            return Some((rec.level, rec.index))
+        }
+        if !0 == rec.level {
+            return None
+        }
+        if let Some(index) = self.groups[self.ccache.0]
+            .iter()
+            .position(|call| call.id() == rec.cid)
+        {
+            return Some((self.ccache.0, index))
+        }
+        self.groups
+            .iter()
+            .enumerate()
+            .filter_map(|(level, group)| group
+                .iter()
+                .position(|call| call.id() == rec.cid)
+                .map(|index| (level, index)))
+            .next()
+    }
+
     /// need to be called after do_fuzz_one, to change level based on slopes!
     ///
     /// - call once do_fuzz_one will return true
@@ -172,6 +298,9 @@ impl State {
         //however, if some syscall poorely implemented this will kill fuzzing for whole fuzzy object most
         // likely ...
         if self.do_fuzz_update_impl() {
+            if let Some(ref mut corpus) = self.corpus {
+                corpus.leveled(&self.info, self.ccache.0, self.replay.is_some())
+            }
             return true
         }
         self.call_dtor();
@@ -254,7 +383,7 @@ impl State {
         limit: usize,
         slopes: Vec<[isize; 2]>,
         groups: Vec< Vec<Call> >,
-        dtor: Call
+        mut dtor: Call
         ) -> State
     {
         assert!(slopes.len() == groups.len());
@@ -267,6 +396,9 @@ impl State {
             panic!("one of the group for {} is oversized!", name);
         }
 
+        dtor.mark_dtor();
+        let replay = replay::take(&id, None).or_else(|| corpus::take(&id));
+
         State {
             info : StateInfo {
                 name : String::from(name),
@@ -275,11 +407,14 @@ impl State {
                 fd : Fd::empty(),
                 id : id,
             },
-            limit : min(FZZCONFIG.new_limit, limit),
+            limit : min(context::config().new_limit, limit),
             slopes : slopes,
-            groups : groups,
+            select : select::open(&groups),
+            groups : placed(groups),
             dtor: dtor,
             ccache : (0, !0),
+            replay,
+            corpus : Recorder::new(),
         }
     }
     /// apply as for new, but here we create already existing object :
@@ -296,7 +431,7 @@ impl State {
         limit: usize,
         slopes: Vec<[isize; 2]>,
         groups: Vec< Vec<Call> >,
-        dtor: Call
+        mut dtor: Call
         ) -> State
     {
         assert!(slopes.len() == groups.len());
@@ -309,7 +444,9 @@ impl State {
             panic!("one of the group for {} is oversized!", name);
         }
 
+        dtor.mark_dtor();
         let level = slopes[0][0] as usize;
+        let replay = replay::take(&id, Some(fd));
 
         State {
             info : StateInfo {
@@ -319,11 +456,14 @@ impl State {
                 fd : fd.clone(),
                 id : id,
             },
-            limit : min(FZZCONFIG.dup_limit, limit),
+            limit : min(context::config().dup_limit, limit),
             slopes : slopes,
-            groups : groups,
+            select : select::open(&groups),
+            groups : placed(groups),
             dtor: dtor,
-            ccache : (level, !0)
+            ccache : (level, !0),
+            replay,
+            corpus : None,
         }
     }
 }
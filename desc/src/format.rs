@@ -0,0 +1,292 @@
+/// whole call table of target, as written in .toml
+///
+/// # Example :
+/// ```toml
+/// module = "libc.so.6"
+///
+/// [[state]]
+/// name = "socket"
+/// id = 0x10
+/// fd_size = 4
+/// limit = 40
+/// slopes = [[1, 0], [0, 0]]
+/// groups = [["socket"], ["connect", "shutdown"]]
+/// dtor = "close"
+///
+/// [[call]]
+/// name = "socket"
+/// id = 0x100
+/// fd_ret = true
+/// args = [
+///     { leaf = "const", size = 4, value = 2 },
+///     { leaf = "flag", size = 4, always = 1, flag = 0x80800 },
+///     { leaf = "bounded", size = 4, ranges = [[0, 6]] },
+/// ]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Description {
+    /// library where calls are resolved by default ( libc.so.6, ntdll.dll, .. )
+    #[serde(default)]
+    pub module: String,
+    #[serde(default)]
+    pub state: Vec<StateDesc>,
+    #[serde(default)]
+    pub call: Vec<CallDesc>,
+}
+
+/// State::new counterpart, calls are referenced by name
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StateDesc {
+    pub name: String,
+    pub id: u64,
+    /// size of runtime id of object ( fd, handle, pointer, .. )
+    pub fd_size: usize,
+    pub limit: usize,
+    /// per group [fail, ok] as in State
+    pub slopes: Vec<[isize; 2]>,
+    pub groups: Vec< Vec<String> >,
+    pub dtor: String,
+}
+
+/// how we decide if call was successfull, from its return value
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Success {
+    Zero,
+    #[default]
+    NonNeg,
+    NonZero,
+    Any,
+}
+
+fn ret_size() -> usize { 4 }
+
+/// Call::new counterpart, ccall is symbol resolved at load time
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CallDesc {
+    pub name: String,
+    pub id: u64,
+    /// overrides Description::module
+    #[serde(default)]
+    pub module: String,
+    /// exported name, if differs from name
+    #[serde(default)]
+    pub symbol: String,
+    /// success condition over returned value
+    #[serde(default)]
+    pub ok: Success,
+    /// size of returned value, int by default ( upper part of register is garbage then )
+    #[serde(default = "ret_size")]
+    pub ret_size: usize,
+    /// call returns runtime id of state ( ctor : socket, open, .. )
+    #[serde(default)]
+    pub fd_ret: bool,
+    /// call writes runtime id of state to memory of argument ( ctor : pipe, NtCreateFile, .. )
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fd_arg: Option<usize>,
+    #[serde(default)]
+    pub args: Vec<ArgDesc>,
+}
+
+/// argument of call, passed by value unless memory or bigger than register
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArgDesc {
+    #[serde(default)]
+    pub memory: bool,
+    #[serde(flatten)]
+    pub leaf: Leaf,
+}
+
+/// member of struct, without offset it follows previous one
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Field {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(flatten)]
+    pub leaf: Leaf,
+}
+
+/// 1:1 to api leafs
+///
+/// - numbers are little endian, sizes 1, 2, 4, 8
+/// - deref and fd without size takes fd_size of state
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "leaf", rename_all = "snake_case")]
+pub enum Leaf {
+    Const { size: usize, value: i64 },
+    Bytes { data: Vec<u8> },
+    Flag {
+        size: usize,
+        #[serde(default)]
+        always: i64,
+        flag: i64,
+    },
+    Bounded {
+        size: usize,
+        ranges: Vec<[i64; 2]>,
+        #[serde(default)]
+        signed: bool,
+    },
+    Rnd { size: usize },
+    Pattern { byte: u8, size: usize },
+    Phantom { size: usize },
+    Ptr { to: Box<Leaf> },
+    Deref {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<usize>,
+        #[serde(default)]
+        offset: usize,
+    },
+    /// FdHolder, of rnd_fd / deref / const ..; empty means holder for fd returned trough argument
+    Fd {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<usize>,
+        #[serde(default)]
+        of: Vec<Leaf>,
+    },
+    /// fd of other alive state, by name of state; only inside of fd!
+    ///
+    /// - or by id + size, as StateTableId is matched by bits ( any state of resource and its children )
+    RndFd {
+        #[serde(default)]
+        state: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<usize>,
+    },
+    Astr {
+        #[serde(default)]
+        prefix: String,
+        count: usize,
+        /// not zero terminated, chars from [a, b]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nz: Option<[u8; 2]>,
+    },
+    /// count = 0 means constant prefix only
+    Wstr {
+        #[serde(default)]
+        prefix: String,
+        #[serde(default)]
+        count: usize,
+        #[serde(default)]
+        nz: bool,
+    },
+    Array {
+        #[serde(default)]
+        name: String,
+        count: usize,
+        of: Box<Leaf>,
+    },
+    Struct {
+        #[serde(default)]
+        name: String,
+        /// without size, struct ends with last field
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<usize>,
+        fields: Vec<Field>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use toml;
+
+    const TABLE: &str = include_str!("../socket.toml");
+
+    #[test]
+    fn table_with_defaults() {
+        let desc: Description = toml::from_str(TABLE).unwrap();
+        assert_eq!("libc.so.6", desc.module);
+        assert_eq!(1, desc.state.len());
+        let sdesc = &desc.state[0];
+        assert_eq!(("socket", 1, 4, 40), (sdesc.name.as_str(), sdesc.id, sdesc.fd_size, sdesc.limit));
+        assert_eq!(vec![[0, 1], [0, 1], [0, 0]], sdesc.slopes);
+        assert_eq!(vec!["bind", "connect", "setsockopt"], sdesc.groups[1]);
+        assert_eq!("close", sdesc.dtor);
+
+        let socket = &desc.call[0];
+        assert_eq!(("socket", 0x100), (socket.name.as_str(), socket.id));
+        assert!(socket.module.is_empty() && socket.symbol.is_empty());
+        assert_eq!(Success::NonNeg, socket.ok);
+        assert_eq!(4, socket.ret_size);
+        assert!(socket.fd_ret);
+        assert_eq!(None, socket.fd_arg);
+        match socket.args[1].leaf {
+            Leaf::Flag { size, always, flag } => assert_eq!((4, 1, 0x80800), (size, always, flag)),
+            ref leaf => panic!("{:?}", leaf),
+        }
+        assert!(!socket.args[1].memory);
+
+        let bind = &desc.call[1];
+        assert_eq!(Success::Zero, bind.ok);
+        assert!(matches!(bind.args[0].leaf, Leaf::Deref { size : None, offset : 0 }));
+        assert!(bind.args[1].memory);
+        match bind.args[1].leaf {
+            Leaf::Struct { ref name, size : None, ref fields } => {
+                assert_eq!("sockaddr_in", name);
+                assert_eq!(4, fields.len());
+                assert!(fields.iter().all(|field| field.offset.is_none()));
+                assert!(matches!(fields[1].leaf, Leaf::Bounded { size : 2, ref ranges, signed : false } if ranges == &[[0x901f, 0x941f]]));
+            },
+            ref leaf => panic!("{:?}", leaf),
+        }
+
+        match desc.call[3].args[3].leaf {
+            Leaf::Ptr { ref to } => assert!(matches!(**to, Leaf::Bounded { size : 4, .. })),
+            ref leaf => panic!("{:?}", leaf),
+        }
+    }
+
+    #[test]
+    fn serialized_table_parses_back() {
+        let desc: Description = toml::from_str(TABLE).unwrap();
+        let again: Description = toml::from_str(&toml::to_string(&desc).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", desc), format!("{:?}", again));
+    }
+
+    #[test]
+    fn nested_fd_leafs() {
+        let call: CallDesc = toml::from_str(r#"
+name = "pipe"
+id = 7
+fd_arg = 0
+ok = "any"
+ret_size = 8
+args = [
+    { leaf = "array", count = 2, memory = true, of = { leaf = "fd" } },
+    { leaf = "fd", size = 4, of = [{ leaf = "rnd_fd", state = "socket" }, { leaf = "rnd_fd", id = 3, size = 8 }] },
+    { leaf = "struct", size = 16, fields = [{ leaf = "rnd", size = 4, offset = 8 }] },
+]
+"#).unwrap();
+        assert_eq!((Some(0), Success::Any, 8), (call.fd_arg, call.ok, call.ret_size));
+        assert!(matches!(call.args[0].leaf, Leaf::Array { count : 2, ref of, .. } if matches!(**of, Leaf::Fd { size : None, ref of } if of.is_empty())));
+        match call.args[1].leaf {
+            Leaf::Fd { size : Some(4), ref of } => {
+                assert!(matches!(of[0], Leaf::RndFd { ref state, id : None, size : None } if "socket" == state));
+                assert!(matches!(of[1], Leaf::RndFd { ref state, id : Some(3), size : Some(8) } if state.is_empty()));
+            },
+            ref leaf => panic!("{:?}", leaf),
+        }
+        match call.args[2].leaf {
+            Leaf::Struct { size : Some(16), ref fields, .. } => assert_eq!(Some(8), fields[0].offset),
+            ref leaf => panic!("{:?}", leaf),
+        }
+    }
+
+    #[test]
+    fn malformed_is_refused() {
+        for table in [
+            "[[call]]\nname = \"x\"\nid = 1\nargs = [{ leaf = \"unknown\", size = 4 }]",
+            "[[call]]\nname = \"x\"\nid = 1\nargs = [{ leaf = \"const\", value = 4 }]",
+            "[[call]]\nname = \"x\"\nid = 1\nok = \"sometimes\"",
+            "[[state]]\nname = \"x\"\nid = 1",
+            "module = 3",
+        ] {
+            assert!(toml::from_str::<Description>(table).is_err(), "{}", table);
+        }
+        assert!(toml::from_str::<Description>("").unwrap().state.is_empty());
+    }
+}
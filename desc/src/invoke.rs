@@ -0,0 +1,100 @@
+use std::mem;
+
+use core::generator::arg::Arg;
+use core::exec::fd_info::{CallInfo, Fd};
+
+use format::Success;
+
+/// max args we know how to pass, C calling convention of platform handles registers vs stack
+pub const MAX_ARGS: usize = 8;
+
+type C0 = extern "C" fn() -> usize;
+type C1 = extern "C" fn(usize) -> usize;
+type C2 = extern "C" fn(usize, usize) -> usize;
+type C3 = extern "C" fn(usize, usize, usize) -> usize;
+type C4 = extern "C" fn(usize, usize, usize, usize) -> usize;
+type C5 = extern "C" fn(usize, usize, usize, usize, usize) -> usize;
+type C6 = extern "C" fn(usize, usize, usize, usize, usize, usize) -> usize;
+type C7 = extern "C" fn(usize, usize, usize, usize, usize, usize, usize) -> usize;
+type C8 = extern "C" fn(usize, usize, usize, usize, usize, usize, usize, usize) -> usize;
+
+/// resolved symbol + how to interpret its result
+#[derive(Clone)]
+pub struct Native {
+    pub addr: usize,
+    pub ok: Success,
+    pub ret_size: usize,
+    pub fd_ret: bool,
+    pub fd_arg: Option<usize>,
+    /// of state call is built for
+    pub fd_size: usize,
+    /// per arg, passed by pointer to its data
+    pub memory: Vec<bool>,
+}
+
+/// primitive goes by value, memory by pointer to its data
+fn word(arg: &Arg, memory: bool) -> usize {
+    if memory {
+        return arg.data().as_ptr() as usize
+    }
+    let mut word = [0u8; mem::size_of::<usize>()];
+    word[..arg.size()].copy_from_slice(arg.data());
+    usize::from_le_bytes(word)
+}
+
+/// sign extend returned value, as only ret_size of register is defined
+fn extend(ret: usize, size: usize) -> isize {
+    if size >= mem::size_of::<usize>() {
+        return ret as isize
+    }
+    let shift = 8 * (mem::size_of::<usize>() - size);
+    ((ret << shift) as isize) >> shift
+}
+
+impl Native {
+    unsafe fn ccall(&self, a: &[usize]) -> usize {
+        let addr = self.addr;
+        match a.len() {
+            0 => mem::transmute::<usize, C0>(addr)(),
+            1 => mem::transmute::<usize, C1>(addr)(a[0]),
+            2 => mem::transmute::<usize, C2>(addr)(a[0], a[1]),
+            3 => mem::transmute::<usize, C3>(addr)(a[0], a[1], a[2]),
+            4 => mem::transmute::<usize, C4>(addr)(a[0], a[1], a[2], a[3]),
+            5 => mem::transmute::<usize, C5>(addr)(a[0], a[1], a[2], a[3], a[4]),
+            6 => mem::transmute::<usize, C6>(addr)(a[0], a[1], a[2], a[3], a[4], a[5]),
+            7 => mem::transmute::<usize, C7>(addr)(a[0], a[1], a[2], a[3], a[4], a[5], a[6]),
+            8 => mem::transmute::<usize, C8>(addr)(a[0], a[1], a[2], a[3], a[4], a[5], a[6], a[7]),
+            n => panic!("native call with {} args, max is {}", n, MAX_ARGS),
+        }
+    }
+
+    /// invoke symbol on generated args, and translate result to CallInfo
+    ///
+    /// - fd_ret : returned value is new fd of state, success if valid
+    /// - fd_arg : fd is written by call to memory of argument
+    /// - otherwise success by ok condition, no extra info
+    pub fn call(&self, args: &mut [Arg]) -> CallInfo {
+        let words = args
+            .iter()
+            .zip(self.memory.iter())
+            .map(|(arg, &memory)| word(arg, memory))
+            .collect::< Vec<usize> >();
+        let ret = extend(unsafe { self.ccall(&words) }, self.ret_size);
+        let ok = match self.ok {
+            Success::Zero => 0 == ret,
+            Success::NonNeg => ret >= 0,
+            Success::NonZero => 0 != ret,
+            Success::Any => true,
+        };
+        if self.fd_ret {
+            return CallInfo::infofromfd(Fd::new(&ret.to_le_bytes()[..self.fd_size]))
+        }
+        if let Some(ind) = self.fd_arg {
+            if !ok {
+                return CallInfo::fail()
+            }
+            return CallInfo::infofromfd(Fd::new(&args[ind].data()[..self.fd_size]))
+        }
+        CallInfo::new(ok, &[])
+    }
+}
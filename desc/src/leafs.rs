@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::ops::RangeInclusive;
+
+use core::generator::arg::Arg;
+use core::generator::composite::ArgComposite;
+use core::generator::leaf::IArgLeaf;
+use core::state::id::StateTableId;
+
+use api::leafs::array_comp::ArrayComposite;
+use api::leafs::bounded_leaf::Bounded;
+use api::leafs::const_leaf::Const;
+use api::leafs::deref_leaf::DeRef;
+use api::leafs::fd_leaf::{FdHolder, RndFd};
+use api::leafs::flag_leaf::Flag;
+use api::leafs::pattern_leaf::Pattern;
+use api::leafs::phantom_leaf::Phantom;
+use api::leafs::ptr_leaf::Ptr;
+use api::leafs::random_leaf::RndData;
+use api::leafs::str_leaf::StrLeaf;
+
+use format::{ArgDesc, Leaf};
+use intern;
+
+/// what leafs need to know about surrounding call table
+pub struct Ctx<'a> {
+    /// fd_size of state, call is built for
+    pub fd_size: usize,
+    /// name -> (id, fd_size), for rnd_fd
+    pub states: &'a HashMap<String, (u64, usize)>,
+}
+
+pub fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+fn bounds<T, F>(ranges: &[[i64; 2]], cast: F) -> Vec< RangeInclusive<T> >
+    where F: Fn(i64) -> T
+{
+    ranges
+        .iter()
+        .map(|&[lo, hi]| cast(lo)..=cast(hi))
+        .collect()
+}
+
+fn number(size: usize, value: i64) -> Result<Box<dyn IArgLeaf>, io::Error> {
+    Ok(match size {
+        1 => Box::new(Const::new8(value as u8)),
+        2 => Box::new(Const::new16(value as u16)),
+        4 => Box::new(Const::new32(value as u32)),
+        8 => Box::new(Const::new64(value as u64)),
+        _ => return Err(invalid(format!("const of unsupported size {}", size))),
+    })
+}
+
+fn flag(size: usize, always: i64, flag: i64) -> Result<Box<dyn IArgLeaf>, io::Error> {
+    Ok(match size {
+        1 => Box::new(Flag::<u8>::new(always as u8, flag as u8)),
+        2 => Box::new(Flag::<u16>::new(always as u16, flag as u16)),
+        4 => Box::new(Flag::<u32>::new(always as u32, flag as u32)),
+        8 => Box::new(Flag::<u64>::new(always as u64, flag as u64)),
+        _ => return Err(invalid(format!("flag of unsupported size {}", size))),
+    })
+}
+
+fn bounded(size: usize, ranges: &[[i64; 2]], signed: bool) -> Result<Box<dyn IArgLeaf>, io::Error> {
+    if ranges.is_empty() || ranges.iter().any(|&[lo, hi]| lo > hi) {
+        return Err(invalid(format!("bounded with empty or reversed ranges {:?}", ranges)))
+    }
+    Ok(match (size, signed) {
+        (1, false) => Box::new(Bounded::ranges(bounds(ranges, |n| n as u8))),
+        (2, false) => Box::new(Bounded::ranges(bounds(ranges, |n| n as u16))),
+        (4, false) => Box::new(Bounded::ranges(bounds(ranges, |n| n as u32))),
+        (8, false) => Box::new(Bounded::ranges(bounds(ranges, |n| n as u64))),
+        (1, true) => Box::new(Bounded::ranges(bounds(ranges, |n| n as i8))),
+        (2, true) => Box::new(Bounded::ranges(bounds(ranges, |n| n as i16))),
+        (4, true) => Box::new(Bounded::ranges(bounds(ranges, |n| n as i32))),
+        (8, true) => Box::new(Bounded::ranges(bounds(ranges, |n| n))),
+        _ => return Err(invalid(format!("bounded of unsupported size {}", size))),
+    })
+}
+
+/// translate description of leaf to api leaf
+///
+/// - in_fd : rnd_fd is serializable only trough FdHolder, so allowed only there
+pub fn build(leaf: &Leaf, ctx: &Ctx, in_fd: bool) -> Result<Box<dyn IArgLeaf>, io::Error> {
+    Ok(match *leaf {
+        Leaf::Const { size, value } => number(size, value)?,
+        Leaf::Bytes { ref data } => Box::new(Const::new(data.clone())),
+        Leaf::Flag { size, always, flag: bits } => flag(size, always, bits)?,
+        Leaf::Bounded { size, ref ranges, signed } => bounded(size, ranges, signed)?,
+        Leaf::Rnd { size } => Box::new(RndData::new(size)),
+        Leaf::Pattern { byte, size } => Box::new(Pattern::new(byte, size)),
+        Leaf::Phantom { size } => Box::new(Phantom::new(size)),
+        Leaf::Ptr { ref to } => Box::new(Ptr::new(build(to, ctx, false)?)),
+        Leaf::Deref { size, offset } => {
+            let size = size.unwrap_or(ctx.fd_size);
+            if offset + size > ctx.fd_size {
+                return Err(invalid(format!("deref {}..{} out of fd of size {}", offset, offset + size, ctx.fd_size)))
+            }
+            Box::new(DeRef::partial(offset, size))
+        },
+        Leaf::Fd { size, ref of } => {
+            let size = size.unwrap_or(ctx.fd_size);
+            if of.is_empty() {
+                return Ok(Box::new(FdHolder::holder(size)))
+            }
+            let fds = of
+                .iter()
+                .map(|fd| build(fd, ctx, true))
+                .collect::< Result<Vec<Box<dyn IArgLeaf>>, io::Error> >()?;
+            if let Some(fd) = fds.iter().find(|fd| fd.size() > size) {
+                return Err(invalid(format!("fd of size {} holds {} of size {}", size, fd.name(), fd.size())))
+            }
+            Box::new(FdHolder::new(size, fds))
+        },
+        Leaf::RndFd { ref state, id, size } => {
+            if !in_fd {
+                return Err(invalid(format!("rnd_fd of {} must be scoped within fd leaf", state)))
+            }
+            match (ctx.states.get(state), id, size) {
+                (_, Some(id), Some(size)) => Box::new(RndFd::new(StateTableId::Id(id), size)),
+                (Some(&(id, size)), _, _) => Box::new(RndFd::new(StateTableId::Id(id), size)),
+                _ => return Err(invalid(format!("rnd_fd of unknown state {} and without id + size", state))),
+            }
+        },
+        Leaf::Astr { ref prefix, count, nz } => match nz {
+            Some([lo, hi]) => Box::new(ArgComposite::astr_leaf_nz(prefix, lo..=hi, count)),
+            None if 0 != count => Box::new(ArgComposite::astr_leaf(prefix, count)),
+            None => return Err(invalid(String::from("astr need at least one char"))),
+        },
+        Leaf::Wstr { ref prefix, count, nz } => match (count, nz) {
+            (0, false) => Box::new(ArgComposite::wstr_leaf_const(prefix)),
+            (0, true) => return Err(invalid(String::from("wstr nz need at least one char"))),
+            (_, false) => Box::new(ArgComposite::wstr_leaf(prefix, count)),
+            (_, true) => Box::new(ArgComposite::wstr_leaf_nz(prefix, count)),
+        },
+        Leaf::Array { ref name, count, ref of } => {
+            build(of, ctx, in_fd)?;
+            Box::new(ArgComposite::array_leaf(
+                intern(name),
+                count,
+                || build(of, ctx, in_fd).unwrap()))
+        },
+        Leaf::Struct { ref name, size, ref fields } => {
+            let mut end = 0;
+            let mut leafs = Vec::new();
+            for field in fields.iter() {
+                let offset = field.offset.unwrap_or(end);
+                let leaf = build(&field.leaf, ctx, false)?;
+                end = offset + leaf.size();
+                leafs.push((offset, leaf));
+            }
+            let max = leafs
+                .iter()
+                .map(|&(offset, ref leaf)| offset + leaf.size())
+                .max()
+                .unwrap_or(0);
+            let size = size.unwrap_or(max);
+            if max > size {
+                return Err(invalid(format!("struct {} of size {} overflown by fields up to {}", name, size, max)))
+            }
+            // same rule as ArgComposite::new, which would panic on it
+            for (ind, &(offset, ref leaf)) in leafs.iter().enumerate() {
+                let end = offset + leaf.size();
+                let other = leafs[ind + 1..]
+                    .iter()
+                    .position(|&(start, ref other)| offset < start + other.size() && end > start);
+                if let Some(other) = other {
+                    let (start, ref other_leaf) = leafs[ind + 1 + other];
+                    return Err(invalid(format!("struct {} : field {} [{}..{}) overlaps field {} [{}..{})",
+                        name, ind, offset, end, ind + 1 + other, start, start + other_leaf.size())))
+                }
+            }
+            Box::new(ArgComposite::new(size, intern(name), leafs))
+        },
+    })
+}
+
+/// primitive arg by default, memory if asked so or if it does not fit to register
+///
+/// - returns arg + if it is memory one
+pub fn arg(desc: &ArgDesc, ctx: &Ctx) -> Result<(Arg, bool), io::Error> {
+    let leaf = build(&desc.leaf, ctx, false)?;
+    Ok(if desc.memory || leaf.size() > mem::size_of::<usize>() {
+        (Arg::memory_arg(leaf), true)
+    } else {
+        (Arg::primitive_arg(leaf), false)
+    })
+}
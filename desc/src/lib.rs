@@ -0,0 +1,315 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate toml;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate core;
+extern crate api;
+extern crate generic;
+
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::sync::Mutex;
+
+use core::exec::call::Call;
+use core::exec::fd_info::Fd;
+use core::exec::id::CallTableId;
+use core::state::id::StateTableId;
+use core::state::state::{IFuzzyObj, State};
+
+pub mod format;
+pub mod invoke;
+pub mod leafs;
+pub mod state;
+
+use format::{CallDesc, Description, StateDesc};
+use invoke::Native;
+use leafs::{invalid, Ctx};
+use state::DescState;
+
+/// slopes, groups, dtor : what State::new wants
+type Table = (Vec<[isize; 2]>, Vec< Vec<Call> >, Call);
+
+lazy_static! {
+    static ref NAMES: Mutex< HashMap<String, &'static str> > = Mutex::new(HashMap::new());
+}
+
+/// core wants &'static str for names of calls, states and composites
+///
+/// - leaked once per unique name, so building states over and over does not leak
+pub fn intern(name: &str) -> &'static str {
+    let mut names = match NAMES.lock() {
+        Ok(names) => names,
+        Err(pois) => pois.into_inner(),
+    };
+    if let Some(&name) = names.get(name) {
+        return name
+    }
+    let leaked: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(name.to_string(), leaked);
+    leaked
+}
+
+/// call table loaded at runtime, instead of hand written State + Call + ArgComposite
+///
+/// - symbols are resolved and whole table is built once at load, to fail early on bad description
+/// - then every state() / duped() build fresh objects, as core State owns its calls + args
+///
+/// # Example :
+/// ```no_run
+/// # extern crate core;
+/// # extern crate desc;
+/// # use core::banana::looper::FuzzyState;
+/// # use core::context;
+/// # use desc::Desc;
+/// # fn main() -> Result<(), std::io::Error> {
+/// let desc = Desc::load("socket.toml")?;
+/// for _ in 0..context::config().max_queue_size {
+///     FuzzyState::fuzz(desc.state("socket").unwrap());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Desc {
+    desc: Description,
+    /// name -> (id, fd_size)
+    states: HashMap<String, (u64, usize)>,
+    /// name of call -> resolved symbol
+    symbols: HashMap<String, usize>,
+}
+
+impl Desc {
+    pub fn load(fname: &str) -> Result<Desc, io::Error> {
+        Desc::parse(&generic::read_file(fname)?)
+    }
+
+    pub fn parse(data: &str) -> Result<Desc, io::Error> {
+        let desc: Description = match toml::from_str(data) {
+            Ok(desc) => desc,
+            Err(e) => return Err(invalid(format!("call table description : {}", e))),
+        };
+
+        let mut states = HashMap::new();
+        for sdesc in desc.state.iter() {
+            if states.values().any(|&(id, _)| id == sdesc.id) {
+                return Err(invalid(format!("state {} : id {:X} used twice", sdesc.name, sdesc.id)))
+            }
+            if states.insert(sdesc.name.clone(), (sdesc.id, sdesc.fd_size)).is_some() {
+                return Err(invalid(format!("state {} declared twice", sdesc.name)))
+            }
+        }
+
+        let mut symbols = HashMap::new();
+        for cdesc in desc.call.iter() {
+            if symbols.contains_key(&cdesc.name) {
+                return Err(invalid(format!("call {} declared twice", cdesc.name)))
+            }
+            let module = if cdesc.module.is_empty() { &desc.module } else { &cdesc.module };
+            let symbol = if cdesc.symbol.is_empty() { &cdesc.name } else { &cdesc.symbol };
+            let addr = generic::load_api(module, symbol) as usize;
+            if 0 == addr {
+                return Err(invalid(format!("call {} : unresolved {} of {}", cdesc.name, symbol, module)))
+            }
+            symbols.insert(cdesc.name.clone(), addr);
+        }
+
+        let desc = Desc {
+            desc,
+            states,
+            symbols,
+        };
+        for sdesc in desc.desc.state.iter() {
+            desc.table(sdesc)?;
+        }
+        Ok(desc)
+    }
+
+    /// names of described states
+    pub fn states(&self) -> Vec<&str> {
+        self.desc.state
+            .iter()
+            .map(|sdesc| sdesc.name.as_str())
+            .collect()
+    }
+
+    /// new object to fuzz, from its ctor
+    pub fn state(&self, name: &str) -> Option<Box<dyn IFuzzyObj>> {
+        let sdesc = self.desc.state.iter().find(|sdesc| sdesc.name == name)?;
+        let (slopes, groups, dtor) = self.table(sdesc).ok()?;
+        Some(Box::new(DescState::new(State::new(
+            intern(&sdesc.name),
+            StateTableId::Id(sdesc.id),
+            sdesc.limit,
+            slopes,
+            groups,
+            dtor))))
+    }
+
+    /// already existing object, for races
+    pub fn duped(&self, name: &str, fd: &Fd) -> Option<Box<dyn IFuzzyObj>> {
+        let sdesc = self.desc.state.iter().find(|sdesc| sdesc.name == name)?;
+        let (slopes, groups, dtor) = self.table(sdesc).ok()?;
+        Some(Box::new(DescState::new(State::duped(
+            intern(&sdesc.name),
+            StateTableId::Id(sdesc.id),
+            fd,
+            sdesc.limit,
+            slopes,
+            groups,
+            dtor))))
+    }
+
+    /// call on its own, not bound to living state ( executing foreign programs f.e. )
+    ///
+    /// - built as for first state which has it in groups or as dtor, fd_size is taken from there
+    pub fn lone(&self, name: &str) -> Result<Call, io::Error> {
+        let sdesc = self.desc.state
+            .iter()
+            .find(|sdesc| sdesc.dtor == name || sdesc.groups.iter().any(|group| group.iter().any(|call| call == name)));
+        match sdesc {
+            Some(sdesc) => self.call(name, sdesc),
+            None => Err(invalid(format!("call {} is not used by any state", name))),
+        }
+    }
+
+    /// parsed description table was loaded from
+    pub fn description(&self) -> &Description {
+        &self.desc
+    }
+
+    fn table(&self, sdesc: &StateDesc) -> Result<Table, io::Error> {
+        if sdesc.slopes.len() != sdesc.groups.len() {
+            return Err(invalid(format!("state {} : {} slopes vs {} groups", sdesc.name, sdesc.slopes.len(), sdesc.groups.len())))
+        }
+        for (level, slope) in sdesc.slopes.iter().enumerate() {
+            if sdesc.groups[level].is_empty() || sdesc.groups[level].len() >= 0x200 {
+                return Err(invalid(format!("state {} : group {} is empty or oversized", sdesc.name, level)))
+            }
+            if slope.iter().any(|&slope| level as isize + slope < 0 || level as isize + slope >= sdesc.groups.len() as isize) {
+                return Err(invalid(format!("state {} : slope {:?} at level {} leads out of groups", sdesc.name, slope, level)))
+            }
+        }
+        let groups = sdesc.groups
+            .iter()
+            .map(|group| group
+                .iter()
+                .map(|name| self.call(name, sdesc))
+                .collect::< Result<Vec<Call>, io::Error> >())
+            .collect::< Result<Vec< Vec<Call> >, io::Error> >()?;
+        Ok((sdesc.slopes.clone(), groups, self.call(&sdesc.dtor, sdesc)?))
+    }
+
+    fn call(&self, name: &str, sdesc: &StateDesc) -> Result<Call, io::Error> {
+        let cdesc = match self.desc.call.iter().find(|cdesc| cdesc.name == name) {
+            Some(cdesc) => cdesc,
+            None => return Err(invalid(format!("state {} : unknown call {}", sdesc.name, name))),
+        };
+        check(cdesc, sdesc)?;
+
+        let ctx = Ctx {
+            fd_size : sdesc.fd_size,
+            states : &self.states,
+        };
+        let (args, memory): (Vec<_>, Vec<bool>) = cdesc.args
+            .iter()
+            .map(|arg| leafs::arg(arg, &ctx))
+            .collect::< Result<Vec<_>, io::Error> >()?
+            .into_iter()
+            .unzip();
+
+        let native = Native {
+            addr : self.symbols[&cdesc.name],
+            ok : cdesc.ok,
+            ret_size : cdesc.ret_size,
+            fd_ret : cdesc.fd_ret,
+            fd_arg : cdesc.fd_arg,
+            fd_size : sdesc.fd_size,
+            memory,
+        };
+        if let Some(ind) = cdesc.fd_arg {
+            if !native.memory[ind] || args[ind].size() < sdesc.fd_size {
+                return Err(invalid(format!("call {} : fd_arg {} is not memory of fd size", cdesc.name, ind)))
+            }
+        }
+        Ok(Call::dynamic(
+            CallTableId::Id(cdesc.id),
+            intern(&cdesc.name),
+            args,
+            Box::new(move |args| native.call(args))))
+    }
+}
+
+fn check(cdesc: &CallDesc, sdesc: &StateDesc) -> Result<(), io::Error> {
+    if cdesc.args.len() > invoke::MAX_ARGS {
+        return Err(invalid(format!("call {} : {} args, max is {}", cdesc.name, cdesc.args.len(), invoke::MAX_ARGS)))
+    }
+    if 0 == cdesc.ret_size || cdesc.ret_size > mem::size_of::<usize>() {
+        return Err(invalid(format!("call {} : unsupported ret_size {}", cdesc.name, cdesc.ret_size)))
+    }
+    if 0 == sdesc.fd_size || (cdesc.fd_ret && sdesc.fd_size > mem::size_of::<usize>()) {
+        return Err(invalid(format!("call {} : fd_size {} of {} can not be returned", cdesc.name, sdesc.fd_size, sdesc.name)))
+    }
+    if cdesc.fd_ret && cdesc.fd_arg.is_some() {
+        return Err(invalid(format!("call {} : fd_ret and fd_arg are exclusive", cdesc.name)))
+    }
+    match cdesc.fd_arg {
+        Some(ind) if ind >= cdesc.args.len() => Err(invalid(format!("call {} : fd_arg {} out of args", cdesc.name, ind))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = include_str!("../socket.toml");
+
+    fn refused(table: &str, why: &str) {
+        match Desc::parse(table) {
+            Ok(_) => panic!("accepted, expected {}", why),
+            Err(e) => {
+                assert_eq!(io::ErrorKind::InvalidData, e.kind());
+                assert!(e.to_string().contains(why), "{} vs {}", e, why);
+            },
+        }
+    }
+
+    #[test]
+    fn builds_states_and_calls() {
+        let desc = Desc::parse(TABLE).unwrap();
+        assert_eq!(vec!["socket"], desc.states());
+        assert!(desc.state("socket").is_some());
+        assert!(desc.duped("socket", &Fd::new(&[3, 0, 0, 0])).is_some());
+        assert!(desc.state("file").is_none());
+
+        let close = desc.lone("close").unwrap();
+        assert_eq!(CallTableId::Id(0x106), close.id());
+        assert_eq!("close", close.name());
+        assert!(desc.lone("accept").is_err());
+    }
+
+    #[test]
+    fn bad_tables_are_refused() {
+        refused("x", "call table description");
+        refused(&TABLE.replace("id = 0x101", "id = 0x100\n[[state]]\nname = \"dup\"\nid = 0x1\nfd_size = 4\nlimit = 1\nslopes = []\ngroups = []\ndtor = \"close\""), "used twice");
+        refused(&(TABLE.to_string() + "\n[[call]]\nname = \"close\"\nid = 0x200\n"), "call close declared twice");
+        refused(&TABLE.replace("slopes = [[0, 1], [0, 1], [0, 0]]", "slopes = [[0, 1], [0, 1]]"), "3 groups");
+        refused(&TABLE.replace("slopes = [[0, 1], [0, 1], [0, 0]]", "slopes = [[0, 1], [0, 1], [0, 1]]"), "leads out of groups");
+        refused(&TABLE.replace("[\"listen\", \"shutdown\", \"setsockopt\"]", "[\"listen\", \"accept\"]"), "unknown call accept");
+        refused(&TABLE.replace("fd_ret = true", "fd_ret = true\nfd_arg = 0"), "exclusive");
+        refused(&TABLE.replace("fd_ret = true", "fd_arg = 5"), "out of args");
+        refused(&TABLE.replace("fd_ret = true", "fd_arg = 0"), "is not memory of fd size");
+        refused(&TABLE.replace("fd_ret = true", "fd_ret = true\nret_size = 0"), "unsupported ret_size");
+    }
+
+    #[test]
+    fn names_are_interned_once() {
+        let name = intern("desc-test-name");
+        assert!(std::ptr::eq(name, intern(&String::from("desc-test-name"))));
+        assert_eq!("desc-test-name", name);
+    }
+}
@@ -0,0 +1,53 @@
+use core::exec::fd_info::Fd;
+use core::state::state::{IFdState, IFuzzyObj, State};
+
+/// fuzzy object of state built from description, no knowledge involved except call table
+///
+/// - ctor ( fd_ret / fd_arg call ) initialize fd of state
+/// - rest is left on slopes + modules
+pub struct DescState {
+    state: State,
+}
+
+impl DescState {
+    pub fn new(state: State) -> DescState {
+        DescState { state }
+    }
+}
+
+/// State holds raw memory of args ( NativeAlloc ) and leafs, therefore it is not Send on its own
+///
+/// - however it is owned by one fuzzing thread only, other threads see just StateInfo trough queue
+unsafe impl Send for DescState {}
+unsafe impl Sync for DescState {}
+
+impl IFdState for DescState {
+    fn invalid(&self) -> bool {
+        self.state.fd().is_invalid()
+    }
+    fn is_online(&mut self) -> bool {
+        true
+    }
+}
+
+impl IFuzzyObj for DescState {
+    fn fuzzy_loop(&mut self) -> bool {
+        if !self.state.do_fuzz_one() {
+            return false
+        }
+        self.state.do_fuzz_update()
+    }
+    fn fuzzy_init(&mut self) -> bool {
+        if !self.state.do_fuzz_one() {
+            return false
+        }
+        if self.state.fd().is_invalid() && self.state.call_view().ok() && !self.state.call_view().einfo().is_empty() {
+            let fd = Fd::new(self.state.call_view().einfo());
+            self.state.init(&fd);
+        }
+        self.state.do_fuzz_update()
+    }
+    fn state(&self) -> &State {
+        &self.state
+    }
+}
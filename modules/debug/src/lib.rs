@@ -1,12 +1,13 @@
-#![feature(integer_atomics)]
-
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
 
 extern crate core;
 
+use std::time::Duration;
+
 use core::exec::call::Call;
+use core::exec::fd_info::CallInfo;
 use core::exec::id::CallTableId;
 use core::banana::observer::{ICallObserver, IStateObserver};
 use core::state::state::StateInfo;
@@ -24,30 +25,38 @@ struct Debug {
 
 impl ICallObserver for Debug {
     fn notify(&self, state: &StateInfo, call: &Call) -> bool {
-        if !self.cfg.noisy {
-            return true;
-        }
-        if !self.cfg.ctors_included && state.fd.is_invalid() {
-            return true;
-        }
-        if 0 != self.cfg.mask && !(CallTableId::Id(self.cfg.mask) & call.id()) {
-            return true;
-        }
-        if self.cfg.only_successfull && !call.ok() {
-            return true;//as this is pre-callback call.ok() will get us print second time call is hit after it suceed
-            //also it will never print ctors in only_successfull mode...
-            //debug modle is mostly to print out if all calls are called with frequency as expected
-            //and if they are sucessfull more or less, for better analysis need separate analyze module
+        if self.cfg.only_successfull || !self.interested(state, call) {
+            return true;//only_successfull is handled at notify_post, once we know result of call
         }
         println!("[d]call : {:?} <{:?}> [fd:{:?} | {:?}]", call.name(), state.name, state.fd, call.success());
         true
     }
+    fn notify_post(&self, state: &StateInfo, call: &Call, result: &CallInfo, elapsed: Duration) {
+        if !self.cfg.only_successfull || !result.success() || !self.interested(state, call) {
+            return;
+        }
+        //debug modle is mostly to print out if all calls are called with frequency as expected
+        //and if they are sucessfull more or less, for better analysis need separate analyze module
+        println!("[d]call : {:?} <{:?}> [fd:{:?} | {:?}] ok in {:?}", call.name(), state.name, state.fd, call.success(), elapsed);
+    }
 }
 
 impl Debug {
     pub(crate) fn new(cfg: &DebugConfig) -> Debug {
         Debug { cfg: *cfg }
     }
+    fn interested(&self, state: &StateInfo, call: &Call) -> bool {
+        if !self.cfg.noisy {
+            return false;
+        }
+        if !self.cfg.ctors_included && state.fd.is_invalid() {
+            return false;
+        }
+        if 0 != self.cfg.mask && !(CallTableId::Id(self.cfg.mask) & call.id()) {
+            return false;
+        }
+        true
+    }
 }
 
 pub fn observers(
@@ -1,5 +1,3 @@
-#![feature(integer_atomics)]
-
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
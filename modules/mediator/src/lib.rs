@@ -1,5 +1,3 @@
-#![feature(integer_atomics)]
-
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
@@ -11,6 +9,7 @@ extern crate core;
 
 use core::exec::call::Call;
 use core::banana::observer::{ICallObserver, IStateObserver};
+use core::rng;
 use core::state::state::StateInfo;
 
 extern crate common;
@@ -30,7 +29,7 @@ impl ICallObserver for Mediator {
         }
         // this is due bad fuzzer implementation and no good config reflecting that
         //   ctors overhlming fuzzing, and core logic is ommited
-        0 == rand::thread_rng().gen::<u8>() % self.cfg.mediate_ctor_weight
+        0 == rng::thread_rng().gen::<u8>() % self.cfg.mediate_ctor_weight
     }
 }
 
@@ -1,10 +1,11 @@
 use std;
+use std::thread;
 
 extern crate core;
 use core::state::id::StateTableId;
 use core::exec::fd_info::Fd;
 
-use core::config::FZZCONFIG;
+use core::context;
 
 extern crate common;
 use self::common::{
@@ -12,7 +13,7 @@ use self::common::{
     TPushState,
 };
 
-//extern crate libpoclog;
+extern crate libpoclog;
 //extern crate libsyzkaller;
 
 #[derive(Clone)]
@@ -32,19 +33,24 @@ impl ModuleCallbacks for PlugCallbacks {
     fn push_state(&self) -> TPushState { self.callback }
 
     fn read_log(&self) -> String {
-//        libpoclog::logger::Logger::log()
-      String::from("")
+        libpoclog::logger::Logger::log()
     }
-    fn log_call(&self, _cmd: String, _info: &str) {
-//        libpoclog::logger::Logger::safe_log(
-//            libpoclog::decorate(cmd, info))
+    fn log_call(&self, cmd: String, info: &str) {
+        libpoclog::logger::Logger::safe_log(
+            libpoclog::decorate(cmd, info))
     }
+    /// modules call it from their callbacks, where queue is locked, so shutdown goes in own thread
+    ///
+    /// - main loop sees bananaq::stopping and ends as well
     fn stop_fuzzing(&self) {
-//        libsyzkaller::SyzKaller::finish();
-//        libpoclog::logger::Logger::flush();
-        if FZZCONFIG.noisy {
-            println!("[fuzzing] DONE");
+        let ctx = context::current();
+        if ctx.stopping() {
+            return
         }
-        std::process::exit(0)
+        ctx.stop();
+        thread::spawn(move || {
+            context::enter(ctx);
+            ::stop_fuzzing()
+        });
     }
 }
@@ -1,22 +1,17 @@
 #[macro_use]
 extern crate serde_derive;
 
-extern crate toml;
-
-extern crate rand;
-use rand::Rng;
-
-extern crate generic;
-
-use std::io;
+use std::env;
 
 extern crate core;
 use core::exec::fd_info::Fd;
+use core::banana::bananaq;
+use core::config::{self, ConfigError};
+use core::context;
 use core::banana::observer::{ICallObserver, IStateObserver};
 use core::state::id::StateTableId;
 
 extern crate common;
-use common::ModuleCallbacks;
 
 pub mod callbacks;
 use callbacks::PlugCallbacks;
@@ -39,6 +34,18 @@ use libdebug::DebugConfig;
 extern crate libmediator;
 use libmediator::MediatorConfig;
 
+extern crate libpoclog;
+use libpoclog::PocLogConfig;
+
+extern crate libstats;
+use libstats::StatsConfig;
+
+extern crate libwebui;
+use libwebui::WebUiConfig;
+
+extern crate libsyzkaller;
+use libsyzkaller::SyzkallerConfig;
+
 #[derive(Debug, Deserialize, Serialize)]
 struct ConfigCore {
     filter: Option<FilterConfig>,
@@ -47,6 +54,10 @@ struct ConfigCore {
     limiter: Option<LimiterConfig>,
     debug: Option<DebugConfig>,
     mediator: Option<MediatorConfig>,
+    poclog: Option<PocLogConfig>,
+    stats: Option<StatsConfig>,
+    webui: Option<WebUiConfig>,
+    syzkaller: Option<SyzkallerConfig>,
 }
 #[derive(Debug, Deserialize, Serialize)]
 struct Config {
@@ -54,22 +65,13 @@ struct Config {
     core: ConfigCore,
 }
 
-//will panic if no correct crafted config!
-fn load_cfg() -> Result<Config, io::Error> {
-    match generic::read_file("modules.toml") {
-        Ok(data) => Ok(toml::from_str(&data).unwrap()),
-        Err(_) => match generic::read_file(
-            &(String::from("e:/filters/modules")
-                + &rand::thread_rng().gen_range(0..2000u16).to_string()
-                + ".toml"),
-        ) {
-            Ok(data) => Ok(toml::from_str(&data).unwrap()),
-            Err(_) => match generic::read_file("e:/modules.toml") {
-                Ok(data) => Ok(toml::from_str(&data).unwrap()),
-                Err(e) => return Err(e),
-            },
-        },
-    }
+/// environment variable with path to modules.toml, when it is not in working folder
+pub const MODULES_ENV: &str = "BANANA_MODULES";
+
+/// modules.toml from working folder or $BANANA_MODULES, typo or missing field is error not panic
+fn load_cfg() -> Result<Config, ConfigError> {
+    let path = env::var(MODULES_ENV).unwrap_or_else(|_| String::from("modules.toml"));
+    config::parse_file(&path)
 }
 
 pub struct Observer {
@@ -154,6 +156,22 @@ impl Plugins {
                 name: module.clone(),
                 obs: libmediator::observers(&cfg.mediator),
             },
+            "libpoclog" => Observer {
+                name: module.clone(),
+                obs: libpoclog::observers(&cfg.poclog),
+            },
+            "libstats" => Observer {
+                name: module.clone(),
+                obs: libstats::observers(&cfg.stats),
+            },
+            "libwebui" => Observer {
+                name: module.clone(),
+                obs: libwebui::observers(&cfg.webui),
+            },
+            "libsyzkaller" => Observer {
+                name: module.clone(),
+                obs: libsyzkaller::observers(&cfg.syzkaller, qcallbacks.clone()),
+            },
             _ => Observer {
                 name: module.clone(),
                 obs: (None, None),
@@ -162,22 +180,26 @@ impl Plugins {
     }
 }
 
-pub fn plug<F>(push_state: &'static F) -> Result<Vec<Observer>, io::Error>
+pub fn plug<F>(push_state: &'static F) -> Result<Vec<Observer>, ConfigError>
 where
     F: Fn(StateTableId, &Fd) + std::marker::Sync + std::marker::Send,
 {
-    match load_cfg() {
-        Ok(cfg) => Ok(Plugins::new(cfg, push_state).observers),
-        Err(e) => return Err(e),
-    }
+    Ok(Plugins::new(load_cfg()?, push_state).observers)
 }
 
-fn bad_design(_: StateTableId, _: &Fd) {}
-
+/// graceful end of fuzzing, states close themselves and modules flush their output ( on_shutdown )
+///
+/// - blocks till it is done, see bananaq::shutdown
+/// - main loop calls it once it is over ( active_seconds ) or modules asked for it ( bananaq::stopping )
 pub fn stop_fuzzing() {
-    // problem is that plugins need this in some generic way
-    // and also plugcallbacks need access to plugin global stuffs ( stop, log, .. )
-    // and there we are with main.rs to stop fuzzing by generic way and PlugCallbacks beeing trait
-    // TODO : maybe to rethink stop_fuzzing mechanism
-    PlugCallbacks::new(&bad_design).stop_fuzzing()
+    let left = match bananaq::shutdown() {
+        Some(left) => left,
+        None => return,
+    };
+    if 0 != left {
+        println!("[fuzzing] {} states did not close in time", left);
+    }
+    if context::config().noisy {
+        println!("[fuzzing] DONE");
+    }
 }
@@ -0,0 +1,113 @@
+// banana fuzzer PoC runtime
+//
+// - counterpart of Call::serialize + ISerializableArg prefixes ( state_fd, shared_fd, ArgPtr )
+// - self contained, only libc + pthread : cc -pthread poc.c -ldl
+// - -DBANANA_RACE to ignore recorded order of calls across threads
+#ifndef BANANA_H
+#define BANANA_H
+
+#ifndef _GNU_SOURCE
+#define _GNU_SOURCE // RTLD_DEFAULT
+#endif
+
+#include <dlfcn.h>
+#include <errno.h>
+#include <pthread.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+// runtime fd ( handle, id, .. ) of fuzzed state
+//
+// - initialized by recorded value, updated once ctor of its state returns live one
+struct banana_fd {
+    size_t size;
+    unsigned char data[64];
+};
+
+static inline void banana_fd_set(struct banana_fd* fd, long ret) {
+    size_t size = fd->size < sizeof(ret) ? fd->size : sizeof(ret);
+    memset(fd->data, 0, fd->size);
+    memcpy(fd->data, &ret, size);
+}
+
+// arguments are byte buffers of n bytes, prefixes patch them in place and return them back
+
+// DeRef : our own state fd, part [doff, doff + size) of it at offset off of argument
+static inline unsigned char* state_fd(struct banana_fd fd, size_t doff, size_t size, size_t off, unsigned char* a, size_t n) {
+    if (off + size <= n && doff + size <= fd.size)
+        memcpy(a + off, fd.data + doff, size);
+    return a;
+}
+
+// FdHolder : fd of foreign ( or duped ) state at offset off of argument
+static inline unsigned char* shared_fd(struct banana_fd fd, size_t off, unsigned char* a, size_t n) {
+    size_t size = fd.size;
+    if (off >= n)
+        return a;
+    if (off + size > n)
+        size = n - off;
+    memcpy(a + off, fd.data, size);
+    return a;
+}
+
+// Ptr : pointer to other argument at offset off of argument
+static inline unsigned char* ArgPtr(void* p, size_t off, unsigned char* a, size_t n) {
+    if (off + sizeof(p) <= n)
+        memcpy(a + off, &p, sizeof(p));
+    return a;
+}
+
+// primitive arguments are passed by value, memory ones as pointer
+static inline unsigned long banana_raw(const unsigned char* a, size_t n) {
+    unsigned long raw = 0;
+    memcpy(&raw, a, n < sizeof(raw) ? n : sizeof(raw));
+    return raw;
+}
+
+// by default calls are resolved from already loaded modules ( libc syscall wrappers, target library, .. )
+#ifndef BANANA_RESOLVE
+#define BANANA_RESOLVE(name) dlsym(RTLD_DEFAULT, name)
+#endif
+
+static inline void* banana_resolve(const char* name) {
+    void* fn = BANANA_RESOLVE(name);
+    if (!fn)
+        fprintf(stderr, "[banana] unresolved call %s\n", name);
+    return fn;
+}
+
+#ifdef BANANA_RACE
+// let threads race as they want, recorded order is ignored
+static inline void banana_turn(size_t turn) { (void)turn; }
+static inline void banana_done(size_t turn) { (void)turn; }
+#else
+// keeps recorded order of calls across threads, once some thread is late for too long we go on
+static pthread_mutex_t banana_lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t banana_cvar = PTHREAD_COND_INITIALIZER;
+static size_t banana_next = 0;
+
+static inline void banana_turn(size_t turn) {
+    struct timespec deadline;
+    clock_gettime(CLOCK_REALTIME, &deadline);
+    deadline.tv_sec += 1;
+    pthread_mutex_lock(&banana_lock);
+    while (banana_next < turn) {
+        if (ETIMEDOUT == pthread_cond_timedwait(&banana_cvar, &banana_lock, &deadline)) {
+            banana_next = turn;
+            break;
+        }
+    }
+    pthread_mutex_unlock(&banana_lock);
+}
+static inline void banana_done(size_t turn) {
+    pthread_mutex_lock(&banana_lock);
+    if (banana_next <= turn)
+        banana_next = turn + 1;
+    pthread_cond_broadcast(&banana_cvar);
+    pthread_mutex_unlock(&banana_lock);
+}
+#endif
+
+#endif // BANANA_H
@@ -0,0 +1,61 @@
+extern crate core;
+extern crate libpoclog;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use core::config;
+use core::poc::trace::Trace;
+
+use libpoclog::emit;
+use libpoclog::minimize::{self, MinimizeConfig};
+
+const USAGE: &str = "usage : banana-poc <command> [--out dir] [--config minimize.toml] trace.jsonl
+  emit                                  C PoC ( poc.c + banana.h ) of recorded trace to --out ( . by default )
+  minimize --config minimize.toml       smallest trace still reproducing, min.jsonl + PoC to workdir of config";
+
+fn fail(what: String) -> ! {
+    eprintln!("[banana-poc] {}", what);
+    process::exit(1)
+}
+
+/// PoC out of trace recorded by poclog, minimized by replaying it against oracle first if asked
+///
+/// - minimize.toml is MinimizeConfig : cmd = ["banana-runner", "replay", "--desc", "table.toml", "{trace}"], oracle, ..
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = match args.next() {
+        Some(ref arg) if "-h" == arg || "--help" == arg => return println!("{}", USAGE),
+        Some(command) => command,
+        None => fail(String::from(USAGE)),
+    };
+    let mut out = String::from(".");
+    let mut cfg = None;
+    let mut trace = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = args.next().unwrap_or_else(|| fail(String::from("--out needs value"))),
+            "--config" => cfg = args.next(),
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ => trace = Some(arg),
+        }
+    }
+    let fname = trace.unwrap_or_else(|| fail(String::from(USAGE)));
+
+    match command.as_str() {
+        "emit" => {
+            let trace = Trace::load(&fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)));
+            fs::create_dir_all(&out).unwrap_or_else(|e| fail(format!("{} : {}", out, e)));
+            emit::write(&trace, &out).unwrap_or_else(|e| fail(format!("{} : {}", out, e)));
+            println!("[banana-poc] {} calls of {} threads to {}/poc.c", trace.calls.len(), trace.threads().len(), out);
+        },
+        "minimize" => {
+            let cfg = cfg.unwrap_or_else(|| fail(String::from("minimize : --config minimize.toml needed")));
+            let cfg: MinimizeConfig = config::parse_file(&cfg).unwrap_or_else(|e| fail(e.to_string()));
+            let min = minimize::run(&fname, &cfg).unwrap_or_else(|e| fail(e.to_string()));
+            println!("[banana-poc] {} calls left, min.jsonl + poc.c in {}", min.calls.len(), cfg.workdir);
+        },
+        _ => fail(String::from(USAGE)),
+    }
+}
@@ -0,0 +1,476 @@
+use std::io;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use core::exec::fd_info::Fd;
+use core::poc::trace::{
+    CallRecord,
+    NoteRecord,
+    Trace,
+};
+
+/// runtime for serialized calls, written next to PoC program
+pub const HEADER: &str = include_str!("banana.h");
+
+/// call names are free form for fuzzer, but c needs identifiers
+fn ident(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || '_' == c { c } else { '_' })
+        .collect()
+}
+
+/// call name as c string literal, it is what gets resolved at runtime
+fn literal(name: &str) -> String {
+    String::from("\"") + &name.replace('\\', "\\\\").replace('"', "\\\"") + "\""
+}
+
+/// fd_XXXX variables serialization refer to, XXXX is fd as printed by generic::u8_to_str
+fn fd_refs(poc: &str) -> Vec<String> {
+    poc.match_indices("fd_")
+        .filter(|&(ind, _)| 0 == ind || !poc[..ind].ends_with(|c: char| c.is_ascii_alphanumeric() || '_' == c))
+        .map(|(ind, _)| poc[ind + 3..]
+            .chars()
+            .take_while(|c| c.is_ascii_hexdigit())
+            .collect::<String>())
+        .collect()
+}
+
+/// generic::u8_to_str prints fd reversed, we need raw bytes back
+fn fd_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len() / 2)
+        .rev()
+        .filter_map(|ind| u8::from_str_radix(&hex[2 * ind..2 * ind + 2], 16).ok())
+        .collect()
+}
+
+/// cursor over serialized call, as Call::serialize + ISerializableArg prefixes print it
+///
+/// - serialization is c++ flavored : new arg<N>{ prefix(.., arg<N>{ {bytes} }) }
+/// - translated to c, where argument is byte buffer ( compound literal ) patched by prefixes
+struct Serialized<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Serialized<'a> {
+    fn rest(&mut self) -> &'a str {
+        let src = self.src;
+        let skip = src[self.pos..].len() - src[self.pos..].trim_start().len();
+        self.pos += skip;
+        &src[self.pos..]
+    }
+    fn eat(&mut self, token: &str) -> bool {
+        if !self.rest().starts_with(token) {
+            return false
+        }
+        self.pos += token.len();
+        true
+    }
+    fn expect(&mut self, token: &str) -> Option<()> {
+        if self.eat(token) { Some(()) } else { None }
+    }
+    fn token(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || '_' == c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+    fn number(&mut self) -> Option<usize> {
+        self.token().parse().ok()
+    }
+
+    /// whole argument, optionally casted and allocated : (name0*)new arg<N>{ .. }
+    ///
+    /// - returns c expression of buffer, its size and if it is memory argument ( passed by pointer )
+    fn arg(&mut self) -> Option<(String, usize, bool)> {
+        if self.eat("(") {
+            self.token();
+            self.expect("*)")?;
+        }
+        let memory = self.eat("new ");
+        self.expect("arg<")?;
+        let size = self.number()?;
+        self.expect(">{")?;
+        let data = self.data(size)?;
+        self.expect("}")?;
+        Some((data, size, memory))
+    }
+    /// content of argument : raw bytes, or prefix patching them
+    fn data(&mut self, size: usize) -> Option<String> {
+        if self.eat("arg<") {
+            self.number()?;
+            self.expect(">{")?;
+            self.expect("{")?;
+            let mut bytes = Vec::new();
+            while !self.eat("}") {
+                bytes.push(self.number()?.to_string());
+                self.eat(",");
+            }
+            self.expect("}")?;
+            if bytes.is_empty() {
+                return Some(String::from("(unsigned char[1]){ 0 }"))
+            }
+            return Some(String::from("(unsigned char[") + &size.to_string() + "]){ " + &bytes.join(", ") + " }")
+        }
+        let name = self.token();
+        if name.is_empty() {
+            return None
+        }
+        self.expect("(")?;
+        let mut params = Vec::new();
+        loop {
+            params.push(self.param(size)?);
+            if self.eat(")") {
+                break
+            }
+            self.expect(",")?;
+        }
+        Some(String::from(name) + "(" + &params.join(", ") + ", " + &size.to_string() + ")")
+    }
+    /// parameter of prefix : other argument ( ArgPtr ), argument being patched, or plain value
+    fn param(&mut self, size: usize) -> Option<String> {
+        let rest = self.rest();
+        if rest.starts_with("arg<") {
+            let data = rest[rest.find('{')? + 1..].trim_start().starts_with('{');
+            return if data { self.data(size) } else { self.arg().map(|(data, _, _)| data) }
+        }
+        if rest.starts_with("new ") || rest.starts_with('(') {
+            return self.arg().map(|(data, _, _)| data)
+        }
+        let start = self.pos;
+        let token = self.token();
+        if !token.is_empty() && self.rest().starts_with('(') {
+            self.pos = start;
+            return self.data(size)
+        }
+        let len = self.src[start..].find([',', ')'])?;
+        self.pos = start + len;
+        Some(self.src[start..start + len].trim().to_string())
+    }
+}
+
+/// serialized call as c statement calling wrapper, None if serialization is not understood
+fn statement(call: &CallRecord, wrapper: &str) -> Option<String> {
+    let mut poc = Serialized { src : &call.poc, pos : 0 };
+    poc.expect(&call.call)?;
+    poc.expect("(")?;
+    let mut args = Vec::new();
+    if !poc.eat(")") && !(poc.eat("void") && poc.eat(")")) {
+        loop {
+            args.push(match poc.arg()? {
+                (data, _, true) => String::from("(unsigned long)") + &data,
+                (data, size, false) => String::from("banana_raw(") + &data + ", " + &size.to_string() + ")",
+            });
+            if poc.eat(")") {
+                break
+            }
+            poc.expect(",")?;
+        }
+    }
+    poc.expect(";")?;
+    if args.len() != call.args.len() {
+        return None
+    }
+    Some(wrapper.to_string() + " ? " + wrapper + "(" + &args.join(",\n\t\t") + ") : -1;")
+}
+
+/// calls PoC can not reproduce : not serialized in trace, or serialization is not understood
+///
+/// - they keep their turn in program, but nothing is called there, so PoC may not crash as run did
+pub fn skipped(trace: &Trace) -> Vec<&CallRecord> {
+    trace.calls
+        .iter()
+        .filter(|call| call.poc.is_empty() || statement(call, "poc").is_none())
+        .collect()
+}
+
+enum Line<'a> {
+    Call(&'a CallRecord),
+    Note(&'a NoteRecord),
+}
+
+/// c program reproducing recorded run
+///
+/// - one thread per fuzzed state ( recorded thread ), started in order of their first call
+/// - calls follow recorded order across threads ( compile with -DBANANA_RACE to let them race )
+/// - calls are resolved at runtime by their recorded name, see BANANA_RESOLVE in banana.h
+/// - ctor's return value is taken as fd of state, once trace shows state got its fd
+pub fn program(trace: &Trace) -> String {
+    let turns = trace.calls
+        .iter()
+        .enumerate()
+        .map(|(turn, call)| (call.seq, turn))
+        .collect::< BTreeMap<u64, usize> >();
+
+    let mut fds = BTreeSet::new();
+    // recorded name -> ( wrapper, number of args ), wrapper is unique even if names sanitize to same one
+    let mut calls = BTreeMap::new();
+    let mut wrappers = BTreeSet::new();
+    for call in trace.calls.iter() {
+        fds.extend(fd_refs(&call.poc));
+        if !Fd::new(&call.fd).is_invalid() {
+            fds.insert(generic::u8_to_str(&call.fd));
+        }
+        if calls.contains_key(&call.call) {
+            continue
+        }
+        let mut wrapper = String::from("poc_") + &ident(&call.call);
+        if wrappers.contains(&wrapper) {
+            wrapper += &(String::from("_") + &wrappers.len().to_string());
+        }
+        wrappers.insert(wrapper.clone());
+        calls.insert(call.call.clone(), (wrapper, call.args.len()));
+    }
+
+    let mut src = String::from("// banana fuzzer PoC, recorded trace version ") +
+        &trace.header.version.to_string() + "\n" +
+        "//\n" +
+        "// cc -pthread poc.c -ldl -o poc\n";
+    let skipped = skipped(trace);
+    if !skipped.is_empty() {
+        src += &(String::from("//\n// WARNING : ") + &skipped.len().to_string() +
+            " calls are skipped, this PoC may not reproduce recorded run\n");
+        for call in skipped.iter() {
+            src += &(String::from("//   seq ") + &call.seq.to_string() + " : " + &call.call + " of " + &call.state + "\n");
+        }
+    }
+    src += "#include \"banana.h\"\n\n";
+
+    for hex in fds.iter() {
+        let data = fd_bytes(hex);
+        src += &(String::from("static struct banana_fd fd_") + hex + " = { " +
+            &data.len().min(64).to_string() + ", { " +
+            &data.iter().take(64).map(|b| b.to_string() + ", ").collect::<String>() +
+            "} };\n");
+    }
+    src += "\n";
+
+    // wrappers are prefixed, as calls are likely to clash with libc ( close, read, .. )
+    for &(ref wrapper, count) in calls.values() {
+        let params = match count {
+            0 => String::from("void"),
+            count => vec!["unsigned long"; count].join(", "),
+        };
+        src += &(String::from("typedef long (*") + wrapper + "_t)(" + &params + ");\n" +
+            "static " + wrapper + "_t " + wrapper + ";\n");
+    }
+    src += "\n";
+
+    let threads = trace.threads();
+    for (ind, &(tid, ref records)) in threads.iter().enumerate() {
+        let mut lines = records
+            .iter()
+            .map(|&call| (call.seq, Line::Call(call)))
+            .chain(trace.notes
+                .iter()
+                .filter(|note| note.tid == tid)
+                .map(|note| (note.seq, Line::Note(note))))
+            .collect::< Vec<(u64, Line)> >();
+        lines.sort_by_key(|&(seq, _)| seq);
+
+        src += &(String::from("// state ") + &records[0].state + " " + &format!("{:?}", records[0].sid) +
+            ", thread " + &tid.to_string() + "\n" +
+            "static void* state_" + &ind.to_string() + "(void* unused) {\n" +
+            "\tlong ret = 0;\n" +
+            "\t(void)unused;\n" +
+            "\t(void)ret;\n");
+        for (pos, (_, line)) in lines.iter().enumerate() {
+            let call = match *line {
+                Line::Note(note) => {
+                    src += &(String::from("\t// [") + &note.cmd + "] " + &note.info.replace('\n', " ") + "\n");
+                    continue
+                },
+                Line::Call(call) => call,
+            };
+            let turn = turns[&call.seq].to_string();
+            if call.poc.is_empty() {
+                src += &(String::from("\t// ") + &call.call + " : not serialized in this trace, skipped\n" +
+                    "\tbanana_turn(" + &turn + ");\n" +
+                    "\tbanana_done(" + &turn + ");\n");
+                continue
+            }
+            match statement(call, &calls[&call.call].0) {
+                Some(statement) => src += &(String::from("\tbanana_turn(") + &turn + ");\n" +
+                    "\tret = " + &statement + "\n" +
+                    "\tbanana_done(" + &turn + ");\n"),
+                None => {
+                    src += &(String::from("\t// ") + &call.call + " : serialization not understood, skipped\n" +
+                        "\tbanana_turn(" + &turn + ");\n" +
+                        "\tbanana_done(" + &turn + ");\n");
+                    continue
+                },
+            }
+            if !Fd::new(&call.fd).is_invalid() {
+                continue
+            }
+            let next = lines[pos + 1..]
+                .iter()
+                .filter_map(|(_, line)| match *line {
+                    Line::Call(call) => Some(call),
+                    Line::Note(_) => None,
+                })
+                .next();
+            if let Some(next) = next {
+                if !Fd::new(&next.fd).is_invalid() {
+                    src += &(String::from("\tbanana_fd_set(&fd_") + &generic::u8_to_str(&next.fd) + ", ret);\n");
+                }
+            }
+        }
+        src += "\treturn NULL;\n}\n\n";
+    }
+
+    src += "int main(void) {\n";
+    if !threads.is_empty() {
+        src += &(String::from("\tpthread_t states[") + &threads.len().to_string() + "];\n\tsize_t ind;\n\n");
+    }
+    for (name, (wrapper, _)) in calls.iter() {
+        src += &(String::from("\t") + wrapper + " = (" + wrapper + "_t)banana_resolve(" + &literal(name) + ");\n");
+    }
+    if !threads.is_empty() {
+        src += "\n";
+        for ind in 0..threads.len() {
+            src += &(String::from("\tpthread_create(&states[") + &ind.to_string() + "], NULL, state_" + &ind.to_string() + ", NULL);\n");
+        }
+        src += &(String::from("\tfor (ind = 0; ind < ") + &threads.len().to_string() + "; ind++)\n\t\tpthread_join(states[ind], NULL);\n");
+    }
+    src += "\treturn 0;\n}\n";
+    src
+}
+
+/// write PoC program ( poc.c ) and its runtime ( banana.h ) to dir
+///
+/// - skipped calls are reported on stderr too, program header lists them as well
+pub fn write(trace: &Trace, dir: &str) -> Result<(), io::Error> {
+    let skipped = skipped(trace);
+    if !skipped.is_empty() {
+        eprintln!("[poclog] WARNING : {} of {} calls can not be emitted, PoC may not reproduce :", skipped.len(), trace.calls.len());
+        for call in skipped.iter() {
+            eprintln!("[poclog]   seq {} : {} of {}", call.seq, call.call, call.state);
+        }
+    }
+    generic::write_file_raw(
+        &Path::new(dir).join("banana.h").to_string_lossy(),
+        HEADER.as_bytes())?;
+    generic::write_file_raw(
+        &Path::new(dir).join("poc.c").to_string_lossy(),
+        program(trace).as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::exec::id::CallTableId;
+    use core::poc::trace::{ArgRecord, TraceHeader};
+    use core::state::id::StateTableId;
+
+    const SOCKET: &str = "socket(\n\targ<4>{ arg<4>{ {2, 0, 0, 0, } }},\n\targ<4>{ arg<4>{ {1, 0, 0, 0, } }},\n\targ<4>{ arg<4>{ {0, 0, 0, 0, } }});";
+    const SETSOCKOPT: &str = "setsockopt(\n\targ<4>{ state_fd(fd_00000004, 0,4,0, arg<4>{ {4, 0, 0, 0, } })},\n\
+        \targ<4>{ arg<4>{ {1, 0, 0, 0, } }},\n\targ<4>{ arg<4>{ {8, 0, 0, 0, } }},\n\
+        \targ<8>{ ArgPtr(new arg<4>{ arg<4>{ {1, 0, 0, 0, } }}, 0, arg<8>{ {0, 160, 227, 210, 15, 86, 0, 0, } })},\n\
+        \targ<4>{ arg<4>{ {4, 0, 0, 0, } }});";
+    const CONNECT: &str = "connect(\n\targ<4>{ state_fd(fd_00000004, 0,4,0, arg<4>{ {4, 0, 0, 0, } })},\n\
+        \t(connect1*)new arg<16>{ arg<16>{ {2, 0, 141, 145, 127, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, } }},\n\
+        \targ<4>{ arg<4>{ {16, 0, 0, 0, } }});";
+    const CLOSE: &str = "close(\n\targ<4>{ state_fd(fd_00000004, 0,4,0, arg<4>{ {4, 0, 0, 0, } })});";
+
+    fn record(seq: u64, tid: u64, fd: &[u8], call: &str, args: usize, poc: &str) -> CallRecord {
+        CallRecord {
+            seq,
+            tid,
+            stream : 0,
+            state : String::from("socket"),
+            sid : StateTableId::Id(1),
+            fd : fd.to_vec(),
+            level : 0,
+            index : 0,
+            cid : CallTableId::Id(0x100 + seq),
+            call : call.to_string(),
+            args : vec![ArgRecord { data : vec![0; 4], extra : Vec::new() }; args],
+            poc : poc.to_string(),
+        }
+    }
+
+    #[test]
+    fn statements_of_serialized_calls() {
+        assert_eq!(Some(String::from("poc_socket ? poc_socket(\
+            banana_raw((unsigned char[4]){ 2, 0, 0, 0 }, 4),\n\t\t\
+            banana_raw((unsigned char[4]){ 1, 0, 0, 0 }, 4),\n\t\t\
+            banana_raw((unsigned char[4]){ 0, 0, 0, 0 }, 4)) : -1;")),
+            statement(&record(0, 1, &[], "socket", 3, SOCKET), "poc_socket"));
+
+        let setsockopt = statement(&record(1, 1, &[4, 0, 0, 0], "setsockopt", 5, SETSOCKOPT), "poc_setsockopt").unwrap();
+        assert!(setsockopt.contains("banana_raw(state_fd(fd_00000004, 0, 4, 0, (unsigned char[4]){ 4, 0, 0, 0 }, 4), 4)"), "{}", setsockopt);
+        assert!(setsockopt.contains("banana_raw(ArgPtr((unsigned char[4]){ 1, 0, 0, 0 }, 0, \
+            (unsigned char[8]){ 0, 160, 227, 210, 15, 86, 0, 0 }, 8), 8)"), "{}", setsockopt);
+
+        let connect = statement(&record(2, 1, &[4, 0, 0, 0], "connect", 3, CONNECT), "poc_connect").unwrap();
+        assert!(connect.contains("(unsigned long)(unsigned char[16]){ 2, 0, 141, 145, 127, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0 }"), "{}", connect);
+
+        assert_eq!(Some(String::from("poc_x ? poc_x() : -1;")), statement(&record(0, 1, &[], "x", 0, "x(void);"), "poc_x"));
+        assert_eq!(Some(String::from("poc_x ? poc_x() : -1;")), statement(&record(0, 1, &[], "x", 0, "x();"), "poc_x"));
+    }
+
+    #[test]
+    fn not_understood_serialization() {
+        for (args, poc) in [
+            (2, SOCKET),
+            (3, "socket(arg<4>{ arg<4>{ {2, 0, 0, 0, } }});"),
+            (3, &SOCKET[..SOCKET.len() - 1]),
+            (3, "bind(arg<4>{ arg<4>{ {2, } }});"),
+            (1, "socket(arg<4>{ arg<4>{ {2, x, } }});"),
+            (1, "socket(arg<4>{ prefix(arg<4>{ {2, } });"),
+            (1, "socket(garbage);"),
+        ] {
+            assert_eq!(None, statement(&record(0, 1, &[], "socket", args, poc), "poc_socket"), "{}", poc);
+        }
+    }
+
+    #[test]
+    fn names_and_fds() {
+        assert_eq!("accept4_ctor_", ident("accept4#ctor$"));
+        assert_eq!("\"a\\\\b\\\"c\"", literal("a\\b\"c"));
+        assert_eq!(vec!["00000004", "0a0b"], fd_refs("state_fd(fd_00000004, my_fd_1, x, fd_0a0b)"));
+        assert!(fd_refs("nofd_1").is_empty());
+        assert_eq!(vec![4, 0, 0, 0], fd_bytes("00000004"));
+        assert_eq!(vec![0x0b, 0x0a], fd_bytes("0a0b"));
+    }
+
+    #[test]
+    fn program_of_two_threads() {
+        let mut trace = Trace::new(TraceHeader { version : 4, created : 0, seed : 0 });
+        trace.calls = vec![
+            record(0, 7, &[], "socket", 3, SOCKET),
+            record(1, 8, &[], "socket", 3, SOCKET),
+            record(2, 7, &[4, 0, 0, 0], "setsockopt", 5, SETSOCKOPT),
+            record(3, 8, &[5, 0, 0, 0], "setsockopt", 5, ""),
+            record(4, 7, &[4, 0, 0, 0], "close", 1, CLOSE),
+        ];
+        trace.notes = vec![NoteRecord { seq : 2, tid : 7, cmd : String::from("module"), info : String::from("a\nb") }];
+
+        assert_eq!(vec![3], skipped(&trace).iter().map(|call| call.seq).collect::< Vec<u64> >());
+        let src = program(&trace);
+        assert!(src.contains("// WARNING : 1 calls are skipped"), "{}", src);
+        assert!(src.contains("//   seq 3 : setsockopt of socket\n"));
+        assert!(src.contains("static struct banana_fd fd_00000004 = { 4, { 4, 0, 0, 0, } };"));
+        assert!(src.contains("static struct banana_fd fd_00000005 = { 4, { 5, 0, 0, 0, } };"));
+        assert!(src.contains("typedef long (*poc_socket_t)(unsigned long, unsigned long, unsigned long);"));
+        assert!(src.contains("typedef long (*poc_close_t)(unsigned long);"));
+        assert!(src.contains("\tpoc_setsockopt = (poc_setsockopt_t)banana_resolve(\"setsockopt\");"));
+        assert!(src.contains("static void* state_0(void* unused)"));
+        assert!(src.contains("static void* state_1(void* unused)"));
+        assert!(src.contains("pthread_t states[2];"));
+        assert!(src.contains("\t// [module] a b\n"));
+
+        // ctor of first thread feeds fd of its state, turns keep recorded order across threads
+        let state_0 = &src[src.find("state_0(").unwrap()..src.find("state_1(").unwrap()];
+        assert!(state_0.find("banana_turn(0);").unwrap() < state_0.find("banana_fd_set(&fd_00000004, ret);").unwrap());
+        assert!(state_0.find("banana_turn(2);").unwrap() < state_0.find("banana_turn(4);").unwrap());
+        let state_1 = &src[src.find("state_1(").unwrap()..src.find("int main").unwrap()];
+        assert!(state_1.contains("banana_fd_set(&fd_00000005, ret);"));
+        assert!(state_1.contains("\t// setsockopt : not serialized in this trace, skipped\n\tbanana_turn(3);\n\tbanana_done(3);\n"));
+    }
+}
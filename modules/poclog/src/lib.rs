@@ -0,0 +1,109 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate core;
+extern crate generic;
+
+use core::exec::call::Call;
+use core::banana::observer::{ICallObserver, IStateObserver};
+use core::exec::watchdog::Hang;
+use core::rng;
+use core::state::state::StateInfo;
+use core::poc::trace::{
+    ArgRecord,
+    CallRecord,
+    NoteRecord,
+    TraceEntry,
+};
+
+pub mod logger;
+use logger::Logger;
+
+pub mod emit;
+pub mod minimize;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PocLogConfig {
+    /// where to store trace, overwritten per fuzzing session
+    trace: String,
+    /// sync to disk after every call, slow but survives also target == kernel crashes
+    sync: bool,
+}
+
+/// records every approved call to trace, so we can replay it later
+///
+/// - observers are asked in order of modules.toml online list, and first deny stops asking
+///     - therefore put libpoclog as last one, then only calls really executed are recorded
+struct PocLog { }
+
+impl ICallObserver for PocLog {
+    fn notify(&self, state: &StateInfo, call: &Call) -> bool {
+        Logger::safe_log(TraceEntry::Call(CallRecord {
+            seq : 0,
+            tid : generic::get_tid(),
+            stream : rng::stream(),
+            state : state.name.clone(),
+            sid : state.id.clone(),
+            fd : state.fd.data().to_vec(),
+            level : call.level(),
+            index : call.index(),
+            cid : call.id(),
+            call : call.name().to_string(),
+            args : (0..call.n_args())
+                .map(|ind| ArgRecord {
+                    data : call.args_view(ind).data().to_vec(),
+                    extra : call.args_view(ind).dump_extra(),
+                })
+                .collect(),
+            poc : call.serialize(state.fd.data()),
+        }));
+        true
+    }
+    fn on_shutdown(&self) {
+        Logger::flush()
+    }
+    /// hang is noted in thread of hanged call, so it shows right after it in trace ( and PoC )
+    fn notify_hang(&self, _: &StateInfo, hang: &Hang) {
+        Logger::safe_log(TraceEntry::Note(NoteRecord {
+            seq : 0,
+            tid : hang.tid,
+            cmd : String::from("hang"),
+            info : format!("{} {:?} did not return for {:?}", hang.name, hang.id, hang.elapsed),
+        }));
+    }
+}
+
+impl PocLog {
+    pub(crate) fn new(cfg: &PocLogConfig) -> PocLog {
+        if let Err(e) = Logger::init(&cfg.trace, cfg.sync) {
+            panic!("[poclog] unable to create trace {} : {}", cfg.trace, e);
+        }
+        PocLog { }
+    }
+}
+
+/// wrap module provided line ( ModuleCallbacks::log_call ) to trace entry
+pub fn decorate(cmd: String, info: &str) -> TraceEntry {
+    TraceEntry::Note(NoteRecord {
+        seq : 0,
+        tid : generic::get_tid(),
+        cmd,
+        info : info.to_string(),
+    })
+}
+
+pub fn observers(
+    cfg: &Option<PocLogConfig>,
+) -> (
+    Option<Box<dyn IStateObserver>>,
+    Option<Box<dyn ICallObserver>>,
+) {
+    match *cfg {
+        Some(ref cfg) => (None, Some(Box::new(PocLog::new(cfg)))),
+        _ => (None, None),
+    }
+}
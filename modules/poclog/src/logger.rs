@@ -0,0 +1,101 @@
+use std::io;
+use std::io::Write;
+use std::fs::File;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use core::rng;
+use core::poc::trace::{
+    TraceEntry,
+    TraceHeader,
+    TRACE_VERSION,
+};
+
+lazy_static! {
+    /// one trace per fuzzing session, shared by all fuzzing threads
+    static ref LOGGER: Mutex< Option<Logger> > = Mutex::new(None);
+}
+
+/// append only writer of trace
+///
+/// - every entry is written ( and optionaly synced ) right away, target crash should not cost us log
+/// - seq is assigned under lock, therefore order in file == order of calls
+pub struct Logger {
+    fname: String,
+    file: File,
+    sync: bool,
+    seq: u64,
+}
+
+impl Logger {
+    /// start new trace, overwriting old one
+    pub fn init(fname: &str, sync: bool) -> Result<(), io::Error> {
+        let mut logger = Logger {
+            fname : fname.to_string(),
+            file : File::create(fname)?,
+            sync,
+            seq : 0,
+        };
+        logger.write(&TraceEntry::Header(TraceHeader {
+            version : TRACE_VERSION,
+            created : match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(time) => time.as_secs(),
+                Err(_) => 0,
+            },
+            seed : rng::seed(),
+        }))?;
+        match LOGGER.lock() {
+            Ok(mut log) => *log = Some(logger),
+            Err(e) => panic!("poclog: logger is poisoned {}", e),
+        };
+        Ok(())
+    }
+
+    fn write(&mut self, entry: &TraceEntry) -> Result<(), io::Error> {
+        self.file.write_all(entry.to_line().as_bytes())?;
+        if self.sync {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// stamp entry with sequence number and write it out
+    pub fn safe_log(mut entry: TraceEntry) {
+        let mut log = match LOGGER.lock() {
+            Ok(log) => log,
+            Err(_) => return,//other thread died in middle of logging, nothing more to record
+        };
+        if let Some(ref mut logger) = *log {
+            match entry {
+                TraceEntry::Call(ref mut call) => call.seq = logger.seq,
+                TraceEntry::Note(ref mut note) => note.seq = logger.seq,
+                TraceEntry::Header(_) => return,
+            }
+            logger.seq += 1;
+            if let Err(e) = logger.write(&entry) {
+                println!("[poclog] unable to write to {} : {}", logger.fname, e);
+            }
+        }
+    }
+
+    /// whole trace recorded so far
+    pub fn log() -> String {
+        let fname = match LOGGER.lock() {
+            Ok(log) => match *log {
+                Some(ref logger) => logger.fname.clone(),
+                None => return String::from(""),
+            },
+            Err(_) => return String::from(""),
+        };
+        generic::read_file(&fname).unwrap_or_default()
+    }
+
+    pub fn flush() {
+        if let Ok(mut log) = LOGGER.lock() {
+            if let Some(ref mut logger) = *log {
+                let _ = logger.file.flush();
+                let _ = logger.file.sync_all();
+            }
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use std::io;
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use core::poc::trace::{
+    NoteRecord,
+    Trace,
+};
+
+use emit;
+
+/// sanitizer ( and kernel ) reports we consider as crash
+const SANITIZER_REPORTS: &[&str] = &[
+    "ERROR: AddressSanitizer",
+    "ERROR: LeakSanitizer",
+    "ERROR: MemorySanitizer",
+    "WARNING: ThreadSanitizer",
+    "ERROR: ThreadSanitizer",
+    "runtime error:",
+    "BUG: KASAN",
+    "BUG: KCSAN",
+];
+
+/// how we know crash still reproduces
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Oracle {
+    /// child did not exit cleanly : non zero exit code or killed by signal
+    Crash,
+    /// child exited with exactly this code
+    Status(i32),
+    /// child was killed by this signal ( unix only )
+    Signal(i32),
+    /// log contains pattern, empty file means output ( stdout + stderr ) of child
+    Log { file: String, pattern: String },
+    /// sanitizer report in log, empty file means output of child
+    /// - kind narrows it to specific report ( heap-use-after-free, data race, .. ), empty for any
+    Sanitizer { file: String, kind: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MinimizeConfig {
+    /// command replaying trace, {trace} is replaced by path of trace to replay
+    /// - f.e. banana-runner replay --desc table.toml {trace}, or own binary feeding core::poc::replay::schedule
+    pub cmd: Vec<String>,
+    pub oracle: Oracle,
+    /// where candidates, outputs of child and results ( min.jsonl, poc.c, banana.h ) go
+    pub workdir: String,
+    /// kill child after this time, timeout == not reproduced
+    pub timeout_ms: u64,
+    /// races are flaky, reproduced if any of tries reproduces
+    pub tries: usize,
+    /// trace recorded by child ( its poclog config ), if any
+    /// - resetting arguments makes serialized calls in trace stale, therefore PoC is emitted from
+    ///   this recording of last reproducing run when available
+    #[serde(default)]
+    pub record: String,
+    /// try to reset argument fields to zeros too, not only whole arguments
+    #[serde(default)]
+    pub fields: bool,
+}
+
+/// delta debugging of recorded trace against user provided oracle
+pub struct Minimizer<'a> {
+    cfg: &'a MinimizeConfig,
+    runs: usize,
+    /// recording of last reproducing run
+    recorded: Option<Trace>,
+}
+
+impl<'a> Minimizer<'a> {
+    pub fn new(cfg: &'a MinimizeConfig) -> Minimizer<'a> {
+        Minimizer {
+            cfg,
+            runs : 0,
+            recorded : None,
+        }
+    }
+
+    /// how many times we replayed so far
+    pub fn runs(&self) -> usize { self.runs }
+
+    /// smallest trace which still reproduces
+    ///
+    /// 1. drop whole threads ( states )
+    /// 2. drop calls
+    /// 3. reset arguments ( and optionaly parts of them ) to zeros
+    ///
+    /// - loaders of arguments ( IArgLeaf::load ) patch fds and pointers on replay, so zeros are safe
+    pub fn minimize(&mut self, trace: &Trace) -> Result<Trace, io::Error> {
+        fs::create_dir_all(&self.cfg.workdir)?;
+        if !self.reproduces(trace)? {
+            return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "[poclog] recorded trace does not reproduce"))
+        }
+
+        let tids = trace.threads()
+            .iter()
+            .map(|&(tid, _)| tid)
+            .collect::< Vec<u64> >();
+        let tids = self.ddmin(tids, |tids| subset(trace, |tid, _| tids.contains(&tid)))?;
+        let trace = subset(trace, |tid, _| tids.contains(&tid));
+        println!("[poclog] minimize : {} threads left", tids.len());
+
+        let seqs = trace.calls
+            .iter()
+            .map(|call| call.seq)
+            .collect::< Vec<u64> >();
+        let seqs = self.ddmin(seqs, |seqs| subset(&trace, |_, seq| seqs.contains(&seq)))?;
+        let mut trace = subset(&trace, |_, seq| seqs.contains(&seq));
+        println!("[poclog] minimize : {} calls left", seqs.len());
+
+        for ind in 0..trace.calls.len() {
+            for arg in 0..trace.calls[ind].args.len() {
+                let size = trace.calls[ind].args[arg].data.len() + trace.calls[ind].args[arg].extra.len();
+                self.reset(&mut trace, ind, arg, 0, size)?;
+            }
+        }
+        println!("[poclog] minimize : done in {} runs", self.runs);
+        Ok(trace)
+    }
+
+    /// zero [from, to) of argument ( data + extra ), if it does not reproduce bisect it
+    fn reset(&mut self, trace: &mut Trace, ind: usize, arg: usize, from: usize, to: usize) -> Result<(), io::Error> {
+        if from >= to {
+            return Ok(())
+        }
+        let orig = trace.calls[ind].args[arg].clone();
+        let mut zeroed = orig.clone();
+        let split = zeroed.data.len();
+        for pos in from..to {
+            let byte = if pos < split {
+                &mut zeroed.data[pos]
+            } else {
+                &mut zeroed.extra[pos - split]
+            };
+            *byte = 0;
+        }
+        if zeroed.data == orig.data && zeroed.extra == orig.extra {
+            return Ok(())
+        }
+
+        trace.calls[ind].args[arg] = zeroed;
+        if self.reproduces(trace)? {
+            return Ok(())
+        }
+        trace.calls[ind].args[arg] = orig;
+
+        if !self.cfg.fields || to - from < 2 {
+            return Ok(())
+        }
+        let half = from + (to - from) / 2;
+        self.reset(trace, ind, arg, from, half)?;
+        self.reset(trace, ind, arg, half, to)
+    }
+
+    /// ddmin over units, candidate makes trace out of units we keep
+    fn ddmin<T, F>(&mut self, mut units: Vec<T>, candidate: F) -> Result<Vec<T>, io::Error>
+        where
+            T: Clone,
+            F: Fn(&[T]) -> Trace,
+    {
+        let mut n = 2;
+        while units.len() >= 2 {
+            let chunk = units.len().div_ceil(n);
+            let chunks = units
+                .chunks(chunk)
+                .map(|chunk| chunk.to_vec())
+                .collect::< Vec<Vec<T>> >();
+
+            let mut reduced = None;
+            for chunk in chunks.iter() {
+                if self.reproduces(&candidate(chunk))? {
+                    reduced = Some((chunk.clone(), 2));
+                    break
+                }
+            }
+            if reduced.is_none() && chunks.len() > 2 {
+                for skip in 0..chunks.len() {
+                    let rest = chunks
+                        .iter()
+                        .enumerate()
+                        .filter(|&(ind, _)| ind != skip)
+                        .flat_map(|(_, chunk)| chunk.iter().cloned())
+                        .collect::< Vec<T> >();
+                    if self.reproduces(&candidate(&rest))? {
+                        reduced = Some((rest, (n - 1).max(2)));
+                        break
+                    }
+                }
+            }
+
+            match reduced {
+                Some((rest, granularity)) => {
+                    units = rest;
+                    n = granularity;
+                },
+                None => {
+                    if n >= units.len() {
+                        break
+                    }
+                    n = (2 * n).min(units.len());
+                },
+            }
+        }
+        Ok(units)
+    }
+
+    /// replay candidate trace ( up to tries times ) and ask oracle
+    pub fn reproduces(&mut self, trace: &Trace) -> Result<bool, io::Error> {
+        let fname = Path::new(&self.cfg.workdir).join("try.jsonl").to_string_lossy().to_string();
+        trace.save(&fname)?;
+        for _ in 0..self.cfg.tries.max(1) {
+            let record = Mark::new(&self.cfg.record);
+            if self.run(&fname)? {
+                if !self.cfg.record.is_empty() && record.changed(&self.cfg.record) {
+                    self.recorded = Trace::load(&self.cfg.record).ok();
+                }
+                return Ok(true)
+            }
+        }
+        Ok(false)
+    }
+
+    fn run(&mut self, fname: &str) -> Result<bool, io::Error> {
+        self.runs += 1;
+        let output = Path::new(&self.cfg.workdir).join("output.log").to_string_lossy().to_string();
+        let args = self.cfg.cmd
+            .iter()
+            .map(|arg| arg.replace("{trace}", fname))
+            .collect::< Vec<String> >();
+        if args.is_empty() {
+            return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "[poclog] minimize cmd is empty"))
+        }
+        let out = File::create(&output)?;
+        // oracle log may be user's one ( kernel log, .. ), only what this run wrote is looked at
+        let logs = self.logs()
+            .into_iter()
+            .map(|log| { let mark = Mark::new(&log); (log, mark) })
+            .collect::< Vec<(String, Mark)> >();
+        let mut child = Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(Stdio::null())
+            .stdout(out.try_clone()?)
+            .stderr(out)
+            .spawn()?;
+
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status)
+            }
+            if start.elapsed() > Duration::from_millis(self.cfg.timeout_ms) {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        let status = match status {
+            Some(status) => status,
+            None => return Ok(false),
+        };
+
+        Ok(match self.cfg.oracle {
+            Oracle::Crash => !status.success(),
+            Oracle::Status(code) => Some(code) == status.code(),
+            Oracle::Signal(sig) => Some(sig) == signal(&status),
+            Oracle::Log { ref pattern, .. } => logs
+                .iter()
+                .any(|(log, mark)| mark.fresh(log).contains(pattern)),
+            Oracle::Sanitizer { ref kind, .. } => logs
+                .iter()
+                .any(|(log, mark)| mark.fresh(log)
+                    .lines()
+                    .any(|line| SANITIZER_REPORTS.iter().any(|report| line.contains(report)) &&
+                        (kind.is_empty() || line.contains(kind.as_str())))),
+        })
+    }
+
+    /// logs oracle looks at
+    fn logs(&self) -> Vec<String> {
+        let output = Path::new(&self.cfg.workdir).join("output.log").to_string_lossy().to_string();
+        match self.cfg.oracle {
+            Oracle::Log { ref file, .. } |
+            Oracle::Sanitizer { ref file, .. } if !file.is_empty() => vec![file.clone()],
+            _ => vec![output],
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+#[cfg(not(unix))]
+fn signal(_: &ExitStatus) -> Option<i32> { None }
+
+/// file we do not own ( oracle log, record ) as it was before run, it is never removed
+struct Mark {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Mark {
+    fn new(fname: &str) -> Mark {
+        match fs::metadata(fname) {
+            Ok(meta) => Mark { len : meta.len(), modified : meta.modified().ok() },
+            Err(_) => Mark { len : 0, modified : None },
+        }
+    }
+    /// file was written since mark
+    fn changed(&self, fname: &str) -> bool {
+        let now = Mark::new(fname);
+        now.len != self.len || now.modified != self.modified
+    }
+    /// text written since mark : appended part, or whole file if it was rewritten
+    fn fresh(&self, fname: &str) -> String {
+        if !self.changed(fname) {
+            return String::new()
+        }
+        match fs::read(fname) {
+            Ok(data) => {
+                let from = if data.len() as u64 > self.len { self.len as usize } else { 0 };
+                String::from_utf8_lossy(&data[from..]).to_string()
+            },
+            Err(_) => String::new(),
+        }
+    }
+}
+
+/// trace with calls we want to keep, notes follow their threads
+fn subset<F: Fn(u64, u64) -> bool>(trace: &Trace, keep: F) -> Trace {
+    let mut sub = Trace::new(trace.header.clone());
+    sub.calls = trace.calls
+        .iter()
+        .filter(|call| keep(call.tid, call.seq))
+        .cloned()
+        .collect();
+    sub.notes = trace.notes
+        .iter()
+        .filter(|note| sub.calls.iter().any(|call| call.tid == note.tid))
+        .cloned()
+        .collect();
+    sub
+}
+
+/// calls of min whose arguments were reset, their serialization ( CallRecord::poc ) is the original one
+fn stale(orig: &Trace, min: &Trace) -> Vec<u64> {
+    min.calls
+        .iter()
+        .filter(|call| orig.calls
+            .iter()
+            .find(|orig| orig.seq == call.seq)
+            .is_some_and(|orig| orig.args
+                .iter()
+                .zip(call.args.iter())
+                .any(|(orig, arg)| orig.data != arg.data || orig.extra != arg.extra)))
+        .map(|call| call.seq)
+        .collect()
+}
+
+/// minimize trace and store min.jsonl + PoC program to workdir
+///
+/// - without record, PoC is emitted from serialization of original calls, so reset arguments
+///   are not in it ( min.jsonl has them ) : it is reported loudly, set record to get exact PoC
+pub fn run(trace: &str, cfg: &MinimizeConfig) -> Result<Trace, io::Error> {
+    let trace = Trace::load(trace)?;
+    let mut minimizer = Minimizer::new(cfg);
+    let min = minimizer.minimize(&trace)?;
+    min.save(&Path::new(&cfg.workdir).join("min.jsonl").to_string_lossy())?;
+    match minimizer.recorded {
+        Some(ref recorded) => emit::write(recorded, &cfg.workdir)?,
+        None => {
+            let stale = stale(&trace, &min);
+            let mut poc = subset(&min, |_, _| true);
+            for call in min.calls.iter().filter(|call| stale.contains(&call.seq)) {
+                poc.notes.push(NoteRecord {
+                    seq : call.seq,
+                    tid : call.tid,
+                    cmd : String::from("poclog"),
+                    info : String::from("WARNING : original arguments of call above, min.jsonl has them reset"),
+                });
+            }
+            if !stale.is_empty() {
+                eprintln!("[poclog] WARNING : no recording of reproducing run ( record is not set or child did not write it ), \
+                    {} calls of PoC keep their original arguments, reset ones are in min.jsonl only :", stale.len());
+                for seq in stale.iter() {
+                    eprintln!("[poclog]   seq {}", seq);
+                }
+            }
+            emit::write(&poc, &cfg.workdir)?
+        },
+    }
+    Ok(min)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    use core::exec::id::CallTableId;
+    use core::poc::trace::{ArgRecord, CallRecord, TraceHeader};
+    use core::state::id::StateTableId;
+
+    /// calls c0, c1, .. spread over threads, every one with arg of its seq
+    fn trace(calls: u64, threads: u64) -> Trace {
+        let mut trace = Trace::new(TraceHeader { version : 4, created : 0, seed : 0 });
+        trace.calls = (0..calls)
+            .map(|seq| CallRecord {
+                seq,
+                tid : seq % threads,
+                stream : 0,
+                state : String::from("state"),
+                sid : StateTableId::Id(1),
+                fd : Vec::new(),
+                level : 0,
+                index : 0,
+                cid : CallTableId::Id(seq),
+                call : format!("c{}", seq),
+                args : vec![ArgRecord { data : vec![seq as u8 + 1; 4], extra : vec![7] }],
+                poc : String::new(),
+            })
+            .collect();
+        trace.notes = vec![NoteRecord { seq : 0, tid : 0, cmd : String::from("x"), info : String::new() }];
+        trace
+    }
+
+    /// child crashes as long as trace has all of calls
+    fn config(name: &str, calls: &[u64]) -> MinimizeConfig {
+        let check = calls
+            .iter()
+            .map(|seq| format!("grep -q '\"call\":\"c{}\"' {{trace}}", seq))
+            .collect::< Vec<String> >()
+            .join(" && ");
+        MinimizeConfig {
+            cmd : vec![String::from("sh"), String::from("-c"), check + " && exit 3; exit 0"],
+            oracle : Oracle::Status(3),
+            workdir : env::temp_dir()
+                .join(format!("banana-minimize-{}-{}", process::id(), name))
+                .to_string_lossy()
+                .to_string(),
+            timeout_ms : 10_000,
+            tries : 1,
+            record : String::new(),
+            fields : false,
+        }
+    }
+
+    #[test]
+    fn ddmin_finds_calls_needed() {
+        let cfg = config("ddmin", &[3, 12]);
+        let mut minimizer = Minimizer::new(&cfg);
+        let min = minimizer.minimize(&trace(16, 4)).unwrap();
+        assert_eq!(vec![3, 12], min.calls.iter().map(|call| call.seq).collect::< Vec<u64> >());
+        assert!(min.calls.iter().all(|call| call.args[0].data.iter().chain(call.args[0].extra.iter()).all(|&byte| 0 == byte)));
+        // 16 calls in far less runs than trying all subsets
+        assert!(minimizer.runs() < 60, "{} runs", minimizer.runs());
+        // note of thread 0 stays with c12
+        assert_eq!(1, min.notes.len());
+        let _ = fs::remove_dir_all(&cfg.workdir);
+    }
+
+    #[test]
+    fn ddmin_over_units() {
+        let cfg = config("units", &[1, 5, 6]);
+        let mut minimizer = Minimizer::new(&cfg);
+        fs::create_dir_all(&cfg.workdir).unwrap();
+        let full = trace(8, 1);
+        let kept = minimizer
+            .ddmin((0..8).collect(), |seqs: &[u64]| subset(&full, |_, seq| seqs.contains(&seq)))
+            .unwrap();
+        assert_eq!(vec![1, 5, 6], kept);
+        // single unit is not tried at all
+        let runs = minimizer.runs();
+        assert_eq!(vec![4], minimizer.ddmin(vec![4], |_: &[u64]| subset(&full, |_, _| true)).unwrap());
+        assert_eq!(runs, minimizer.runs());
+        let _ = fs::remove_dir_all(&cfg.workdir);
+    }
+
+    #[test]
+    fn not_reproducing_is_error() {
+        let cfg = config("none", &[99]);
+        let err = Minimizer::new(&cfg).minimize(&trace(4, 1)).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        let _ = fs::remove_dir_all(&cfg.workdir);
+    }
+
+    #[test]
+    fn stale_calls_are_noted() {
+        let orig = trace(4, 2);
+        let mut min = subset(&orig, |_, seq| seq != 1);
+        min.calls[1].args[0].data = vec![0; 4];
+        assert_eq!(vec![2], stale(&orig, &min));
+        assert!(stale(&orig, &orig).is_empty());
+        // note of thread 0 stays while thread has some call
+        assert_eq!(1, min.notes.len());
+        assert!(subset(&orig, |tid, _| 0 != tid).notes.is_empty());
+    }
+}
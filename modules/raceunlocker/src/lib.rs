@@ -8,7 +8,10 @@ use std::{thread, time};
 
 extern crate core;
 
+use core::banana::bananaq;
 use core::banana::observer::{ICallObserver, IStateObserver};
+use core::context;
+use core::rng;
 use core::state::state::StateInfo;
 
 extern crate common;
@@ -31,14 +34,15 @@ impl IStateObserver for RaceUnlock {
         }
         for _ in 0..self.cfg.racer_count {
             let info = state.clone();
-            let sleep = self.cfg.sleep;
+            // drawn here, by thread of state, so it is part of its rng stream
+            let sleep = rng::thread_rng().gen_range(0..=self.cfg.sleep);
             let push_state = self.callbacks.push_state();
-            thread::spawn(move || {
-                thread::sleep(time::Duration::from_millis(
-                    rand::thread_rng().gen_range(0..=sleep),
-                ));
+            let ctx = context::current();
+            bananaq::adopt(thread::spawn(move || {
+                context::enter(ctx);
+                thread::sleep(time::Duration::from_millis(sleep));
                 push_state(info.id, &info.fd);
-            });
+            }));
         }
         true
     }
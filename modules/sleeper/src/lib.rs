@@ -12,6 +12,7 @@ extern crate core;
 use core::exec::call::Call;
 use core::exec::id::CallTableId;
 use core::banana::observer::{ICallObserver, IStateObserver};
+use core::rng;
 use core::state::state::StateInfo;
 
 use std::collections::BTreeMap;
@@ -40,7 +41,7 @@ impl ICallObserver for Sleeper {
         //   setting
         if let Some(time) = self.target_list.get(&call.id()) {
           thread::sleep(time::Duration::from_millis(
-              rand::thread_rng().gen_range(0..=time.clone()),
+              rng::thread_rng().gen_range(0..=time.clone()),
           ));
         }
         true
@@ -0,0 +1,104 @@
+extern crate libstats;
+
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+use libstats::ipc::{fetch, Status};
+
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// [#####-----] of given width
+fn bar(ratio: f64, width: usize) -> String {
+    let full = ((ratio.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    String::from("[") + &"#".repeat(full) + &"-".repeat(width - full) + "]"
+}
+
+fn color(ratio: f64) -> &'static str {
+    if ratio >= 0.5 {
+        GREEN
+    } else if ratio > 0.05 {
+        YELLOW
+    } else {
+        RED
+    }
+}
+
+fn hms(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn render(addr: &str, status: &Status, rows: usize) -> String {
+    let stats = &status.stats;
+    let elapsed = stats.uptime_ms / 1000;
+    let mut out = format!("{}banana fuzzer{} @ {}\n\n", BOLD, RESET, addr);
+
+    out += &format!("elapsed  {} / {} {}\n",
+        hms(elapsed), hms(status.active_seconds),
+        bar(if 0 == status.active_seconds { 0.0 } else { elapsed as f64 / status.active_seconds as f64 }, 30));
+    out += &format!("exec/s   {:.1}   calls {}   ok {}{:.1}%{}   crashes {}{}{}   hangs {}\n",
+        stats.exec_per_sec, stats.total,
+        color(stats.ratio), 100.0 * stats.ratio, RESET,
+        if 0 != status.crashes { RED } else { "" }, status.crashes, RESET,
+        status.hangs);
+    out += &format!("queue    {} {}/{}   racers {}   reclaimed {}\n\n",
+        bar(if 0 == status.queue.max { 0.0 } else { status.queue.states as f64 / status.queue.max as f64 }, 30),
+        status.queue.states, status.queue.max, status.queue.racers, status.queue.reclaimed);
+
+    out += &format!("{}{:<24} {:>8} {:>8} {:>10} {:>7}{}\n", BOLD, "state", "alive", "created", "calls", "ok%", RESET);
+    for state in stats.states.iter() {
+        out += &format!("{:<24} {:>8} {:>8} {:>10} {}{:>6.1}%{}\n",
+            state.name, state.alive, state.created, state.calls,
+            color(state.ratio), 100.0 * state.ratio, RESET);
+    }
+
+    let mut calls = stats.calls.iter().collect::<Vec<_>>();
+    calls.sort_by_key(|call| std::cmp::Reverse(call.total));
+    out += &format!("\n{}{:<24} {:>10} {:>7} {:22} {:>9} {:>9} {}{}\n",
+        BOLD, "call", "total", "ok%", "", "exec/s", "avg us", "dead", RESET);
+    for call in calls.iter().take(rows) {
+        out += &format!("{:<24} {:>10} {}{:>6.1}% {}{} {:>9.1} {:>9} {}\n",
+            call.name, call.total,
+            color(call.ratio), 100.0 * call.ratio, bar(call.ratio, 20), RESET,
+            call.exec_per_sec, call.avg_us,
+            if call.dead { String::from(RED) + "DEAD" + RESET } else { String::new() });
+    }
+    if calls.len() > rows {
+        out += &format!("... {} more\n", calls.len() - rows);
+    }
+    out
+}
+
+/// dashboard [addr] [--once] [--rows N]
+///
+/// - attach to fuzzer serving stats ( modules.toml [core.stats] listen ), detach by ctrl+c
+fn main() {
+    let mut addr = String::from("127.0.0.1:7878");
+    let mut once = false;
+    let mut rows = 30;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--once" => once = true,
+            "--rows" => rows = args.next().and_then(|rows| rows.parse().ok()).unwrap_or(rows),
+            _ => addr = arg,
+        }
+    }
+
+    loop {
+        let screen = match fetch(&addr) {
+            Ok(status) => render(&addr, &status, rows),
+            Err(e) => format!("waiting for fuzzer at {} : {}\n", addr, e),
+        };
+        if once {
+            return print!("{}", screen)
+        }
+        print!("{}{}", CLEAR, screen);
+        thread::sleep(Duration::from_secs(1));
+    }
+}
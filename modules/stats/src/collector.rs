@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use core::context;
+use core::exec::id::CallTableId;
+use core::state::id::StateTableId;
+
+/// latency buckets, bucket i holds calls which took [2^(i-1), 2^i) us, bucket 0 is < 1us
+pub const LATENCY_BUCKETS: usize = 32;
+
+lazy_static! {
+    /// one for all fuzzing threads, Call keeps its numbers only per thread ( and they die with it )
+    static ref STATS: Mutex<Collector> = Mutex::new(Collector::new());
+}
+
+struct CallStats {
+    name: String,
+    total: u64,
+    success: u64,
+    /// how many times call was executed while Call::dead in its thread
+    dead_hits: u64,
+    elapsed: Duration,
+    latency: [u64; LATENCY_BUCKETS],
+}
+
+#[derive(Default)]
+struct StateStats {
+    name: String,
+    created: u64,
+    closed: u64,
+    calls: u64,
+    success: u64,
+}
+
+struct Collector {
+    start: Instant,
+    calls: BTreeMap<u64, CallStats>,
+    states: BTreeMap<u64, StateStats>,
+}
+
+/// numbers per call kind ( CallTableId ), across all threads
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CallSnapshot {
+    pub id: u64,
+    pub name: String,
+    pub total: u64,
+    pub success: u64,
+    pub ratio: f64,
+    /// same rule as Call::dead, applied to global numbers
+    pub dead: bool,
+    pub dead_hits: u64,
+    pub exec_per_sec: f64,
+    /// average time in ccall, us
+    pub avg_us: u64,
+    /// see LATENCY_BUCKETS
+    pub latency: Vec<u64>,
+}
+
+/// numbers per state kind ( StateTableId ), across all threads
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StateSnapshot {
+    pub id: u64,
+    pub name: String,
+    pub created: u64,
+    pub closed: u64,
+    /// in queue right now ( created - closed )
+    pub alive: u64,
+    pub calls: u64,
+    pub success: u64,
+    pub ratio: f64,
+}
+
+/// copy of statistics at one point of time, cheap to pass around ( dump, ipc, .. )
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub uptime_ms: u64,
+    pub total: u64,
+    pub success: u64,
+    pub ratio: f64,
+    pub exec_per_sec: f64,
+    pub calls: Vec<CallSnapshot>,
+    pub states: Vec<StateSnapshot>,
+}
+
+fn ratio(success: u64, total: u64) -> f64 {
+    if 0 == total {
+        return 0.0
+    }
+    success as f64 / total as f64
+}
+
+fn bucket(elapsed: Duration) -> usize {
+    let us = elapsed.as_micros() as u64;
+    ((64 - us.leading_zeros()) as usize).min(LATENCY_BUCKETS - 1)
+}
+
+impl Collector {
+    fn new() -> Collector {
+        Collector {
+            start : Instant::now(),
+            calls : BTreeMap::new(),
+            states : BTreeMap::new(),
+        }
+    }
+
+    fn state(&mut self, sid: &StateTableId, name: &str) -> &mut StateStats {
+        let StateTableId::Id(id) = *sid;
+        let state = self.states.entry(id).or_default();
+        if state.name.is_empty() {
+            state.name = name.to_string();
+        }
+        state
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let uptime = self.start.elapsed();
+        let secs = uptime.as_secs_f64().max(0.001);
+        let dead_call = context::config().dead_call;
+        let calls = self.calls
+            .iter()
+            .map(|(&id, call)| CallSnapshot {
+                id,
+                name : call.name.clone(),
+                total : call.total,
+                success : call.success,
+                ratio : ratio(call.success, call.total),
+                dead : call.total > dead_call as u64 * (1 + call.success),
+                dead_hits : call.dead_hits,
+                exec_per_sec : call.total as f64 / secs,
+                avg_us : if 0 == call.total {
+                    0
+                } else {
+                    (call.elapsed.as_micros() / call.total as u128) as u64
+                },
+                latency : call.latency.to_vec(),
+            })
+            .collect::< Vec<CallSnapshot> >();
+        let states = self.states
+            .iter()
+            .map(|(&id, state)| StateSnapshot {
+                id,
+                name : state.name.clone(),
+                created : state.created,
+                closed : state.closed,
+                alive : state.created.saturating_sub(state.closed),
+                calls : state.calls,
+                success : state.success,
+                ratio : ratio(state.success, state.calls),
+            })
+            .collect();
+        let total = calls.iter().map(|call| call.total).sum();
+        let success = calls.iter().map(|call| call.success).sum();
+        Snapshot {
+            uptime_ms : uptime.as_millis() as u64,
+            total,
+            success,
+            ratio : ratio(success, total),
+            exec_per_sec : total as f64 / secs,
+            calls,
+            states,
+        }
+    }
+}
+
+/// account executed call
+pub fn call(sid: &StateTableId, state: &str, cid: &CallTableId, name: &str, ok: bool, dead: bool, elapsed: Duration) {
+    let mut stats = match STATS.lock() {
+        Ok(stats) => stats,
+        Err(_) => return,
+    };
+    let CallTableId::Id(id) = *cid;
+    let call = stats.calls
+        .entry(id)
+        .or_insert_with(|| CallStats {
+            name : name.to_string(),
+            total : 0,
+            success : 0,
+            dead_hits : 0,
+            elapsed : Duration::from_secs(0),
+            latency : [0; LATENCY_BUCKETS],
+        });
+    call.total += 1;
+    call.success += ok as u64;
+    call.dead_hits += dead as u64;
+    call.elapsed += elapsed;
+    call.latency[bucket(elapsed)] += 1;
+
+    let state = stats.state(sid, state);
+    state.calls += 1;
+    state.success += ok as u64;
+}
+
+/// account state entering queue
+pub fn created(sid: &StateTableId, name: &str) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.state(sid, name).created += 1;
+    }
+}
+/// account state leaving queue
+pub fn closed(sid: &StateTableId, name: &str) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.state(sid, name).closed += 1;
+    }
+}
+
+/// statistics as of now
+pub fn snapshot() -> Snapshot {
+    match STATS.lock() {
+        Ok(stats) => stats.snapshot(),
+        Err(e) => e.into_inner().snapshot(),
+    }
+}
@@ -0,0 +1,106 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use serde_json;
+
+use core::banana::bananaq;
+use core::context;
+use core::exec::{forkserver, watchdog};
+
+use collector::{snapshot, Snapshot};
+
+/// everything dashboard shows, one json line per request
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Status {
+    pub stats: Snapshot,
+    pub queue: bananaq::Occupancy,
+    /// workers crashed so far, in forkserver mode
+    pub crashes: u64,
+    /// threads stuck in call right now, see exec::watchdog
+    pub hangs: usize,
+    /// config.active_seconds, planned length of session
+    pub active_seconds: u64,
+}
+
+static SERVER: Once = Once::new();
+
+pub fn status() -> Status {
+    Status {
+        stats : snapshot(),
+        queue : bananaq::occupancy(),
+        crashes : forkserver::crashes(),
+        hangs : watchdog::hanged(),
+        active_seconds : context::config().active_seconds,
+    }
+}
+
+/// clients of listener till fuzzing stops, so server thread can be joined at shutdown
+///
+/// - listener which can not poll would block in accept past stop, so it serves no one
+/// - accept errors ( fd limit, aborted client, .. ) are waited out, not spun on
+pub fn incoming(listener: &TcpListener) -> impl Iterator<Item = TcpStream> + '_ {
+    let polling = match listener.set_nonblocking(true) {
+        Ok(_) => true,
+        Err(e) => {
+            println!("[stats] unable to poll listener, not serving : {}", e);
+            false
+        },
+    };
+    std::iter::from_fn(move || {
+        while polling && !bananaq::stopping() {
+            match listener.accept() {
+                Ok((client, _)) => {
+                    // accepted one may inherit non blocking mode of listener
+                    if client.set_nonblocking(false).is_err() {
+                        continue
+                    }
+                    return Some(client)
+                },
+                Err(_) => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+        None
+    })
+}
+
+/// serve status on local tcp, client connects, reads one line and is done
+///
+/// - no state per client, so dashboard can attach and detach anytime
+/// - localhost only ( or ssh tunnel to VM ), there is no auth whatsoever
+/// - server ends once fuzzing stops, it is joined at shutdown
+pub fn serve(listen: &str) {
+    let addr: SocketAddr = match listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => return println!("[stats] bad listen address {} : {}", listen, e),
+    };
+    if !addr.ip().is_loopback() {
+        return println!("[stats] refusing to listen on {}, only localhost is allowed ( use ssh tunnel )", addr);
+    }
+    let ctx = context::current();
+    SERVER.call_once(|| { bananaq::adopt(thread::spawn(move || {
+        context::enter(ctx);
+        let listener = match TcpListener::bind(addr) {
+            Ok(listener) => listener,
+            Err(e) => return println!("[stats] unable to listen on {} : {}", addr, e),
+        };
+        for mut client in incoming(&listener) {
+            let _ = client.set_write_timeout(Some(Duration::from_secs(1)));
+            if let Ok(line) = serde_json::to_string(&status()) {
+                let _ = client.write_all((line + "\n").as_bytes());
+            }
+        }
+    })); });
+}
+
+/// ask running fuzzer for its status
+pub fn fetch(addr: &str) -> Result<Status, io::Error> {
+    let client = TcpStream::connect(addr)?;
+    client.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut line = String::new();
+    BufReader::new(client).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
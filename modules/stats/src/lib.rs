@@ -0,0 +1,127 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate core;
+extern crate generic;
+
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use core::banana::bananaq;
+use core::context;
+use core::exec::call::Call;
+use core::exec::fd_info::CallInfo;
+use core::banana::observer::{ICallObserver, IStateObserver};
+use core::state::state::StateInfo;
+
+pub mod collector;
+pub mod ipc;
+pub use collector::{
+    snapshot,
+    Snapshot,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatsConfig {
+    /// where to periodically dump snapshot ( json ), empty = no dump, only snapshot api
+    #[serde(default)]
+    dump: String,
+    /// seconds between dumps
+    #[serde(default)]
+    dump_secs: u64,
+    /// address to serve status for dashboard on, loopback only, f.e. 127.0.0.1:7878; empty = no server
+    #[serde(default)]
+    listen: String,
+}
+
+/// aggregates outcome of every call across all threads, fed by post-callback
+///
+/// - order in online list does not matter, denied calls never reach notify_post
+struct CallStats {
+    /// StatsConfig.dump, last snapshot is dumped at shutdown
+    dump: String,
+}
+
+impl ICallObserver for CallStats {
+    fn notify(&self, _: &StateInfo, _: &Call) -> bool {
+        true
+    }
+    fn notify_post(&self, state: &StateInfo, call: &Call, result: &CallInfo, elapsed: Duration) {
+        collector::call(&state.id, &state.name, &call.id(), call.name(), result.success(), call.dead(), elapsed);
+    }
+    fn on_shutdown(&self) {
+        if !self.dump.is_empty() {
+            dump(&self.dump)
+        }
+    }
+}
+
+/// states entering and leaving queue
+struct StateStats { }
+
+impl IStateObserver for StateStats {
+    fn notify_ctor(&self, info: &StateInfo) -> bool {
+        collector::created(&info.id, &info.name);
+        true
+    }
+    fn notify_dtor(&self, info: &StateInfo) {
+        collector::closed(&info.id, &info.name);
+    }
+}
+
+static DUMPER: Once = Once::new();
+
+/// write snapshot to file, pretty json as it is meant for humans as well
+pub fn dump(fname: &str) {
+    match serde_json::to_string_pretty(&snapshot()) {
+        Ok(data) => if let Err(e) = generic::write_file_raw(fname, data.as_bytes()) {
+            println!("[stats] unable to dump to {} : {}", fname, e);
+        },
+        Err(e) => println!("[stats] snapshot is not serializable : {}", e),
+    }
+}
+
+fn dumper(cfg: &StatsConfig) {
+    if cfg.dump.is_empty() {
+        return
+    }
+    let fname = cfg.dump.clone();
+    let period = Duration::from_secs(cfg.dump_secs.max(1));
+    let ctx = context::current();
+    DUMPER.call_once(|| { bananaq::adopt(thread::spawn(move || {
+        context::enter(ctx);
+        // sleeps in slices, so it ends with fuzzing and can be joined at shutdown
+        let mut last = Instant::now();
+        while !bananaq::stopping() {
+            thread::sleep(Duration::from_millis(100).min(period));
+            if last.elapsed() >= period {
+                dump(&fname);
+                last = Instant::now();
+            }
+        }
+    })); });
+}
+
+pub fn observers(
+    cfg: &Option<StatsConfig>,
+) -> (
+    Option<Box<dyn IStateObserver>>,
+    Option<Box<dyn ICallObserver>>,
+) {
+    match *cfg {
+        Some(ref cfg) => {
+            dumper(cfg);
+            if !cfg.listen.is_empty() {
+                ipc::serve(&cfg.listen);
+            }
+            (Some(Box::new(StateStats { })), Some(Box::new(CallStats { dump : cfg.dump.clone() })))
+        },
+        _ => (None, None),
+    }
+}
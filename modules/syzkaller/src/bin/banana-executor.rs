@@ -0,0 +1,101 @@
+extern crate libc;
+extern crate libsyzkaller;
+extern crate toml;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::path::PathBuf;
+use std::process;
+
+use libsyzkaller::executor::{Executor, ExecutorConfig};
+use libsyzkaller::ipc::{self, ExecuteReq, HandshakeReq};
+
+fn fail(what: String) -> ! {
+    eprintln!("[banana-executor] {}", what);
+    process::exit(1)
+}
+
+/// executor.toml : $BANANA_EXECUTOR, or next to binary
+///
+/// - we move to folder of config, as fuzzer there expects its config.toml + call table
+fn config() -> ExecutorConfig {
+    let fname = match env::var("BANANA_EXECUTOR") {
+        Ok(fname) => PathBuf::from(fname),
+        Err(_) => env::current_exe()
+            .unwrap_or_else(|e| fail(e.to_string()))
+            .with_file_name("executor.toml"),
+    };
+    let data = std::fs::read_to_string(&fname).unwrap_or_else(|e| fail(format!("{} : {}", fname.display(), e)));
+    if let Some(dir) = fname.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        env::set_current_dir(dir).unwrap_or_else(|e| fail(format!("{} : {}", dir.display(), e)));
+    }
+    toml::from_str(&data).unwrap_or_else(|e| fail(format!("{} : {}", fname.display(), e)))
+}
+
+/// control pipe to our own fds, stdout goes to stderr so nothing printed by fuzzer breaks protocol
+fn pipes() -> (BufReader<File>, BufWriter<File>) {
+    unsafe {
+        let (input, output) = (libc::dup(0), libc::dup(1));
+        if input < 0 || output < 0 || libc::dup2(2, 1) < 0 {
+            fail(format!("control pipe : {}", io::Error::last_os_error()))
+        }
+        (BufReader::new(File::from_raw_fd(input)), BufWriter::new(File::from_raw_fd(output)))
+    }
+}
+
+fn serve(cfg: &ExecutorConfig) -> Result<(), io::Error> {
+    let (mut ctl_in, mut ctl_out) = pipes();
+    let (input, output) = unsafe { (File::from_raw_fd(ipc::IN_FD), File::from_raw_fd(ipc::OUT_FD)) };
+
+    let mut exec = Executor::new(cfg)?;
+    let handshake = HandshakeReq::read(&mut ctl_in)?;
+    if ipc::IN_MAGIC != handshake.magic {
+        fail(format!("bad handshake magic 0x{:x}", handshake.magic))
+    }
+    ipc::write_reply(&mut ctl_out, None)?;
+
+    let mut prog = vec![0u8; ipc::MAX_INPUT];
+    loop {
+        let req = match ExecuteReq::read(&mut ctl_in) {
+            Ok(req) => req,
+            Err(ref e) if io::ErrorKind::UnexpectedEof == e.kind() => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if ipc::IN_MAGIC != req.magic || 0 != req.prog_size {
+            fail(format!("bad execute request {:?}", req))
+        }
+        let size = input.read_at(&mut prog, 0)?;
+        let instrs = ipc::decode(&prog[..size])?;
+        let outputs = exec.execute(&instrs, handshake.pid, 0 != req.exec_flags & ipc::EXEC_COLLECT_COVER);
+        for warning in exec.warnings.drain(..) {
+            eprintln!("[banana-executor] {}", warning);
+        }
+        let data = ipc::write_output(&outputs);
+        if data.len() > ipc::MAX_OUTPUT {
+            fail(format!("output of {} bytes overflows shm", data.len()))
+        }
+        output.write_all_at(&data, 0)?;
+        ipc::write_reply(&mut ctl_out, Some(0))?;
+    }
+}
+
+/// syz-executor replacement, syz-fuzzer executes its programs trough our call table
+///
+/// - exec : serve programs over shm + control pipe, see libsyzkaller::ipc
+/// - version : as configured, syz-fuzzer compares it with its own build
+/// - setup* / leak : features we do not provide, nothing to do
+fn main() {
+    let cmd = env::args().nth(1).unwrap_or_default();
+    let cfg = config();
+    match cmd.as_str() {
+        "version" => println!("{}", cfg.version),
+        "exec" => if let Err(e) = serve(&cfg) {
+            fail(e.to_string())
+        },
+        "leak" | "setup" | "setup_kcsan_filterlist" => (),
+        _ => fail(format!("unknown command {:?}, usage : banana-executor exec|version", cmd)),
+    }
+}
@@ -0,0 +1,79 @@
+extern crate core;
+extern crate desc;
+extern crate libsyzkaller;
+extern crate serde;
+extern crate toml;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process;
+
+use core::poc::trace::Trace;
+
+use desc::format::Description;
+
+use libsyzkaller::export::{self, Exporter};
+
+const USAGE: &str = "usage : banana2syz [--table table.toml] [--names names.toml] [--tail 40] [--out prog.txt] trace.jsonl";
+
+fn fail(what: String) -> ! {
+    eprintln!("[banana2syz] {}", what);
+    process::exit(1)
+}
+
+fn toml_file<T: for<'de> serde::Deserialize<'de>>(fname: &str) -> T {
+    let data = fs::read_to_string(fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)));
+    toml::from_str(&data).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)))
+}
+
+/// recorded trace ( poclog ) -> syz program, for syz-execprog / syz-prog2c or to be added to syzkaller corpus
+///
+/// - names.toml : "syzkaller$name" = call id, for call tables not coming from desc
+///     - with table, its call names are used, names.toml overrides them
+/// - tail : only last calls of every thread, as crash context is usually what we want to forward
+fn main() {
+    let mut table = None;
+    let mut names = None;
+    let mut tail = None;
+    let mut out = None;
+    let mut trace = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--table" => table = args.next(),
+            "--names" => names = args.next(),
+            "--tail" => tail = args.next().and_then(|tail| tail.parse::<usize>().ok()),
+            "--out" => out = args.next(),
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ => trace = Some(arg),
+        }
+    }
+    let fname = trace.unwrap_or_else(|| fail(String::from(USAGE)));
+    if table.is_none() && names.is_none() {
+        fail(String::from("no call names : --table or --names needed"))
+    }
+
+    let table: Option<Description> = table.map(|table| toml_file(&table));
+    let mut ids = table.as_ref().map(export::names).unwrap_or_default();
+    if let Some(names) = names {
+        let names: HashMap<String, u64> = toml_file(&names);
+        ids.extend(names.into_iter().map(|(name, id)| (id, name)));
+    }
+
+    let mut trace = Trace::load(&fname).unwrap_or_else(|e| fail(e.to_string()));
+    if let Some(tail) = tail {
+        trace = trace.tail(tail);
+    }
+    let mut exporter = Exporter::new(ids, table.as_ref());
+    let prog = exporter.export(&trace);
+    for warning in exporter.warnings.iter() {
+        eprintln!("[banana2syz] {}", warning);
+    }
+    let text = format!("# exported by banana2syz from {}\n{}", fname, prog.serialize());
+    match out {
+        Some(out) => fs::write(&out, text).unwrap_or_else(|e| fail(format!("{} : {}", out, e))),
+        None => print!("{}", text),
+    }
+    eprintln!("[banana2syz] {} of {} calls", prog.calls.len(), trace.calls.len());
+}
@@ -0,0 +1,217 @@
+extern crate desc;
+extern crate libc;
+extern crate libsyzkaller;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Command, Stdio};
+
+use desc::format::Description;
+
+use libsyzkaller::corpus;
+use libsyzkaller::executor::ExecutorConfig;
+use libsyzkaller::ipc::{self, CallOutput, Encoder, ExecuteReq, HandshakeReq};
+
+const USAGE: &str = "usage : syz-standin [--executor ./banana-executor] [--update] recording.jsonl
+        syz-standin --encode prog.txt [--config executor.toml] >> recording.jsonl";
+
+/// one recorded round of control pipe, program as executor got it + what it answered
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Exchange {
+    #[serde(default)]
+    env_flags: u64,
+    #[serde(default)]
+    exec_flags: u64,
+    /// syz program it was encoded from, for humans
+    #[serde(default)]
+    syz: String,
+    /// exec encoding
+    prog: Vec<u8>,
+    /// expected results, signal and cover are not compared as they differ from run to run
+    #[serde(default)]
+    calls: Vec<CallOutput>,
+}
+
+fn fail(what: String) -> ! {
+    eprintln!("[syz-standin] {}", what);
+    process::exit(1)
+}
+
+fn shm(name: &str, size: usize) -> File {
+    let fname = env::temp_dir().join(format!("syz-standin-{}-{}", name, process::id()));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&fname)
+        .unwrap_or_else(|e| fail(format!("{} : {}", fname.display(), e)));
+    let _ = fs::remove_file(&fname);
+    file.set_len(size as u64).unwrap_or_else(|e| fail(e.to_string()));
+    file
+}
+
+/// syz program(s) -> exchanges without expected results, as syz-fuzzer would encode them for us
+fn encode(fname: &str, config: &str) {
+    let cfg: ExecutorConfig = toml::from_str(&fs::read_to_string(config).unwrap_or_else(|e| fail(format!("{} : {}", config, e))))
+        .unwrap_or_else(|e| fail(format!("{} : {}", config, e)));
+    let table: Description = toml::from_str(&fs::read_to_string(&cfg.table).unwrap_or_else(|e| fail(format!("{} : {}", cfg.table, e))))
+        .unwrap_or_else(|e| fail(format!("{} : {}", cfg.table, e)));
+    let names = fs::read_to_string(&cfg.calls)
+        .unwrap_or_else(|e| fail(format!("{} : {}", cfg.calls, e)))
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect::< Vec<String> >();
+
+    let (progs, warnings) = corpus::load(fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)));
+    for warning in warnings.iter() {
+        eprintln!("[syz-standin] {}", warning);
+    }
+    let mut encoder = Encoder::new(&table, &names, cfg.data_offset);
+    for prog in progs.iter() {
+        let instrs = encoder.encode(prog).unwrap_or_else(|e| fail(e.to_string()));
+        let exchange = Exchange {
+            env_flags : ipc::ENV_SIGNAL,
+            exec_flags : 0,
+            syz : prog.serialize(),
+            prog : ipc::encode(&instrs),
+            calls : Vec::new(),
+        };
+        println!("{}", serde_json::to_string(&exchange).unwrap_or_else(|e| fail(e.to_string())));
+    }
+}
+
+/// replay recorded exchanges against executor, as syz-fuzzer would drive it
+///
+/// - returns results of executor, per exchange
+fn drive(executor: &str, exchanges: &[Exchange]) -> Result<Vec< Vec<CallOutput> >, io::Error> {
+    let input = shm("in", ipc::MAX_INPUT);
+    let output = shm("out", ipc::MAX_OUTPUT);
+    let (infd, outfd) = (input.as_raw_fd(), output.as_raw_fd());
+    let mut child = unsafe {
+        Command::new(executor)
+            .arg("exec")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .pre_exec(move || {
+                // out of the way first, shm files may already sit at 3 / 4 ( with cloexec )
+                let (infd, outfd) = (libc::fcntl(infd, libc::F_DUPFD, 10), libc::fcntl(outfd, libc::F_DUPFD, 10));
+                if infd < 0 || outfd < 0 || libc::dup2(infd, ipc::IN_FD) < 0 || libc::dup2(outfd, ipc::OUT_FD) < 0 {
+                    return Err(io::Error::last_os_error())
+                }
+                Ok(())
+            })
+            .spawn()?
+    };
+    let (mut ctl_out, mut ctl_in) = match (child.stdin.take(), child.stdout.take()) {
+        (Some(stdin), Some(stdout)) => (stdin, BufReader::new(stdout)),
+        _ => return Err(io::Error::new(io::ErrorKind::BrokenPipe, "control pipe of executor")),
+    };
+
+    let env_flags = exchanges.first().map_or(0, |exchange| exchange.env_flags);
+    HandshakeReq { magic : ipc::IN_MAGIC, env_flags, pid : 0 }.write(&mut ctl_out)?;
+    ipc::read_reply(&mut ctl_in, false)?;
+
+    let mut results = Vec::new();
+    let mut data = vec![0u8; ipc::MAX_OUTPUT];
+    for exchange in exchanges.iter() {
+        input.write_all_at(&exchange.prog, 0)?;
+        ExecuteReq {
+            magic : ipc::IN_MAGIC,
+            env_flags : exchange.env_flags,
+            exec_flags : exchange.exec_flags,
+            ..ExecuteReq::default()
+        }.write(&mut ctl_out)?;
+        if let Some(status) = ipc::read_reply(&mut ctl_in, true)? {
+            if 0 != status {
+                eprintln!("[syz-standin] executor status {}", status);
+            }
+        }
+        let size = output.read_at(&mut data, 0)?;
+        results.push(ipc::read_output(&data[..size])?);
+    }
+    drop(ctl_out);
+    child.wait()?;
+    Ok(results)
+}
+
+fn same(expected: &[CallOutput], got: &[CallOutput]) -> bool {
+    expected.len() == got.len() && expected
+        .iter()
+        .zip(got.iter())
+        .all(|(a, b)| (a.index, a.num, a.errno, a.flags) == (b.index, b.num, b.errno, b.flags))
+}
+
+/// stand-in of syz-fuzzer side of executor protocol, to test banana-executor without syzkaller
+///
+/// - recording : json lines of exchanges, made by --encode out of syz programs
+/// - every exchange is sent to executor, its results compared to recorded ones
+/// - --update : record what executor answered instead, for new recordings or intended changes
+fn main() {
+    let mut executor = String::from("./banana-executor");
+    let mut config = String::from("executor.toml");
+    let mut update = false;
+    let mut encoded = None;
+    let mut recording = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--executor" => executor = args.next().unwrap_or_else(|| fail(String::from(USAGE))),
+            "--config" => config = args.next().unwrap_or_else(|| fail(String::from(USAGE))),
+            "--encode" => encoded = args.next(),
+            "--update" => update = true,
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ => recording = Some(arg),
+        }
+    }
+    if let Some(fname) = encoded {
+        return encode(&fname, &config)
+    }
+
+    let fname = recording.unwrap_or_else(|| fail(String::from(USAGE)));
+    let mut exchanges = BufReader::new(File::open(&fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e))))
+        .lines()
+        .map(|line| line.unwrap_or_else(|e| fail(e.to_string())))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<Exchange>(&line).unwrap_or_else(|e| fail(format!("{} : {}", fname, e))))
+        .collect::< Vec<Exchange> >();
+    let results = drive(&executor, &exchanges).unwrap_or_else(|e| fail(format!("{} : {}", executor, e)));
+
+    let mut diffs = 0;
+    for (ind, (exchange, got)) in exchanges.iter_mut().zip(results).enumerate() {
+        let signal = got.iter().map(|call| call.signal.len()).sum::<usize>();
+        let errnos = got.iter().map(|call| call.errno.to_string()).collect::< Vec<String> >().join(", ");
+        if update {
+            println!("[syz-standin] #{} : {} calls, errno [{}], {} signal", ind, got.len(), errnos, signal);
+            exchange.calls = got
+                .into_iter()
+                .map(|call| CallOutput { signal : Vec::new(), cover : Vec::new(), ..call })
+                .collect();
+        } else if same(&exchange.calls, &got) {
+            println!("[syz-standin] #{} : ok, errno [{}], {} signal", ind, errnos, signal);
+        } else {
+            diffs += 1;
+            println!("[syz-standin] #{} : differs\n\texpected {:?}\n\tgot {:?}", ind, exchange.calls, got);
+        }
+    }
+    if update {
+        let data = exchanges
+            .iter()
+            .map(|exchange| serde_json::to_string(exchange).unwrap_or_else(|e| fail(e.to_string())) + "\n")
+            .collect::<String>();
+        let mut file = File::create(&fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)));
+        file.write_all(data.as_bytes()).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)));
+    }
+    if 0 != diffs {
+        fail(format!("{} of {} exchanges differ", diffs, exchanges.len()))
+    }
+}
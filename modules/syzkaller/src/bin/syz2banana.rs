@@ -0,0 +1,64 @@
+extern crate libsyzkaller;
+extern crate toml;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use libsyzkaller::import::Import;
+use libsyzkaller::syzlang::Syzlang;
+
+const USAGE: &str = "usage : syz2banana [--arch amd64] [--module libc.so.6] [--out table.toml] descriptions.txt.. [consts.txt.const..]";
+
+fn fail(what: String) -> ! {
+    eprintln!("[syz2banana] {}", what);
+    process::exit(1)
+}
+
+/// syzkaller descriptions -> call table for desc crate
+///
+/// - .const files are recognized by extension, for syscall numbers + flags values
+/// - warnings about what was not mapped 1:1 goes to stderr
+fn main() {
+    let mut arch = String::from("amd64");
+    let mut module = String::from("libc.so.6");
+    let mut out = None;
+    let mut files = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--arch" => arch = args.next().unwrap_or_else(|| fail(String::from(USAGE))),
+            "--module" => module = args.next().unwrap_or_else(|| fail(String::from(USAGE))),
+            "--out" => out = args.next(),
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        fail(String::from(USAGE))
+    }
+
+    let mut syz = Syzlang::default();
+    for file in files.iter() {
+        let data = fs::read_to_string(file).unwrap_or_else(|e| fail(format!("{} : {}", file, e)));
+        if file.ends_with(".const") {
+            syz.consts(&data, &arch);
+        } else if let Err(e) = syz.parse(&data) {
+            fail(format!("{} : {}", file, e))
+        }
+    }
+
+    let (desc, warnings) = Import::new(&syz, &module)
+        .run()
+        .unwrap_or_else(|e| fail(e.to_string()));
+    for warning in warnings.iter() {
+        eprintln!("[syz2banana] {}", warning);
+    }
+    let table = toml::to_string(&desc).unwrap_or_else(|e| fail(e.to_string()));
+    let table = format!("# generated by syz2banana from {}\n\n{}", files.join(", "), table);
+    match out {
+        Some(out) => fs::write(&out, table).unwrap_or_else(|e| fail(format!("{} : {}", out, e))),
+        None => print!("{}", table),
+    }
+    eprintln!("[syz2banana] {} states, {} calls", desc.state.len(), desc.call.len());
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::DeflateDecoder;
+
+use prog::Prog;
+
+/// pkg/db of syzkaller : header + records, later record of same key wins
+const DB_MAGIC: u32 = 0xbaddb;
+const REC_MAGIC: u32 = 0xfee1bad;
+const SEQ_DELETED: u64 = !0;
+
+fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8], io::Error> {
+        if self.pos + size > self.data.len() {
+            return Err(invalid(format!("corpus.db truncated at {}", self.pos)))
+        }
+        self.pos += size;
+        Ok(&self.data[self.pos - size..self.pos])
+    }
+    fn u32(&mut self) -> Result<u32, io::Error> {
+        let mut raw = [0u8; 4];
+        raw.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(raw))
+    }
+    fn u64(&mut self) -> Result<u64, io::Error> {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(raw))
+    }
+}
+
+/// programs stored in syzkaller corpus.db, as text
+///
+/// - values are raw deflate compressed, deleted records drop key
+/// - torn last record ( manager killed while writing ) is ignored, as syzkaller does
+pub fn db(data: &[u8]) -> Result<Vec<String>, io::Error> {
+    let mut rd = Reader { data, pos : 0 };
+    if DB_MAGIC != rd.u32()? {
+        return Err(invalid(String::from("not a syzkaller corpus.db")))
+    }
+    rd.u64()?;//version, layout of records is same for all of them
+
+    let mut records: Vec<(Vec<u8>, String)> = Vec::new();
+    while rd.pos < data.len() {
+        let record = (|| -> Result<(Vec<u8>, Option<String>), io::Error> {
+            if REC_MAGIC != rd.u32()? {
+                return Err(invalid(format!("bad record magic at {}", rd.pos)))
+            }
+            let size = rd.u32()? as usize;
+            let key = rd.take(size)?.to_vec();
+            if SEQ_DELETED == rd.u64()? {
+                return Ok((key, None))
+            }
+            let size = rd.u32()? as usize;
+            let mut prog = String::new();
+            if 0 != size {
+                DeflateDecoder::new(rd.take(size)?).read_to_string(&mut prog)?;
+            }
+            Ok((key, Some(prog)))
+        })();
+        let (key, prog) = match record {
+            Ok(record) => record,
+            Err(_) => break,
+        };
+        records.retain(|(rkey, _)| rkey[..] != key[..]);
+        if let Some(prog) = prog {
+            records.push((key, prog));
+        }
+    }
+    Ok(records
+        .into_iter()
+        .map(|(_, prog)| prog)
+        .collect())
+}
+
+/// corpus.db, directory of programs ( syz-db unpack ), or text file of programs separated by empty line
+///
+/// - programs we can not parse are skipped, reported back as warnings
+pub fn load(path: &str) -> Result<(Vec<Prog>, Vec<String>), io::Error> {
+    let texts = if Path::new(path).is_dir() {
+        let mut files = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::< Vec<_> >();
+        files.sort();
+        files
+            .iter()
+            .map(fs::read_to_string)
+            .collect::< Result<Vec<String>, io::Error> >()?
+    } else {
+        let data = fs::read(path)?;
+        if data.starts_with(&DB_MAGIC.to_le_bytes()) {
+            db(&data)?
+        } else {
+            String::from_utf8_lossy(&data)
+                .split("\n\n")
+                .map(String::from)
+                .collect()
+        }
+    };
+    let mut progs = Vec::new();
+    let mut warnings = Vec::new();
+    for text in texts.iter().filter(|text| !text.trim().is_empty()) {
+        match Prog::parse(text) {
+            Ok(prog) => progs.push(prog),
+            Err(e) => warnings.push(e.to_string()),
+        }
+    }
+    Ok((progs, warnings))
+}
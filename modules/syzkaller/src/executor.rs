@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::mem;
+use std::slice;
+
+use core::exec::call::Call;
+use core::exec::coverage::kcov::{self, Kcov};
+use core::poc::trace::ArgRecord;
+
+use desc::Desc;
+use desc::format::{CallDesc, Leaf};
+
+use import::width;
+use ipc::{self, Arg, CallOutput, Chunk, Instr, Meta};
+use seed;
+
+fn data_offset() -> u64 { 0x2000_0000 }
+fn data_size() -> u64 { 0x100_0000 }
+
+/// banana-executor config, executor.toml next to its binary
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExecutorConfig {
+    /// desc call table programs are executed by
+    pub table: String,
+    /// names of syzkaller syscalls, one per line, line = id of syscall in syzkaller target
+    pub calls: String,
+    /// answer to `version`, must match what syz-fuzzer expects ( syz-executor version of its build )
+    #[serde(default)]
+    pub version: String,
+    /// data segment of syzkaller target, pointers of programs point there
+    #[serde(default = "data_offset")]
+    pub data_offset: u64,
+    #[serde(default = "data_size")]
+    pub data_size: u64,
+}
+
+fn int(data: &[u8]) -> u64 {
+    let mut raw = [0u8; 8];
+    let size = data.len().min(8);
+    raw[..size].copy_from_slice(&data[..size]);
+    u64::from_le_bytes(raw)
+}
+
+/// syz-executor hash of previous pc, edge signal = pc ^ hash(prev)
+fn hash(mut a: u32) -> u32 {
+    a = (a ^ 61) ^ (a >> 16);
+    a = a.wrapping_add(a << 3);
+    a ^= a >> 4;
+    a = a.wrapping_mul(0x27d4_eb2d);
+    a ^ (a >> 15)
+}
+
+fn signal(pcs: &[u64]) -> Vec<u32> {
+    let mut prev = 0;
+    let mut seen = HashSet::new();
+    let mut signal = Vec::new();
+    for &pc in pcs.iter() {
+        let sig = pc as u32 ^ hash(prev);
+        prev = pc as u32;
+        if seen.insert(sig) {
+            signal.push(sig);
+        }
+    }
+    signal
+}
+
+/// inet checksum, as syzkaller computes it for packets
+fn csum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|pair| u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+        .sum::<u32>();
+    while 0 != sum >> 16 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// offsets of pointers inside data of leaf, with leafs they point to, in leaf order ( as extra is laid )
+fn pointers<'l>(leaf: &'l Leaf, fd_size: usize, base: usize, out: &mut Vec<(usize, &'l Leaf)>) {
+    match *leaf {
+        Leaf::Ptr { ref to } => out.push((base, &**to)),
+        Leaf::Array { count, ref of, .. } => {
+            let size = width(of, fd_size);
+            for ind in 0..count {
+                pointers(of, fd_size, base + ind * size, out);
+            }
+        },
+        Leaf::Struct { ref fields, .. } => {
+            let mut end = 0;
+            for field in fields.iter() {
+                let offset = field.offset.unwrap_or(end);
+                end = offset + width(&field.leaf, fd_size);
+                pointers(&field.leaf, fd_size, base + offset, out);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// data segment of program, what copyin instructions write and memory args are read from
+pub struct Memory {
+    base: u64,
+    data: Vec<u8>,
+}
+
+impl Memory {
+    pub fn new(base: u64, size: u64) -> Memory {
+        Memory { base, data : vec![0; size as usize] }
+    }
+
+    fn range(&self, addr: u64, size: u64) -> Option<(usize, usize)> {
+        let start = addr.checked_sub(self.base)? as usize;
+        let end = start.checked_add(size as usize)?;
+        if end > self.data.len() {
+            return None
+        }
+        Some((start, end))
+    }
+
+    pub fn read(&self, addr: u64, size: u64) -> Option<&[u8]> {
+        self.range(addr, size).map(|(start, end)| &self.data[start..end])
+    }
+
+    pub fn write(&mut self, addr: u64, data: &[u8]) -> bool {
+        match self.range(addr, data.len() as u64) {
+            Some((start, end)) => { self.data[start..end].copy_from_slice(data); true },
+            None => false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for byte in self.data.iter_mut() {
+            *byte = 0;
+        }
+    }
+}
+
+/// syz-executor counterpart : executes programs of syz-fuzzer trough Calls of desc call table
+///
+/// - syscall is mapped to our call by name, role variants ( accept4#ctor ) prefer ctor when its
+///   result is used by program, plain variant otherwise
+/// - memory args and memory behind pointers are loaded into our leafs ( as replay does ), what
+///   call wrote is copied back to data segment afterwards, for copyout + following calls
+/// - pointers outside of data segment can not be followed, leaf generates its memory then
+/// - calls run sequentially in calling thread, threaded / collide modes and fault injection are
+///   not supported, every call is reported as finished
+/// - signal + cover from kcov when available, otherwise calls come back without them
+pub struct Executor {
+    desc: Desc,
+    names: Vec<String>,
+    /// built calls, by our name
+    calls: HashMap<String, Call>,
+    mem: Memory,
+    kcov: Option<Kcov>,
+    pub warnings: Vec<String>,
+}
+
+impl Executor {
+    pub fn new(cfg: &ExecutorConfig) -> Result<Executor, io::Error> {
+        let desc = Desc::load(&cfg.table)?;
+        let names = generic::read_file(&cfg.calls)?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect();
+        let mut warnings = Vec::new();
+        let kcov = match Kcov::open(kcov::COVER_SIZE) {
+            Ok(kcov) => Some(kcov),
+            Err(e) => { warnings.push(format!("kcov not available, no signal : {}", e)); None },
+        };
+        Ok(Executor {
+            desc,
+            names,
+            calls : HashMap::new(),
+            mem : Memory::new(cfg.data_offset, cfg.data_size),
+            kcov,
+            warnings,
+        })
+    }
+
+    fn value(arg: &Arg, results: &HashMap<u64, u64>, procid: u64) -> u64 {
+        match *arg {
+            Arg::Const { meta, value } => value.wrapping_add(meta.pid_stride.wrapping_mul(procid)),
+            Arg::Result { index, div, add, default, .. } => match results.get(&index) {
+                Some(&value) if 0 != div => (value / div).wrapping_add(add),
+                Some(&value) => value.wrapping_add(add),
+                None => default,
+            },
+            Arg::Data(_) | Arg::Csum { .. } => 0,
+        }
+    }
+
+    /// bytes of int as it goes to memory, by its format
+    fn bytes(meta: &Meta, value: u64) -> Vec<u8> {
+        let size = meta.size as usize;
+        let mut data = match meta.format {
+            ipc::FORMAT_BIGENDIAN => value.to_be_bytes()[8 - size.min(8)..].to_vec(),
+            ipc::FORMAT_STRDEC => format!("{:020}", value).into_bytes(),
+            ipc::FORMAT_STRHEX => format!("0x{:016x}", value).into_bytes(),
+            ipc::FORMAT_STROCT => format!("{:023o}", value).into_bytes(),
+            _ => value.to_le_bytes().to_vec(),
+        };
+        data.resize(size, 0);
+        data
+    }
+
+    fn copyin(&mut self, addr: u64, arg: &Arg, results: &HashMap<u64, u64>, procid: u64) -> bool {
+        match *arg {
+            Arg::Const { ref meta, .. } | Arg::Result { ref meta, .. } => {
+                let mut value = Executor::value(arg, results, procid);
+                if 0 != meta.bf_len {
+                    let old = match self.mem.read(addr, meta.size) {
+                        Some(old) => int(old),
+                        None => return false,
+                    };
+                    let mask = (!0u64 >> (64 - meta.bf_len.min(64))) << meta.bf_off;
+                    value = (old & !mask) | ((value << meta.bf_off) & mask);
+                }
+                self.mem.write(addr, &Executor::bytes(meta, value))
+            },
+            Arg::Data(ref data) => self.mem.write(addr, data),
+            Arg::Csum { size, ref chunks } => {
+                let mut data = Vec::new();
+                for chunk in chunks.iter() {
+                    match *chunk {
+                        Chunk::Data { addr, size } => match self.mem.read(addr, size) {
+                            Some(chunk) => data.extend_from_slice(chunk),
+                            None => return false,
+                        },
+                        Chunk::Const { value, size } => data.extend_from_slice(&value.to_le_bytes()[..(size as usize).min(8)]),
+                    }
+                }
+                let sum = csum(&data).to_le_bytes();
+                self.mem.write(addr, &sum[..(size as usize).min(2)])
+            },
+        }
+    }
+
+    /// pointed memory behind pointers of leaf data, laid out as extra of ArgRecord
+    ///
+    /// - stops at first pointer we can not follow, as following pointers would take its extra
+    fn gather(&self, leaf: &Leaf, data: &[u8], fd_size: usize, extra: &mut Vec<u8>) -> bool {
+        let mut ptrs = Vec::new();
+        pointers(leaf, fd_size, 0, &mut ptrs);
+        for (offset, to) in ptrs {
+            let addr = int(&data[offset..offset + mem::size_of::<usize>()]);
+            let pointee = match self.mem.read(addr, width(to, fd_size) as u64) {
+                Some(pointee) => pointee,
+                None => return false,
+            };
+            extra.extend_from_slice(pointee);
+            if !self.gather(to, pointee, fd_size, extra) {
+                return false
+            }
+        }
+        true
+    }
+
+    /// copy what call left in memory of its leafs back to data segment, pointers stay as program set them
+    ///
+    /// - addr : where data of leaf lives in data segment, None for register args
+    fn write_back(&mut self, leaf: &Leaf, live: &[u8], orig: &[u8], addr: Option<u64>, fd_size: usize) {
+        let mut ptrs = Vec::new();
+        pointers(leaf, fd_size, 0, &mut ptrs);
+        if let Some(addr) = addr {
+            let mut data = live.to_vec();
+            for &(offset, _) in ptrs.iter() {
+                let end = offset + mem::size_of::<usize>();
+                data[offset..end].copy_from_slice(&orig[offset..end]);
+            }
+            self.mem.write(addr, &data);
+        }
+        for (offset, to) in ptrs {
+            let end = offset + mem::size_of::<usize>();
+            let (target, ptr) = (int(&orig[offset..end]), int(&live[offset..end]) as usize);
+            let size = width(to, fd_size);
+            let orig = match self.mem.read(target, size as u64) {
+                Some(orig) if 0 != ptr => orig.to_vec(),
+                _ => continue,
+            };
+            // memory of Ptr leaf, owned by call and alive as long as it is
+            let live = unsafe { slice::from_raw_parts(ptr as *const u8, size) }.to_vec();
+            self.write_back(to, &live, &orig, Some(target), fd_size);
+        }
+    }
+
+    /// our variant of syscall, ctor if its result is used
+    fn cdesc(&self, name: &str, ctor: bool) -> Option<CallDesc> {
+        let variants = self.desc.description().call
+            .iter()
+            .filter(|cdesc| cdesc.name.split('#').next() == Some(name))
+            .collect::< Vec<&CallDesc> >();
+        variants
+            .iter()
+            .find(|cdesc| ctor == (cdesc.fd_ret || cdesc.fd_arg.is_some()))
+            .or_else(|| variants.first())
+            .map(|&cdesc| cdesc.clone())
+    }
+
+    /// fd_size of state lone call is built for, see Desc::lone
+    fn fd_size(&self, cdesc: &CallDesc) -> usize {
+        self.desc.description().state
+            .iter()
+            .find(|sdesc| sdesc.dtor == cdesc.name || sdesc.groups.iter().any(|group| group.contains(&cdesc.name)))
+            .map_or(0, |sdesc| sdesc.fd_size)
+    }
+
+    /// returns output of call + returned fd when it succeeded
+    fn call(&mut self, index: u32, num: u64, ctor: bool, args: &[Arg], results: &HashMap<u64, u64>, procid: u64) -> (CallOutput, Option<u64>) {
+        let mut out = CallOutput { index, num : num as u32, errno : 38, ..CallOutput::default() };//ENOSYS
+        let name = self.names.get(num as usize).cloned().unwrap_or_default();
+        let cdesc = match self.cdesc(&name, ctor) {
+            Some(cdesc) => cdesc,
+            None => {
+                self.warnings.push(format!("call {} ( {} ) not in call table", num, name));
+                return (out, None)
+            },
+        };
+        if !self.calls.contains_key(&cdesc.name) {
+            match self.desc.lone(&cdesc.name) {
+                Ok(call) => { self.calls.insert(cdesc.name.clone(), call); },
+                Err(e) => {
+                    self.warnings.push(e.to_string());
+                    return (out, None)
+                },
+            }
+        }
+
+        let fd_size = self.fd_size(&cdesc);
+        let skip = ("syscall" == cdesc.symbol) as usize;
+        let mut records = Vec::new();
+        let mut addrs = Vec::new();
+        let mut fd = Vec::new();
+        for (ind, adesc) in cdesc.args.iter().enumerate() {
+            let size = width(&adesc.leaf, fd_size);
+            let value = match args.get(ind.wrapping_sub(skip)) {
+                Some(arg) if ind >= skip => Executor::value(arg, results, procid),
+                _ => {
+                    // syscall number or arg program does not have
+                    let (data, extra) = seed::encode(&adesc.leaf, None, fd_size, &HashMap::new());
+                    records.push(ArgRecord { data, extra });
+                    addrs.push(None);
+                    continue
+                },
+            };
+            let memory = adesc.memory || size > mem::size_of::<usize>();
+            let data = match self.mem.read(value, size as u64) {
+                Some(data) if memory => data.to_vec(),
+                _ => Executor::bytes(&Meta::new(size), value),
+            };
+            let mut extra = Vec::new();
+            self.gather(&adesc.leaf, &data, fd_size, &mut extra);
+            if let Leaf::Deref { .. } = adesc.leaf {
+                if fd.is_empty() {
+                    fd = data.clone();
+                }
+            }
+            addrs.push(if memory { Some(value) } else { None });
+            records.push(ArgRecord { data, extra });
+        }
+
+        if let Some(ref kcov) = self.kcov {
+            kcov.reset();
+        }
+        let call = match self.calls.get_mut(&cdesc.name) {
+            Some(call) => call,
+            None => return (out, None),
+        };
+        call.do_replay(&fd, &records);
+        // taken by Call right after ccall, kcov + observers + poclog below would overwrite it
+        let errno = call.errno() as u32;
+        if let Some(ref kcov) = self.kcov {
+            let pcs = kcov.pcs();
+            out.signal = signal(pcs);
+            out.cover = pcs.iter().map(|&pc| pc as u32).collect();
+        }
+
+        let ok = call.ok();
+        let ret = if ok && !call.einfo().is_empty() { Some(int(call.einfo())) } else { None };
+        let live = (0..call.n_args())
+            .map(|ind| call.args_view(ind).data().to_vec())
+            .collect::< Vec< Vec<u8> > >();
+        for (ind, adesc) in cdesc.args.iter().enumerate() {
+            if let (Some(live), Some(rec)) = (live.get(ind), records.get(ind)) {
+                self.write_back(&adesc.leaf, live, &rec.data, addrs[ind], fd_size);
+            }
+        }
+        out.errno = if ok { 0 } else { errno };
+        out.flags = ipc::CALL_EXECUTED | ipc::CALL_FINISHED;
+        (out, ret)
+    }
+
+    /// execute one program, collect_cover : cover of calls is reported, not just signal
+    pub fn execute(&mut self, prog: &[Instr], procid: u64, collect_cover: bool) -> Vec<CallOutput> {
+        self.mem.reset();
+        let mut results = HashMap::new();
+        let mut outputs = Vec::new();
+        let mut finished = false;
+        for instr in prog.iter() {
+            match *instr {
+                Instr::Copyin { addr, ref arg } => if !self.copyin(addr, arg, &results, procid) {
+                    self.warnings.push(format!("copyin out of data segment : 0x{:x}", addr));
+                },
+                Instr::Copyout { index, addr, size } => if finished {
+                    if let Some(data) = self.mem.read(addr, size) {
+                        results.insert(index, int(data));
+                    }
+                },
+                Instr::Call { num, copyout, ref args } => {
+                    let (mut out, ret) = self.call(outputs.len() as u32, num, copyout.is_some(), args, &results, procid);
+                    finished = 0 == out.errno;
+                    if let (Some(index), Some(ret)) = (copyout, ret) {
+                        results.insert(index, ret);
+                    }
+                    if !collect_cover {
+                        out.cover.clear();
+                    }
+                    outputs.push(out);
+                },
+            }
+        }
+        outputs
+    }
+}
+
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use core::exec::fd_info::Fd;
+use core::exec::id::CallTableId;
+use core::poc::trace::{ArgRecord, CallRecord, Trace};
+use core::state::id::StateTableId;
+
+use desc::format::{CallDesc, Description, Leaf};
+
+use import::width;
+use prog::{Prog, ProgCall, Value};
+
+fn int(data: &[u8]) -> u64 {
+    let mut raw = [0u8; 8];
+    let size = data.len().min(8);
+    raw[..size].copy_from_slice(&data[..size]);
+    u64::from_le_bytes(raw)
+}
+
+/// id -> syz name out of call table, role variants ( accept4#ctor ) are one syscall for syzkaller
+pub fn names(table: &Description) -> HashMap<u64, String> {
+    table.call
+        .iter()
+        .map(|cdesc| (cdesc.id, cdesc.name.split('#').next().unwrap_or(&cdesc.name).to_string()))
+        .collect()
+}
+
+/// recorded trace -> syz program
+///
+/// - calls are named by user supplied id -> syscall map, unknown ids are skipped
+/// - fd of state becomes resource variable defined by its ctor, args holding that fd use it
+///     - fds are reused by target, latest ctor of fd wins
+/// - with call table, args are decoded by its leafs : pointers, structs, arrays, fds inside of them
+/// - without it, args are plain ints, or pointers to raw data for memory ones
+/// - calls of different threads interleaved in trace are raced, therefore marked async, except ctors
+///   whose resource must be ready for following calls
+pub struct Exporter<'a> {
+    names: HashMap<u64, String>,
+    calls: HashMap<u64, &'a CallDesc>,
+    /// state id -> fd_size, for fd leafs without size
+    fd_sizes: HashMap<u64, usize>,
+    /// fd -> resource variable
+    vars: Vec<(Vec<u8>, String)>,
+    /// resource variable ctor writes trough fd_arg, taken by first fd holder decoded
+    out: Option<String>,
+    defined: usize,
+    pub warnings: Vec<String>,
+}
+
+impl<'a> Exporter<'a> {
+    pub fn new(names: HashMap<u64, String>, table: Option<&'a Description>) -> Exporter<'a> {
+        Exporter {
+            names,
+            calls : table
+                .map(|table| table.call.iter().map(|cdesc| (cdesc.id, cdesc)).collect())
+                .unwrap_or_default(),
+            fd_sizes : table
+                .map(|table| table.state.iter().map(|sdesc| (sdesc.id, sdesc.fd_size)).collect())
+                .unwrap_or_default(),
+            vars : Vec::new(),
+            out : None,
+            defined : 0,
+            warnings : Vec::new(),
+        }
+    }
+
+    fn var(&self, fd: &[u8]) -> Option<String> {
+        if Fd::new(fd).is_invalid() {
+            return None
+        }
+        self.vars
+            .iter()
+            .find(|&(var_fd, _)| var_fd[..] == fd[..])
+            .map(|(_, var)| var.clone())
+    }
+
+    /// fd of every thread, first valid one it called with
+    fn thread_fds(trace: &Trace) -> HashMap<u64, Vec<u8>> {
+        let mut fds = HashMap::new();
+        for rec in trace.calls.iter() {
+            if !Fd::new(&rec.fd).is_invalid() {
+                fds.entry(rec.tid).or_insert_with(|| rec.fd.clone());
+            }
+        }
+        fds
+    }
+
+    pub fn export(&mut self, trace: &Trace) -> Prog {
+        let fds = Exporter::thread_fds(trace);
+        let mut started = Vec::new();
+        let mut prog = Prog::default();
+        let mut tids = Vec::new();
+        let mut unknown = Vec::new();
+
+        for rec in trace.calls.iter() {
+            let ctor = !started.contains(&rec.tid) && Fd::new(&rec.fd).is_invalid();
+            if !started.contains(&rec.tid) {
+                started.push(rec.tid);
+            }
+
+            let CallTableId::Id(cid) = rec.cid.clone();
+            let name = match self.names.get(&cid) {
+                Some(name) => name.clone(),
+                None => {
+                    if !unknown.contains(&cid) {
+                        unknown.push(cid);
+                    }
+                    continue
+                },
+            };
+            let defined = match fds.get(&rec.tid) {
+                Some(fd) if ctor => {
+                    self.defined += 1;
+                    Some((fd.clone(), format!("r{}", self.defined - 1)))
+                },
+                _ => None,
+            };
+            // ctor writing fd trough fd_arg : first fd holder decoded takes variable as <rN=>0x0
+            let out = self.calls.get(&cid).is_some_and(|cdesc| !cdesc.fd_ret && cdesc.fd_arg.is_some());
+            self.out = defined.as_ref().filter(|_| out).map(|(_, var)| var.clone());
+
+            let args = match self.calls.get(&cid).cloned() {
+                Some(cdesc) => self.decode_args(rec, cdesc),
+                None => rec.args.iter().map(|arg| self.raw(rec, arg)).collect(),
+            };
+            let ret = match defined {
+                Some(_) if out && self.out.is_none() => None,
+                Some((_, ref var)) => Some(var.clone()),
+                None => None,
+            };
+            self.out = None;
+            if let Some((fd, var)) = defined {
+                self.vars.retain(|(var_fd, _)| var_fd[..] != fd[..]);
+                self.vars.push((fd, var));
+            }
+
+            tids.push(rec.tid);
+            prog.calls.push(ProgCall { ret, name, args, props : Vec::new() });
+        }
+
+        for ind in 0..prog.calls.len().saturating_sub(1) {
+            if tids[ind] != tids[ind + 1] && prog.calls[ind].defines().is_empty() {
+                prog.calls[ind].props.push(String::from("async"));
+            }
+        }
+        if !unknown.is_empty() {
+            self.warnings.push(format!("call ids without syz name skipped : {}", unknown
+                .iter()
+                .map(|cid| format!("0x{:x}", cid))
+                .collect::< Vec<String> >()
+                .join(", ")));
+        }
+        prog
+    }
+
+    /// no layout known : ints, state fd as its variable, memory and pointers as raw data
+    fn raw(&self, rec: &CallRecord, arg: &ArgRecord) -> Value {
+        if arg.data.len() > 8 {
+            return Value::Ptr(Some(Box::new(Value::Data([&arg.data[..], &arg.extra[..]].concat()))))
+        }
+        if !arg.extra.is_empty() {
+            return Value::Ptr(Some(Box::new(Value::Data(arg.extra.clone()))))
+        }
+        let fd = &rec.fd[..];
+        if !fd.is_empty() && arg.data.len() >= fd.len() && arg.data[..fd.len()] == fd[..] && arg.data[fd.len()..].iter().all(|&b| 0 == b) {
+            if let Some(var) = self.var(fd) {
+                return Value::Res(var)
+            }
+        }
+        Value::Int(int(&arg.data))
+    }
+
+    fn decode_args(&mut self, rec: &CallRecord, cdesc: &CallDesc) -> Vec<Value> {
+        let StateTableId::Id(sid) = rec.sid.clone();
+        let fd_size = self.fd_sizes.get(&sid).cloned().unwrap_or(rec.fd.len());
+        let skip = ("syscall" == cdesc.symbol) as usize;
+        let mut args = Vec::new();
+        for (ind, desc) in cdesc.args.iter().enumerate().skip(skip) {
+            let arg = match rec.args.get(ind) {
+                Some(arg) => arg,
+                None => break,
+            };
+            if arg.data.len() != width(&desc.leaf, fd_size) {
+                self.warnings.push(format!("{} arg {} recorded with size {}, call table changed since ?", cdesc.name, ind, arg.data.len()));
+                args.push(self.raw(rec, arg));
+                continue
+            }
+            let (val, _) = self.decode(&desc.leaf, &arg.data, &arg.extra, &rec.fd, fd_size);
+            args.push(if desc.memory { Value::Ptr(Some(Box::new(val))) } else { val });
+        }
+        args
+    }
+
+    /// value of leaf out of its recorded data, returns how much of extra it took
+    fn decode(&mut self, leaf: &Leaf, data: &[u8], extra: &[u8], fd: &[u8], fd_size: usize) -> (Value, usize) {
+        let size = width(leaf, fd_size).min(data.len());
+        let data = &data[..size];
+        let val = match *leaf {
+            Leaf::Const { .. } | Leaf::Flag { .. } | Leaf::Bounded { .. } => Value::Int(int(data)),
+            Leaf::Rnd { .. } | Leaf::Pattern { .. } | Leaf::Phantom { .. } if size <= 8 => Value::Int(int(data)),
+            Leaf::Rnd { .. } | Leaf::Pattern { .. } | Leaf::Phantom { .. } |
+            Leaf::Bytes { .. } | Leaf::Astr { .. } | Leaf::Wstr { .. } => Value::Data(data.to_vec()),
+            Leaf::Ptr { ref to } => {
+                let size = width(to, fd_size);
+                if extra.len() < size {
+                    return (Value::Ptr(None), 0)
+                }
+                let (val, used) = self.decode(to, &extra[..size], &extra[size..], fd, fd_size);
+                return (Value::Ptr(Some(Box::new(val))), size + used)
+            },
+            // state fd, recorded fd of call is the real one
+            Leaf::Deref { .. } => match self.var(fd) {
+                Some(var) => Value::Res(var),
+                None => Value::Int(int(data)),
+            },
+            Leaf::Fd { ref of, .. } if of.is_empty() && self.out.is_some() => Value::Out(self.out.take().unwrap_or_default()),
+            Leaf::Fd { .. } | Leaf::RndFd { .. } => match self.var(data) {
+                Some(var) => Value::Res(var),
+                None => Value::Int(int(data)),
+            },
+            Leaf::Array { count, ref of, .. } => {
+                let size = width(of, fd_size);
+                let mut used = 0;
+                let mut vals = Vec::new();
+                for ind in 0..count {
+                    if (ind + 1) * size > data.len() {
+                        break
+                    }
+                    let (val, more) = self.decode(of, &data[ind * size..], &extra[used..], fd, fd_size);
+                    used += more;
+                    vals.push(val);
+                }
+                return (Value::Array(vals), used)
+            },
+            Leaf::Struct { ref fields, .. } => {
+                let mut end = 0;
+                let mut used = 0;
+                let mut vals = Vec::new();
+                for field in fields.iter() {
+                    let offset = field.offset.unwrap_or(end);
+                    end = offset + width(&field.leaf, fd_size);
+                    if end > data.len() {
+                        break
+                    }
+                    let (val, more) = self.decode(&field.leaf, &data[offset..end], &extra[used..], fd, fd_size);
+                    used += more;
+                    vals.push(val);
+                }
+                return (Value::Group(vals), used)
+            },
+        };
+        (val, 0)
+    }
+}
@@ -0,0 +1,645 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::mem;
+
+use desc::format::{ArgDesc, CallDesc, Description, Field, Leaf, StateDesc, Success};
+
+use syzlang::{Syscall, Syzlang, Ty};
+
+/// fuzzing iterations per object, as toy tables do
+pub const LIMIT: usize = 40;
+/// count for arrays / strings without size, to keep args fixed sized
+const VARLEN: usize = 8;
+/// recursive structs ( lists, trees ) are cut there, by NULL pointer
+const DEPTH: usize = 8;
+/// first call id, 0..0x10 are reserved ( CallTableId::is_default ) and limiter, filter, .. let them trough
+pub const CALL_ID_BASE: u64 = 0x10;
+/// fields computed from other fields
+const LENS: &[&str] = &["len", "bytesize", "bitsize", "offsetof"];
+
+fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+/// size of leaf as built by desc, importer need it for layout of structs and for len fields
+///
+/// - importer always states size of fd leafs, so fd_size of state does not matter there
+pub fn size(leaf: &Leaf) -> usize {
+    width(leaf, 0)
+}
+
+/// size of leaf as built by desc, fd leafs without size take fd_size of state
+pub fn width(leaf: &Leaf, fd_size: usize) -> usize {
+    match *leaf {
+        Leaf::Const { size, .. } | Leaf::Flag { size, .. } | Leaf::Bounded { size, .. } => size,
+        Leaf::Rnd { size } | Leaf::Pattern { size, .. } | Leaf::Phantom { size } => size,
+        Leaf::Bytes { ref data } => data.len(),
+        Leaf::Ptr { .. } => mem::size_of::<usize>(),
+        Leaf::Deref { size, .. } | Leaf::Fd { size, .. } | Leaf::RndFd { size, .. } => size.unwrap_or(fd_size),
+        Leaf::Astr { ref prefix, count, nz } => prefix.len() + count + nz.map_or(1, |_| 0),
+        Leaf::Wstr { ref prefix, count, nz } => 2 * (prefix.len() + count + if nz { 0 } else { 1 }),
+        Leaf::Array { count, ref of, .. } => count * width(of, fd_size),
+        Leaf::Struct { size: Some(size), .. } => size,
+        Leaf::Struct { ref fields, .. } => fields
+            .iter()
+            .fold((0, 0), |(end, max), field| {
+                let end = field.offset.unwrap_or(end) + width(&field.leaf, fd_size);
+                (end, max.max(end))
+            }).1,
+    }
+}
+
+fn int_size(name: &str) -> Option<usize> {
+    match name.trim_end_matches("be") {
+        "int8" | "bool8" => Some(1),
+        "int16" | "bool16" => Some(2),
+        "int32" | "bool32" => Some(4),
+        "int64" | "bool64" | "intptr" | "boolptr" => Some(8),
+        _ => None,
+    }
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// role of call in state, decides how resources of state are passed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+enum Role {
+    /// all resources are foreign ones, rnd_fd
+    Ctor,
+    /// resource at index is our own, deref
+    Worker(usize),
+}
+
+/// syzlang -> call table description of desc crate
+///
+/// - resource : State, StateTableId is bit of resource + bits of its parents ( fd > sock > sock_tcp )
+///     - so rnd_fd of fd picks any alive fd based state
+/// - call returning resource ( or writing it trough ptr[out, res] ) : ctor of state, group 0
+/// - call taking resource of state or its parent : worker of state, group 1
+/// - close / *destroy / *delete / .. taking resource : dtor
+/// - flags : Flag, int ranges : Bounded, ptr[in, T] : ptr ( memory arg at top level ), len : const of
+///   known size, as all our args are fixed size ( varlen arrays and strings included )
+pub struct Import<'a> {
+    syz: &'a Syzlang,
+    module: String,
+    bits: HashMap<String, u64>,
+    calls: BTreeMap<(usize, Role), CallDesc>,
+    pub warnings: Vec<String>,
+}
+
+impl<'a> Import<'a> {
+    pub fn new(syz: &'a Syzlang, module: &str) -> Import<'a> {
+        Import {
+            syz,
+            module : module.to_string(),
+            bits : HashMap::new(),
+            calls : BTreeMap::new(),
+            warnings : Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, what: String) {
+        if !self.warnings.contains(&what) {
+            self.warnings.push(what);
+        }
+    }
+
+    /// type aliases are resolved until real type
+    fn resolve(&self, ty: &Ty) -> Ty {
+        let mut ty = ty.clone();
+        for _ in 0..DEPTH {
+            match self.syz.types.get(ty.name()) {
+                Some(alias) if ty.args().is_empty() => ty = alias.clone(),
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    fn is_resource(&self, name: &str) -> bool {
+        "fd" == name || self.syz.resources.iter().any(|res| res.name == name)
+    }
+
+    /// resource and its parents, sock_tcp -> [sock_tcp, sock, fd]
+    fn chain(&self, name: &str) -> Vec<String> {
+        let mut chain = vec![name.to_string()];
+        while let Some(res) = self.syz.resources.iter().find(|res| chain.last() == Some(&res.name)) {
+            let base = self.resolve(&res.base);
+            if !self.is_resource(base.name()) || chain.iter().any(|name| name == base.name()) {
+                break
+            }
+            chain.push(base.name().to_string());
+        }
+        chain
+    }
+
+    /// fd is int32 by convention even if not declared ( sys.txt not imported )
+    fn fd_size(&self, name: &str) -> usize {
+        let last = self.chain(name).pop().unwrap_or_default();
+        match self.syz.resources.iter().find(|res| res.name == last) {
+            Some(res) => int_size(self.resolve(&res.base).name()).unwrap_or(4),
+            None => 4,
+        }
+    }
+
+    fn bit(&mut self, name: &str) -> Result<u64, io::Error> {
+        if let Some(&bit) = self.bits.get(name) {
+            return Ok(bit)
+        }
+        if self.bits.len() >= 64 {
+            return Err(invalid(format!("more than 64 resources ( at {} ), StateTableId is bitmask; import less descriptions at once", name)))
+        }
+        let bit = 1u64 << self.bits.len();
+        self.bits.insert(name.to_string(), bit);
+        Ok(bit)
+    }
+
+    fn state_id(&mut self, name: &str) -> Result<u64, io::Error> {
+        let mut id = 0;
+        for res in self.chain(name) {
+            id |= self.bit(&res)?;
+        }
+        Ok(id)
+    }
+
+    fn value(&mut self, ty: &Ty) -> i64 {
+        match self.syz.value(ty) {
+            Some(value) => value,
+            None => {
+                self.warn(format!("unknown constant {:?}, used 0 ( missing .const file ? )", ty));
+                0
+            },
+        }
+    }
+
+    /// size of int type given as argument, or intptr for top level args where it can be omitted
+    fn arg_size(&self, args: &[Ty], ind: usize) -> usize {
+        args.get(ind)
+            .map(|ty| self.resolve(ty))
+            .and_then(|ty| int_size(ty.name()))
+            .unwrap_or(8)
+    }
+
+    fn string(&mut self, args: &[Ty], zero: bool) -> Leaf {
+        let mut data = match args.first() {
+            Some(Ty::Str(lit)) => lit.as_bytes().to_vec(),
+            Some(Ty::Ident(flags, _)) => match self.syz.flags.get(flags).and_then(|values| values.first()) {
+                Some(Ty::Str(lit)) => lit.as_bytes().to_vec(),
+                _ => {
+                    self.warn(format!("string flags {} unknown", flags));
+                    Vec::new()
+                },
+            },
+            _ => return match zero {
+                true => Leaf::Astr { prefix : String::new(), count : VARLEN, nz : None },
+                false => Leaf::Astr { prefix : String::new(), count : VARLEN, nz : Some(*b"az") },
+            },
+        };
+        if zero {
+            data.push(0);
+        }
+        if let Some(&Ty::Int(pad)) = args.get(1) {
+            data.resize((pad as usize).max(data.len()), 0);
+        }
+        if data.is_empty() {
+            data.push(0);
+        }
+        Leaf::Bytes { data }
+    }
+
+    /// resource passed in : random alive fd of resource or its childs, or special value of its chain
+    fn resource(&mut self, name: &str, out: bool) -> Result<Leaf, io::Error> {
+        let size = self.fd_size(name);
+        if out {
+            return Ok(Leaf::Fd { size : Some(size), of : Vec::new() })
+        }
+        let mut of = vec![Leaf::RndFd { state : String::new(), id : Some(self.bit(name)?), size : Some(size) }];
+        let values: Vec<Ty> = self.chain(name)
+            .iter()
+            .filter_map(|name| self.syz.resources.iter().find(|res| &res.name == name))
+            .flat_map(|res| res.values.clone())
+            .collect();
+        for value in values.iter() {
+            let value = self.value(value);
+            of.push(Leaf::Const { size, value });
+        }
+        Ok(Leaf::Fd { size : Some(size), of })
+    }
+
+    /// struct / union, fields are laid out with C alignment unless packed
+    fn structure(&mut self, name: &str, depth: usize) -> Result<(Leaf, usize), io::Error> {
+        let st = match self.syz.structs.get(name) {
+            Some(st) => st.clone(),
+            None => return Err(invalid(format!("no struct {}", name))),
+        };
+        let packed = st.attrs.iter().any(|attr| "packed" == attr.name());
+        let attr = |key: &str| st.attrs
+            .iter()
+            .find(|attr| key == attr.name())
+            .and_then(|attr| attr.args().first())
+            .and_then(|arg| self.syz.value(arg))
+            .map(|val| val as usize);
+        let (size_attr, align_attr) = (attr("size"), attr("align"));
+
+        let leafs = self.fields(&st.fields, depth)?;
+        if st.union {
+            let size = leafs.iter().map(|(_, leaf, _)| size(leaf)).max().unwrap_or(0);
+            let align = leafs.iter().map(|&(_, _, align)| align).max().unwrap_or(1);
+            let size = size_attr.unwrap_or_else(|| align_to(size, align));
+            let fields = leafs
+                .into_iter()
+                .take(1)
+                .map(|(_, leaf, _)| Field { offset : Some(0), leaf })
+                .collect();
+            return Ok((Leaf::Struct { name : name.to_string(), size : Some(size), fields }, align_attr.unwrap_or(align)))
+        }
+
+        let mut fields = Vec::new();
+        let (mut offset, mut align) = (0, 1);
+        // (bits left, size) of bitfield unit in use
+        let mut unit = (0, 0);
+        for ((_, ty), (_, leaf, falign)) in st.fields.iter().zip(leafs) {
+            if let Ty::Bits(_, bits) = *ty {
+                let fsize = size(&leaf);
+                if unit.1 == fsize && unit.0 >= bits {
+                    unit.0 -= bits;
+                    continue
+                }
+                unit = (8 * fsize - bits.min(8 * fsize), fsize);
+            } else {
+                unit = (0, 0);
+            }
+            let falign = if packed { 1 } else { falign };
+            offset = align_to(offset, falign);
+            align = align.max(falign);
+            let fsize = size(&leaf);
+            fields.push(Field { offset : Some(offset), leaf });
+            offset += fsize;
+        }
+        let size = size_attr.unwrap_or_else(|| align_to(offset, align));
+        if size < offset {
+            self.warn(format!("struct {} declared smaller than its fields {} < {}", name, size, offset));
+        }
+        Ok((Leaf::Struct { name : name.to_string(), size : Some(size.max(offset)), fields }, align_attr.unwrap_or(align)))
+    }
+
+    /// fields of struct or args of call; len fields are resolved after their targets
+    fn fields(&mut self, fields: &[(String, Ty)], depth: usize) -> Result<Vec<(String, Leaf, usize)>, io::Error> {
+        let mut leafs: Vec<Option<(Leaf, usize)>> = Vec::new();
+        for (_, ty) in fields.iter() {
+            let ty = self.resolve(ty);
+            if LENS.contains(&ty.name()) {
+                leafs.push(None);
+                continue
+            }
+            leafs.push(self.leaf(&ty, false, depth)?);
+        }
+        for (ind, (_, ty)) in fields.iter().enumerate() {
+            let ty = self.resolve(ty);
+            if !LENS.contains(&ty.name()) {
+                continue
+            }
+            let width = self.arg_size(ty.args(), 1);
+            let target = ty.args().first().map(|target| target.name().to_string()).unwrap_or_default();
+            let value = fields
+                .iter()
+                .position(|(name, _)| *name == target)
+                .and_then(|pos| leafs[pos].as_ref())
+                .map(|(leaf, _)| match (ty.name(), leaf) {
+                    ("len", Leaf::Ptr { to }) => match **to {
+                        Leaf::Array { count, .. } => count,
+                        ref leaf => size(leaf),
+                    },
+                    ("len", &Leaf::Array { count, .. }) => count,
+                    ("bitsize", Leaf::Ptr { to }) => 8 * size(to),
+                    (_, Leaf::Ptr { to }) => size(to),
+                    ("bitsize", leaf) => 8 * size(leaf),
+                    (_, leaf) => size(leaf),
+                });
+            leafs[ind] = Some(match value {
+                Some(value) => (Leaf::Const { size : width, value : value as i64 }, width),
+                None => {
+                    self.warn(format!("{}[{}] not resolvable, bounded instead", ty.name(), target));
+                    (Leaf::Bounded { size : width, ranges : vec![[0, 0x100]], signed : false }, width)
+                },
+            });
+        }
+        Ok(fields
+            .iter()
+            .zip(leafs)
+            .filter_map(|((name, _), leaf)| leaf.map(|(leaf, align)| (name.clone(), leaf, align)))
+            .collect())
+    }
+
+    /// syzlang type -> leaf + its alignment, None for void
+    fn leaf(&mut self, ty: &Ty, out: bool, depth: usize) -> Result<Option<(Leaf, usize)>, io::Error> {
+        let ty = self.resolve(ty);
+        let args = ty.args().to_vec();
+        if let Ty::Bits(ref ty, _) = ty {
+            return self.leaf(ty, out, depth)
+        }
+        if let Some(size) = int_size(ty.name()) {
+            let leaf = match args.first() {
+                _ if ty.name().starts_with("bool") => Leaf::Bounded { size, ranges : vec![[0, 1]], signed : false },
+                Some(&Ty::Range(lo, hi)) => Leaf::Bounded { size, ranges : vec![[lo, hi]], signed : lo < 0 },
+                Some(&Ty::Int(value)) => Leaf::Const { size, value },
+                _ => Leaf::Rnd { size },
+            };
+            return Ok(Some((leaf, size)))
+        }
+        let leaf = match ty.name() {
+            "void" => return Ok(None),
+            "const" => {
+                let size = self.arg_size(&args, 1);
+                let value = args.first().map_or(0, |value| self.value(value));
+                (Leaf::Const { size, value }, size)
+            },
+            "flags" => {
+                let size = self.arg_size(&args, 1);
+                let name = args.first().map(|name| name.name().to_string()).unwrap_or_default();
+                let values = self.syz.flags.get(&name).cloned().unwrap_or_default();
+                if values.is_empty() {
+                    self.warn(format!("flags {} unknown", name));
+                }
+                let flag = values.iter().fold(0, |flag, value| flag | self.value(value));
+                (Leaf::Flag { size, always : 0, flag }, size)
+            },
+            "proc" => {
+                let size = self.arg_size(&args, 2);
+                let start = args.first().map_or(0, |value| self.value(value));
+                let per = args.get(1).map_or(1, |value| self.value(value));
+                (Leaf::Bounded { size, ranges : vec![[start, start + 4 * per - 1]], signed : false }, size)
+            },
+            "ptr" | "ptr64" => {
+                if depth >= DEPTH {
+                    return Ok(Some((Leaf::Const { size : 8, value : 0 }, 8)))
+                }
+                let out = args.first().is_some_and(|dir| "in" != dir.name());
+                let to = match args.get(1) {
+                    Some(to) => self.leaf(to, out, depth + 1)?,
+                    None => None,
+                };
+                match to {
+                    Some((to, _)) => (Leaf::Ptr { to : Box::new(to) }, 8),
+                    None => (Leaf::Const { size : 8, value : 0 }, 8),
+                }
+            },
+            "buffer" => {
+                let to = match args.first().map(|dir| dir.name()) {
+                    Some("in") => Leaf::Rnd { size : 8 * VARLEN },
+                    _ => Leaf::Phantom { size : 0x100 },
+                };
+                (Leaf::Ptr { to : Box::new(to) }, 8)
+            },
+            "array" => {
+                let elem = match args.first() {
+                    Some(elem) => self.leaf(elem, out, depth)?,
+                    None => None,
+                };
+                let (elem, align) = match elem {
+                    Some(elem) => elem,
+                    None => return Ok(None),
+                };
+                let count = match args.get(1) {
+                    Some(&Ty::Int(count)) => count as usize,
+                    Some(&Ty::Range(lo, hi)) => (lo as usize).max((hi as usize).min(VARLEN)),
+                    _ => VARLEN,
+                }.max(1);
+                match elem {
+                    Leaf::Rnd { size } => (Leaf::Rnd { size : size * count }, align),
+                    elem => (Leaf::Array { name : String::new(), count, of : Box::new(elem) }, align),
+                }
+            },
+            "string" => (self.string(&args, true), 1),
+            "stringnoz" => (self.string(&args, false), 1),
+            "filename" => (Leaf::Astr { prefix : String::from("./file"), count : 1, nz : None }, 1),
+            "glob" => (Leaf::Astr { prefix : String::from("./"), count : 1, nz : None }, 1),
+            "fmt" => (Leaf::Bytes { data : b"0".to_vec() }, 1),
+            // len outside of struct / call args ( ptr[inout, len[..]] ), sibling unknown here
+            name if LENS.contains(&name) => {
+                let size = self.arg_size(&args, 1);
+                (Leaf::Bounded { size, ranges : vec![[0, 0x100]], signed : false }, size)
+            },
+            "vma" | "vma64" => (Leaf::Const { size : 8, value : 0 }, 8),
+            "csum" => {
+                let size = self.arg_size(&args, 2);
+                (Leaf::Rnd { size }, size)
+            },
+            "text" | "compressed_image" => (Leaf::Rnd { size : 8 * VARLEN }, 1),
+            name if self.is_resource(name) => {
+                let size = self.fd_size(name);
+                (self.resource(name, out)?, size)
+            },
+            name if self.syz.structs.contains_key(name) => self.structure(name, depth)?,
+            name => {
+                if self.syz.templates.iter().any(|tmpl| tmpl == name) {
+                    self.warn(format!("template {} not supported, rnd instead", name));
+                } else {
+                    self.warn(format!("unknown type {:?}, rnd instead", ty));
+                }
+                (Leaf::Rnd { size : 8 }, 1)
+            },
+        };
+        Ok(Some(leaf))
+    }
+
+    /// resource call creates : returned one, or written trough ptr[out, res]
+    fn produces(&self, call: &Syscall) -> Option<(String, Option<usize>)> {
+        if let Some(ref ret) = call.ret {
+            let ret = self.resolve(ret);
+            if self.is_resource(ret.name()) {
+                return Some((ret.name().to_string(), None))
+            }
+        }
+        call.args
+            .iter()
+            .enumerate()
+            .filter_map(|(ind, (_, ty))| {
+                let ty = self.resolve(ty);
+                if !["ptr", "ptr64"].contains(&ty.name()) || Some("in") == ty.args().first().map(|dir| dir.name()) {
+                    return None
+                }
+                let to = self.resolve(ty.args().get(1)?);
+                if self.is_resource(to.name()) { Some((to.name().to_string(), Some(ind))) } else { None }
+            })
+            .next()
+    }
+
+    /// index of top level arg which is resource of chain
+    fn consumes(&self, call: &Syscall, chain: &[String]) -> Option<usize> {
+        call.args
+            .iter()
+            .position(|(_, ty)| chain.iter().any(|res| *res == self.resolve(ty).name()))
+    }
+
+    fn skip(&mut self, call: &Syscall) -> bool {
+        if call.name.starts_with("syz_") {
+            self.warn(format!("{} is pseudo syscall of syz-executor, skipped", call.name));
+            return true
+        }
+        if call.attrs.iter().any(|attr| "disabled" == attr.name()) {
+            return true
+        }
+        false
+    }
+
+    /// call of role, built once and shared by states
+    fn call(&mut self, ind: usize, role: Role) -> Result<String, io::Error> {
+        if let Some(cdesc) = self.calls.get(&(ind, role)) {
+            return Ok(cdesc.name.clone())
+        }
+        let call = self.syz.syscalls[ind].clone();
+        let nr = self.syz.consts.get(&format!("__NR_{}", call.base())).cloned();
+
+        let mut args = match nr {
+            Some(nr) => vec![ArgDesc { memory : false, leaf : Leaf::Const { size : 8, value : nr } }],
+            None => Vec::new(),
+        };
+        let produces = self.produces(&call);
+        let leafs = self.fields(&call.args, 0)?;
+        for (pos, (_, leaf, _)) in leafs.into_iter().enumerate() {
+            let ty = self.resolve(&call.args[pos].1);
+            args.push(match (role, leaf) {
+                (Role::Worker(own), _) if own == pos => ArgDesc {
+                    memory : false,
+                    leaf : Leaf::Deref { size : None, offset : 0 },
+                },
+                (_, Leaf::Ptr { to }) if ["ptr", "ptr64"].contains(&ty.name()) => ArgDesc { memory : true, leaf : *to },
+                (_, leaf) => ArgDesc { memory : false, leaf },
+            });
+        }
+
+        // same call can be ctor of one state and worker of other, or deref different args
+        let first = call.args
+            .iter()
+            .position(|(_, ty)| self.is_resource(self.resolve(ty).name()));
+        let name = match role {
+            Role::Ctor if first.is_none() => call.name.clone(),
+            Role::Ctor => format!("{}#ctor", call.name),
+            Role::Worker(own) if Some(own) == first => call.name.clone(),
+            Role::Worker(own) => format!("{}#{}", call.name, own),
+        };
+        let shift = if nr.is_some() { 1 } else { 0 };
+        let cdesc = CallDesc {
+            name : name.clone(),
+            id : CALL_ID_BASE + self.calls.len() as u64,
+            module : String::new(),
+            symbol : if nr.is_some() { String::from("syscall") } else { call.base().to_string() },
+            ok : Success::NonNeg,
+            ret_size : if nr.is_some() { 8 } else { 4 },
+            fd_ret : Role::Ctor == role && produces.as_ref().is_some_and(|&(_, arg)| arg.is_none()),
+            fd_arg : match (role, produces) {
+                (Role::Ctor, Some((_, Some(arg)))) => Some(arg + shift),
+                _ => None,
+            },
+            args,
+        };
+        if cdesc.args.len() > desc::invoke::MAX_ARGS {
+            return Err(invalid(format!("{} has too many args", call.name)))
+        }
+        self.calls.insert((ind, role), cdesc);
+        Ok(name)
+    }
+
+    /// dtor named so, with least args ( close(fd) rather than shmctl$IPC_RMID(..) ), or close for fd
+    fn dtor(&self, chain: &[String], workers: &[(usize, usize)]) -> Option<(usize, usize)> {
+        const DTORS: &[&str] = &["close", "destroy", "delete", "free", "release", "unlink"];
+        workers
+            .iter()
+            .cloned()
+            .filter(|&(ind, _)| DTORS.iter().any(|name| self.syz.syscalls[ind].base().contains(name)))
+            .min_by_key(|&(ind, _)| self.syz.syscalls[ind].args.len())
+            .or_else(|| {
+                if !chain.iter().any(|res| "fd" == res) {
+                    return None
+                }
+                self.syz.syscalls
+                    .iter()
+                    .position(|call| "close" == call.name)
+                    .map(|ind| (ind, 0))
+            })
+            .or_else(|| workers.first().cloned())
+    }
+
+    /// build whole description
+    pub fn run(mut self) -> Result<(Description, Vec<String>), io::Error> {
+        let mut states = Vec::new();
+        let mut skipped = Vec::new();
+        for res in self.syz.resources.iter() {
+            let chain = self.chain(&res.name);
+            let mut ctors = Vec::new();
+            let mut workers = Vec::new();
+            for (ind, call) in self.syz.syscalls.iter().enumerate() {
+                if self.produces(call).map(|(name, _)| name) == Some(res.name.clone()) {
+                    ctors.push(ind);
+                }
+                if let Some(own) = self.consumes(call, &chain) {
+                    workers.push((ind, own));
+                }
+            }
+            if ctors.is_empty() || workers.is_empty() {
+                skipped.push(res.name.clone());
+                continue
+            }
+            states.push((res.name.clone(), chain, ctors, workers));
+        }
+        for name in skipped {
+            self.warn(format!("resource {} has no ctor or no worker, not a state", name));
+        }
+
+        let mut sdescs = Vec::new();
+        for (name, chain, ctors, workers) in states {
+            let ctors = ctors
+                .into_iter()
+                .filter(|&ind| !self.skip(&self.syz.syscalls[ind].clone()))
+                .collect::< Vec<usize> >();
+            let workers = workers
+                .into_iter()
+                .filter(|&(ind, _)| !self.skip(&self.syz.syscalls[ind].clone()))
+                .collect::< Vec<(usize, usize)> >();
+            let dtor = match self.dtor(&chain, &workers) {
+                Some(dtor) if !ctors.is_empty() => dtor,
+                _ => {
+                    self.warn(format!("resource {} has no usable ctor or dtor, not a state", name));
+                    continue
+                },
+            };
+
+            let mut group0 = Vec::new();
+            for &ind in ctors.iter() {
+                group0.push(self.call(ind, Role::Ctor)?);
+            }
+            let mut group1 = Vec::new();
+            for &(ind, own) in workers.iter() {
+                if (ind, own) != dtor {
+                    group1.push(self.call(ind, Role::Worker(own))?);
+                }
+            }
+            let dtor = self.call(dtor.0, Role::Worker(dtor.1))?;
+            if group1.is_empty() {
+                group1.push(dtor.clone());
+            }
+            group0.truncate(0x1FF);
+            group1.truncate(0x1FF);
+
+            sdescs.push(StateDesc {
+                id : self.state_id(&name)?,
+                fd_size : self.fd_size(&name),
+                limit : LIMIT,
+                slopes : vec![[0, 1], [0, 0]],
+                groups : vec![group0, group1],
+                dtor,
+                name,
+            });
+        }
+
+        let desc = Description {
+            module : self.module.clone(),
+            state : sdescs,
+            call : self.calls.values().cloned().collect(),
+        };
+        Ok((desc, self.warnings))
+    }
+}
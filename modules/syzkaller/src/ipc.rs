@@ -0,0 +1,722 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+
+use desc::format::{CallDesc, Description, Leaf};
+
+use import::width;
+use prog::{Prog, Value};
+use seed;
+
+/// syz-executor ipc, shared memory + control pipe flavour ( syzkaller before flatbuffers rpc )
+///
+/// - fd 3 : input shm, program in exec encoding ( prog/encodingexec.go )
+/// - fd 4 : output shm, per call results : errno, flags, signal, cover
+/// - stdin / stdout : control pipe, handshake once, then execute request -> reply per program
+pub const IN_MAGIC: u64 = 0xbadc_0ffe_ebad_face;
+pub const OUT_MAGIC: u32 = 0x0bad_f00d;
+
+pub const IN_FD: i32 = 3;
+pub const OUT_FD: i32 = 4;
+pub const MAX_INPUT: usize = 4 << 20;
+pub const MAX_OUTPUT: usize = 16 << 20;
+
+/// env flags of handshake / execute request we care about
+pub const ENV_SIGNAL: u64 = 1 << 1;
+/// exec flags of execute request we care about
+pub const EXEC_COLLECT_COVER: u64 = 1;
+
+pub const CALL_EXECUTED: u32 = 1;
+pub const CALL_FINISHED: u32 = 1 << 1;
+
+const INSTR_EOF: u64 = !0;
+const INSTR_COPYIN: u64 = !1;
+const INSTR_COPYOUT: u64 = !2;
+
+const ARG_CONST: u64 = 0;
+const ARG_RESULT: u64 = 1;
+const ARG_DATA: u64 = 2;
+const ARG_CSUM: u64 = 3;
+
+const NO_COPYOUT: u64 = !0;
+/// data arg is readable by executor, we do not care
+const DATA_READABLE: u64 = 1 << 63;
+
+const CSUM_INET: u64 = 0;
+const CHUNK_DATA: u64 = 0;
+const CHUNK_CONST: u64 = 1;
+
+pub const FORMAT_NATIVE: u64 = 0;
+pub const FORMAT_BIGENDIAN: u64 = 1;
+pub const FORMAT_STRDEC: u64 = 2;
+pub const FORMAT_STRHEX: u64 = 3;
+pub const FORMAT_STROCT: u64 = 4;
+
+fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+fn read_words<R: Read>(r: &mut R, count: usize) -> Result<Vec<u64>, io::Error> {
+    let mut raw = vec![0u8; 8 * count];
+    r.read_exact(&mut raw)?;
+    Ok(raw
+        .chunks(8)
+        .map(|word| u64::from_le_bytes([word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7]]))
+        .collect())
+}
+
+fn write_words<W: Write>(w: &mut W, words: &[u64]) -> Result<(), io::Error> {
+    let raw = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect::< Vec<u8> >();
+    w.write_all(&raw)?;
+    w.flush()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HandshakeReq {
+    pub magic: u64,
+    pub env_flags: u64,
+    /// procid of executor, pid_stride of consts is multiplied by it
+    pub pid: u64,
+}
+
+impl HandshakeReq {
+    pub fn read<R: Read>(r: &mut R) -> Result<HandshakeReq, io::Error> {
+        let words = read_words(r, 3)?;
+        Ok(HandshakeReq { magic : words[0], env_flags : words[1], pid : words[2] })
+    }
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        write_words(w, &[self.magic, self.env_flags, self.pid])
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecuteReq {
+    pub magic: u64,
+    pub env_flags: u64,
+    pub exec_flags: u64,
+    pub pid: u64,
+    pub fault_call: u64,
+    pub fault_nth: u64,
+    pub syscall_timeout_ms: u64,
+    pub program_timeout_ms: u64,
+    pub slowdown_scale: u64,
+    /// 0 = program is in input shm, the only mode we support
+    pub prog_size: u64,
+}
+
+impl ExecuteReq {
+    pub fn read<R: Read>(r: &mut R) -> Result<ExecuteReq, io::Error> {
+        let words = read_words(r, 10)?;
+        Ok(ExecuteReq {
+            magic : words[0],
+            env_flags : words[1],
+            exec_flags : words[2],
+            pid : words[3],
+            fault_call : words[4],
+            fault_nth : words[5],
+            syscall_timeout_ms : words[6],
+            program_timeout_ms : words[7],
+            slowdown_scale : words[8],
+            prog_size : words[9],
+        })
+    }
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        write_words(w, &[
+            self.magic,
+            self.env_flags,
+            self.exec_flags,
+            self.pid,
+            self.fault_call,
+            self.fault_nth,
+            self.syscall_timeout_ms,
+            self.program_timeout_ms,
+            self.slowdown_scale,
+            self.prog_size])
+    }
+}
+
+/// handshake reply is just OUT_MAGIC, execute reply adds done + status
+pub fn write_reply<W: Write>(w: &mut W, done: Option<u32>) -> Result<(), io::Error> {
+    let mut raw = OUT_MAGIC.to_le_bytes().to_vec();
+    if let Some(status) = done {
+        raw.extend_from_slice(&1u32.to_le_bytes());
+        raw.extend_from_slice(&status.to_le_bytes());
+    }
+    w.write_all(&raw)?;
+    w.flush()
+}
+
+/// returns status of execute reply, None for handshake one
+pub fn read_reply<R: Read>(r: &mut R, execute: bool) -> Result<Option<u32>, io::Error> {
+    let mut raw = [0u8; 12];
+    let size = if execute { 12 } else { 4 };
+    r.read_exact(&mut raw[..size])?;
+    let word = |ind: usize| u32::from_le_bytes([raw[4 * ind], raw[4 * ind + 1], raw[4 * ind + 2], raw[4 * ind + 3]]);
+    if OUT_MAGIC != word(0) {
+        return Err(invalid(format!("bad reply magic 0x{:x}", word(0))))
+    }
+    Ok(if execute { Some(word(2)) } else { None })
+}
+
+/// size + format of const, how to write it to memory
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Meta {
+    pub size: u64,
+    pub format: u64,
+    /// bitfield, bf_len 0 = whole size
+    pub bf_off: u64,
+    pub bf_len: u64,
+    /// value is increased by pid_stride * procid, so parallel executors do not collide ( ports, .. )
+    pub pid_stride: u64,
+}
+
+impl Meta {
+    pub fn new(size: usize) -> Meta {
+        Meta { size : size as u64, ..Meta::default() }
+    }
+    fn decode(meta: u64) -> Meta {
+        Meta {
+            size : meta & 0xff,
+            format : (meta >> 8) & 0xff,
+            bf_off : (meta >> 16) & 0xff,
+            bf_len : (meta >> 24) & 0xff,
+            pid_stride : meta >> 32,
+        }
+    }
+    fn encode(&self) -> u64 {
+        self.size | self.format << 8 | self.bf_off << 16 | self.bf_len << 24 | self.pid_stride << 32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    Data { addr: u64, size: u64 },
+    Const { value: u64, size: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Const { meta: Meta, value: u64 },
+    /// result of previous call or copyout, default if it did not execute
+    Result { meta: Meta, index: u64, div: u64, add: u64, default: u64 },
+    Data(Vec<u8>),
+    /// inet checksum over chunks
+    Csum { size: u64, chunks: Vec<Chunk> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Copyin { addr: u64, arg: Arg },
+    /// once preceding call finished, read size at addr as result index
+    Copyout { index: u64, addr: u64, size: u64 },
+    /// num is syzkaller id of syscall, copyout = result index of returned value
+    Call { num: u64, copyout: Option<u64>, args: Vec<Arg> },
+}
+
+struct Words<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Words<'a> {
+    fn next(&mut self) -> Result<u64, io::Error> {
+        match self.data.get(self.pos..self.pos + 8) {
+            Some(word) => {
+                self.pos += 8;
+                Ok(u64::from_le_bytes([word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7]]))
+            },
+            None => Err(invalid(format!("exec program truncated at {}", self.pos))),
+        }
+    }
+
+    fn bytes(&mut self, size: usize) -> Result<Vec<u8>, io::Error> {
+        let padded = (size + 7) & !7;
+        match self.data.get(self.pos..self.pos + padded) {
+            Some(data) => {
+                self.pos += padded;
+                Ok(data[..size].to_vec())
+            },
+            None => Err(invalid(format!("exec program data of size {} truncated at {}", size, self.pos))),
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, io::Error> {
+        Ok(match self.next()? {
+            ARG_CONST => Arg::Const { meta : Meta::decode(self.next()?), value : self.next()? },
+            ARG_RESULT => Arg::Result {
+                meta : Meta::decode(self.next()?),
+                index : self.next()?,
+                div : self.next()?,
+                add : self.next()?,
+                default : self.next()?,
+            },
+            ARG_DATA => {
+                let size = self.next()? & !DATA_READABLE;
+                if size as usize > MAX_INPUT {
+                    return Err(invalid(format!("exec program data of size {}", size)))
+                }
+                Arg::Data(self.bytes(size as usize)?)
+            },
+            ARG_CSUM => {
+                let size = self.next()?;
+                let kind = self.next()?;
+                if CSUM_INET != kind {
+                    return Err(invalid(format!("unknown csum kind {}", kind)))
+                }
+                let count = self.next()?;
+                let mut chunks = Vec::new();
+                for _ in 0..count {
+                    chunks.push(match (self.next()?, self.next()?, self.next()?) {
+                        (CHUNK_DATA, addr, size) => Chunk::Data { addr, size },
+                        (CHUNK_CONST, value, size) => Chunk::Const { value, size },
+                        (kind, _, _) => return Err(invalid(format!("unknown csum chunk kind {}", kind))),
+                    });
+                }
+                Arg::Csum { size, chunks }
+            },
+            kind => return Err(invalid(format!("unknown arg kind {} at {}", kind, self.pos - 8))),
+        })
+    }
+}
+
+/// exec encoding -> instructions, up to instr_eof
+pub fn decode(data: &[u8]) -> Result<Vec<Instr>, io::Error> {
+    let mut words = Words { data, pos : 0 };
+    let mut instrs = Vec::new();
+    loop {
+        instrs.push(match words.next()? {
+            INSTR_EOF => return Ok(instrs),
+            INSTR_COPYIN => Instr::Copyin { addr : words.next()?, arg : words.arg()? },
+            INSTR_COPYOUT => Instr::Copyout { index : words.next()?, addr : words.next()?, size : words.next()? },
+            num => {
+                let copyout = match words.next()? {
+                    NO_COPYOUT => None,
+                    index => Some(index),
+                };
+                let count = words.next()?;
+                let mut args = Vec::new();
+                for _ in 0..count {
+                    args.push(words.arg()?);
+                }
+                Instr::Call { num, copyout, args }
+            },
+        });
+    }
+}
+
+fn encode_arg(arg: &Arg, out: &mut Vec<u64>) {
+    match *arg {
+        Arg::Const { meta, value } => out.extend_from_slice(&[ARG_CONST, meta.encode(), value]),
+        Arg::Result { meta, index, div, add, default } => out.extend_from_slice(&[ARG_RESULT, meta.encode(), index, div, add, default]),
+        Arg::Data(ref data) => {
+            out.extend_from_slice(&[ARG_DATA, data.len() as u64]);
+            out.extend(data.chunks(8).map(|chunk| {
+                let mut word = [0u8; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            }));
+        },
+        Arg::Csum { size, ref chunks } => {
+            out.extend_from_slice(&[ARG_CSUM, size, CSUM_INET, chunks.len() as u64]);
+            for chunk in chunks.iter() {
+                out.extend_from_slice(&match *chunk {
+                    Chunk::Data { addr, size } => [CHUNK_DATA, addr, size],
+                    Chunk::Const { value, size } => [CHUNK_CONST, value, size],
+                });
+            }
+        },
+    }
+}
+
+/// instructions -> exec encoding, instr_eof appended
+pub fn encode(instrs: &[Instr]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for instr in instrs.iter() {
+        match *instr {
+            Instr::Copyin { addr, ref arg } => {
+                out.extend_from_slice(&[INSTR_COPYIN, addr]);
+                encode_arg(arg, &mut out);
+            },
+            Instr::Copyout { index, addr, size } => out.extend_from_slice(&[INSTR_COPYOUT, index, addr, size]),
+            Instr::Call { num, copyout, ref args } => {
+                out.extend_from_slice(&[num, copyout.unwrap_or(NO_COPYOUT), args.len() as u64]);
+                for arg in args.iter() {
+                    encode_arg(arg, &mut out);
+                }
+            },
+        }
+    }
+    out.push(INSTR_EOF);
+    out.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+}
+
+/// result of one call, as written to output shm
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct CallOutput {
+    /// position of call in program
+    pub index: u32,
+    pub num: u32,
+    pub errno: u32,
+    pub flags: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signal: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cover: Vec<u32>,
+}
+
+/// output shm : completed calls, then per call magic, index, num, errno, flags, sizes, signal, cover
+pub fn write_output(calls: &[CallOutput]) -> Vec<u8> {
+    let mut out = vec![calls.len() as u32];
+    for call in calls.iter() {
+        out.extend_from_slice(&[
+            OUT_MAGIC,
+            call.index,
+            call.num,
+            call.errno,
+            call.flags,
+            call.signal.len() as u32,
+            call.cover.len() as u32,
+            0]);
+        out.extend_from_slice(&call.signal);
+        out.extend_from_slice(&call.cover);
+    }
+    out.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+}
+
+pub fn read_output(data: &[u8]) -> Result<Vec<CallOutput>, io::Error> {
+    let words = data
+        .chunks(4)
+        .filter(|word| 4 == word.len())
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect::< Vec<u32> >();
+    let word = |ind: usize| words
+        .get(ind)
+        .cloned()
+        .ok_or_else(|| invalid(format!("output truncated at word {}", ind)));
+    let mut pos = 1;
+    let mut calls = Vec::new();
+    for _ in 0..word(0)? {
+        if OUT_MAGIC != word(pos)? {
+            return Err(invalid(format!("bad output magic at word {}", pos)))
+        }
+        let (signal, cover, comps) = (word(pos + 5)? as usize, word(pos + 6)? as usize, word(pos + 7)? as usize);
+        if 0 != comps {
+            return Err(invalid(String::from("comparisons in output are not supported")))
+        }
+        let start = pos + 8;
+        if start + signal + cover > words.len() {
+            return Err(invalid(format!("output truncated at word {}", start)))
+        }
+        calls.push(CallOutput {
+            index : word(pos + 1)?,
+            num : word(pos + 2)?,
+            errno : word(pos + 3)?,
+            flags : word(pos + 4)?,
+            signal : words[start..start + signal].to_vec(),
+            cover : words[start + signal..start + signal + cover].to_vec(),
+        });
+        pos = start + signal + cover;
+    }
+    Ok(calls)
+}
+
+/// syz program -> exec encoding, as syz-fuzzer would send it, layout taken from our call table
+///
+/// - for testing executor without manager, see syz-standin
+/// - memory is allocated from data_offset on, pointers are consts of its addresses
+/// - resource variables are results : returned one by call copyout, <rN=> by copyout instruction
+/// - shape of values follows our leafs, missing values are leaf defaults ( see seed::encode )
+pub struct Encoder<'a> {
+    table: &'a Description,
+    nums: HashMap<&'a str, u64>,
+    next: u64,
+    /// resource variable -> result index
+    vars: HashMap<String, u64>,
+    instrs: Vec<Instr>,
+    copyouts: Vec<Instr>,
+}
+
+impl<'a> Encoder<'a> {
+    /// names : syzkaller syscall names indexed by their id
+    pub fn new(table: &'a Description, names: &'a [String], data_offset: u64) -> Encoder<'a> {
+        Encoder {
+            table,
+            nums : names.iter().enumerate().map(|(num, name)| (name.as_str(), num as u64)).collect(),
+            next : data_offset,
+            vars : HashMap::new(),
+            instrs : Vec::new(),
+            copyouts : Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self, size: usize) -> u64 {
+        let addr = self.next;
+        self.next += ((size as u64).max(1) + 0x3f) & !0x3f;
+        addr
+    }
+
+    fn result(&mut self, var: &str, size: usize) -> Arg {
+        match self.vars.get(var) {
+            Some(&index) => Arg::Result { meta : Meta::new(size), index, div : 0, add : 0, default : !0 },
+            None => Arg::Const { meta : Meta::new(size), value : !0 },
+        }
+    }
+
+    fn define(&mut self, var: &str) -> u64 {
+        let index = self.vars.len() as u64;
+        self.vars.insert(var.to_string(), index);
+        index
+    }
+
+    fn copyin(&mut self, addr: u64, arg: Arg) {
+        self.instrs.push(Instr::Copyin { addr, arg });
+    }
+
+    /// writes value of leaf to memory at addr
+    fn place(&mut self, leaf: &Leaf, val: Option<&Value>, addr: u64, fd_size: usize) {
+        match (leaf, val) {
+            (Leaf::Ptr { to }, val) => {
+                let val = match val {
+                    Some(&Value::Ptr(Some(ref val))) => Some(&**val),
+                    _ => None,
+                };
+                let at = self.alloc(width(to, fd_size));
+                self.place(to, val, at, fd_size);
+                self.copyin(addr, Arg::Const { meta : Meta::new(8), value : at });
+            },
+            (&Leaf::Fd { size, .. }, Some(Value::Res(var))) |
+            (&Leaf::RndFd { size, .. }, Some(Value::Res(var))) |
+            (&Leaf::Deref { size, .. }, Some(Value::Res(var))) => {
+                let arg = self.result(var, size.unwrap_or(fd_size));
+                self.copyin(addr, arg);
+            },
+            (&Leaf::Fd { size, .. }, Some(Value::Out(var))) => {
+                let size = size.unwrap_or(fd_size);
+                self.copyin(addr, Arg::Const { meta : Meta::new(size), value : !0 });
+                let index = self.define(var);
+                self.copyouts.push(Instr::Copyout { index, addr, size : size as u64 });
+            },
+            (&Leaf::Array { count, ref of, .. }, Some(Value::Array(vals))) |
+            (&Leaf::Array { count, ref of, .. }, Some(Value::Group(vals))) => {
+                let size = width(of, fd_size) as u64;
+                for ind in 0..count {
+                    self.place(of, vals.get(ind), addr + ind as u64 * size, fd_size);
+                }
+            },
+            (Leaf::Struct { fields, .. }, Some(val @ Value::Group(_))) |
+            (Leaf::Struct { fields, .. }, Some(val @ Value::Union(..))) => {
+                let (data, _) = seed::encode(leaf, None, fd_size, &HashMap::new());
+                self.copyin(addr, Arg::Data(data));
+                let vals = match *val {
+                    Value::Group(ref vals) => vals.iter().collect(),
+                    Value::Union(_, Some(ref val)) => vec![&**val],
+                    _ => Vec::new(),
+                };
+                let mut end = 0;
+                for (ind, field) in fields.iter().enumerate() {
+                    let offset = field.offset.unwrap_or(end);
+                    end = offset + width(&field.leaf, fd_size);
+                    self.place(&field.leaf, vals.get(ind).cloned(), addr + offset as u64, fd_size);
+                }
+            },
+            (leaf, val) => {
+                let (data, _) = seed::encode(leaf, val, fd_size, &HashMap::new());
+                self.copyin(addr, Arg::Data(data));
+            },
+        }
+    }
+
+    /// top level argument, register sized
+    fn arg(&mut self, leaf: &Leaf, memory: bool, val: Option<&Value>, fd_size: usize) -> Arg {
+        let size = width(leaf, fd_size);
+        match (leaf, val) {
+            (_, val) if memory || size > 8 => {
+                let val = match val {
+                    Some(Value::Ptr(val)) => val.as_ref().map(|val| &**val),
+                    val => val,
+                };
+                let at = self.alloc(size);
+                self.place(leaf, val, at, fd_size);
+                Arg::Const { meta : Meta::new(8), value : at }
+            },
+            (Leaf::Ptr { to }, val) => {
+                let val = match val {
+                    Some(&Value::Ptr(Some(ref val))) => Some(&**val),
+                    _ => None,
+                };
+                let at = self.alloc(width(to, fd_size));
+                self.place(to, val, at, fd_size);
+                Arg::Const { meta : Meta::new(8), value : at }
+            },
+            (_, Some(Value::Res(var))) => self.result(var, size),
+            (_, val) => {
+                let (data, _) = seed::encode(leaf, val, fd_size, &HashMap::new());
+                let mut word = [0u8; 8];
+                word[..data.len()].copy_from_slice(&data);
+                Arg::Const { meta : Meta::new(size), value : u64::from_le_bytes(word) }
+            },
+        }
+    }
+
+    fn cdesc(&self, name: &str) -> Option<&'a CallDesc> {
+        self.table.call
+            .iter()
+            .find(|cdesc| cdesc.name.split('#').next() == Some(name))
+    }
+
+    /// fd_size of first state using call, as desc builds lone calls
+    fn fd_size(&self, cdesc: &CallDesc) -> usize {
+        self.table.state
+            .iter()
+            .find(|sdesc| sdesc.dtor == cdesc.name || sdesc.groups.iter().any(|group| group.contains(&cdesc.name)))
+            .map_or(4, |sdesc| sdesc.fd_size)
+    }
+
+    pub fn encode(&mut self, prog: &Prog) -> Result<Vec<Instr>, io::Error> {
+        self.vars.clear();
+        self.instrs.clear();
+        for call in prog.calls.iter() {
+            let num = match self.nums.get(call.name.as_str()) {
+                Some(&num) => num,
+                None => return Err(invalid(format!("{} is not syzkaller call", call.name))),
+            };
+            let cdesc = match self.cdesc(&call.name) {
+                Some(cdesc) => cdesc,
+                None => return Err(invalid(format!("{} is not in call table", call.name))),
+            };
+            let fd_size = self.fd_size(cdesc);
+            let skip = ("syscall" == cdesc.symbol) as usize;
+            let args = cdesc.args
+                .iter()
+                .skip(skip)
+                .enumerate()
+                .map(|(ind, adesc)| self.arg(&adesc.leaf, adesc.memory, call.args.get(ind), fd_size))
+                .collect();
+            let copyout = call.ret.as_ref().map(|var| self.define(var));
+            self.instrs.push(Instr::Call { num, copyout, args });
+            let copyouts = self.copyouts.split_off(0);
+            self.instrs.extend(copyouts);
+        }
+        Ok(self.instrs.split_off(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn le(words: &[u64]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+    }
+
+    /// copyin const + data + csum, call with copyout, copyout of pointer result
+    fn program() -> Vec<u8> {
+        le(&[
+            INSTR_COPYIN, 0x7f00_0000_0000, ARG_CONST, 0x0000_0004_0503_0104, 0x1234,
+            INSTR_COPYIN, 0x7f00_0000_0040, ARG_DATA, DATA_READABLE | 3, 0x00cc_bbaa,
+            INSTR_COPYIN, 0x7f00_0000_0080, ARG_CSUM, 2, CSUM_INET, 2,
+                CHUNK_DATA, 0x7f00_0000_0040, 3,
+                CHUNK_CONST, 0x11, 1,
+            41, 0, 2,
+                ARG_CONST, 8, 0x7f00_0000_0000,
+                ARG_RESULT, 4, 1, 0, 0, !0,
+            INSTR_COPYOUT, 1, 0x7f00_0000_0040, 4,
+            INSTR_EOF,
+        ])
+    }
+
+    #[test]
+    fn decode_exec_program() {
+        let meta = Meta { size : 4, format : FORMAT_BIGENDIAN, bf_off : 3, bf_len : 5, pid_stride : 4 };
+        assert_eq!(vec![
+            Instr::Copyin { addr : 0x7f00_0000_0000, arg : Arg::Const { meta, value : 0x1234 } },
+            Instr::Copyin { addr : 0x7f00_0000_0040, arg : Arg::Data(vec![0xaa, 0xbb, 0xcc]) },
+            Instr::Copyin { addr : 0x7f00_0000_0080, arg : Arg::Csum { size : 2, chunks : vec![
+                Chunk::Data { addr : 0x7f00_0000_0040, size : 3 },
+                Chunk::Const { value : 0x11, size : 1 },
+            ] } },
+            Instr::Call { num : 41, copyout : Some(0), args : vec![
+                Arg::Const { meta : Meta::new(8), value : 0x7f00_0000_0000 },
+                Arg::Result { meta : Meta::new(4), index : 1, div : 0, add : 0, default : !0 },
+            ] },
+            Instr::Copyout { index : 1, addr : 0x7f00_0000_0040, size : 4 },
+        ], decode(&program()).unwrap());
+    }
+
+    #[test]
+    fn encode_is_what_decode_eats() {
+        let instrs = decode(&program()).unwrap();
+        let mut expected = program();
+        // readable flag of data is not kept, executor does not need it
+        expected[8 * 8] = 3;
+        expected[8 * 8 + 7] = 0;
+        assert_eq!(expected, encode(&instrs));
+        assert_eq!(instrs, decode(&encode(&instrs)).unwrap());
+        assert_eq!(le(&[INSTR_EOF]), encode(&[]));
+    }
+
+    #[test]
+    fn decode_refuses_broken_program() {
+        let program = program();
+        for broken in [
+            program[..program.len() - 8].to_vec(),
+            program[..8 * 9].to_vec(),
+            le(&[INSTR_COPYIN, 0, 7, 0, 0, INSTR_EOF]),
+            le(&[INSTR_COPYIN, 0, ARG_CSUM, 2, 1, 0, INSTR_EOF]),
+            le(&[INSTR_COPYIN, 0, ARG_CSUM, 2, CSUM_INET, 1, 5, 0, 0, INSTR_EOF]),
+            le(&[INSTR_COPYIN, 0, ARG_DATA, MAX_INPUT as u64 + 1, INSTR_EOF]),
+        ] {
+            assert_eq!(io::ErrorKind::InvalidData, decode(&broken).unwrap_err().kind());
+        }
+    }
+
+    #[test]
+    fn output_encoding() {
+        let calls = vec![
+            CallOutput { index : 0, num : 41, errno : 0, flags : CALL_EXECUTED | CALL_FINISHED, signal : vec![0xa, 0xb], cover : vec![0xc] },
+            CallOutput { index : 1, num : 3, errno : 9, flags : CALL_EXECUTED, ..CallOutput::default() },
+        ];
+        let data = write_output(&calls);
+        assert_eq!(vec![
+            2, 0, 0, 0,
+            0x0d, 0xf0, 0xad, 0x0b, 0, 0, 0, 0, 41, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            0xa, 0, 0, 0, 0xb, 0, 0, 0, 0xc, 0, 0, 0,
+            0x0d, 0xf0, 0xad, 0x0b, 1, 0, 0, 0, 3, 0, 0, 0, 9, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ], data);
+        assert_eq!(calls, read_output(&data).unwrap());
+        assert!(read_output(&[0, 0, 0, 0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn output_refuses_broken_data() {
+        let data = write_output(&[CallOutput { signal : vec![1, 2], ..CallOutput::default() }]);
+        let mut magic = data.clone();
+        magic[4] = 0;
+        let mut comps = data.clone();
+        comps[4 * 8] = 1;
+        for broken in [data[..data.len() - 4].to_vec(), data[..8].to_vec(), magic, comps] {
+            assert_eq!(io::ErrorKind::InvalidData, read_output(&broken).unwrap_err().kind());
+        }
+    }
+
+    #[test]
+    fn control_pipe() {
+        let mut raw = Vec::new();
+        HandshakeReq { magic : IN_MAGIC, env_flags : ENV_SIGNAL, pid : 3 }.write(&mut raw).unwrap();
+        assert_eq!(le(&[IN_MAGIC, ENV_SIGNAL, 3]), raw);
+        let req = HandshakeReq::read(&mut raw.as_slice()).unwrap();
+        assert_eq!((IN_MAGIC, ENV_SIGNAL, 3), (req.magic, req.env_flags, req.pid));
+
+        let mut raw = Vec::new();
+        write_reply(&mut raw, None).unwrap();
+        write_reply(&mut raw, Some(7)).unwrap();
+        assert_eq!(vec![0x0d, 0xf0, 0xad, 0x0b, 0x0d, 0xf0, 0xad, 0x0b, 1, 0, 0, 0, 7, 0, 0, 0], raw);
+        let mut r = raw.as_slice();
+        assert_eq!(None, read_reply(&mut r, false).unwrap());
+        assert_eq!(Some(7), read_reply(&mut r, true).unwrap());
+        assert_eq!(io::ErrorKind::InvalidData, read_reply(&mut [0u8; 4].as_slice(), false).unwrap_err().kind());
+    }
+}
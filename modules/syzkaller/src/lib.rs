@@ -0,0 +1,129 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate toml;
+extern crate flate2;
+
+extern crate core;
+extern crate common;
+extern crate desc;
+extern crate generic;
+
+use std::io;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use core::banana::bananaq;
+use core::banana::observer::{ICallObserver, IStateObserver};
+use core::context;
+use core::exec::fd_info::Fd;
+use core::poc::replay;
+use core::state::id::StateTableId;
+
+use common::ModuleCallbacks;
+
+use desc::format::Description;
+
+/// syzkaller interop
+///
+/// - syzlang : parser of syzkaller descriptions ( .txt + .const )
+/// - import : syzlang -> call table of desc crate, 1:1 as far as our leafs allow
+/// - prog + corpus : syz programs, from corpus.db or text
+/// - seed : syz programs -> trace replayed by our states before they start fuzzing
+/// - export : recorded trace -> syz program, forwarding our findings to syzkaller
+/// - ipc + executor : syz-executor protocol, syz-manager drives our calls ( banana-executor )
+pub mod syzlang;
+pub mod import;
+pub mod prog;
+pub mod corpus;
+pub mod seed;
+pub mod export;
+pub mod ipc;
+pub mod executor;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SyzkallerConfig {
+    /// corpus.db of syz-manager, directory of programs ( syz-db unpack ) or text file of them
+    pub corpus: String,
+    /// desc call table fuzzer runs with, programs are mapped onto its calls + args
+    pub table: String,
+    /// seed at most this many programs, 0 = all
+    #[serde(default)]
+    pub limit: usize,
+    /// keep order of calls across states of program, ms to wait for call of other state; 0 = no order
+    #[serde(default)]
+    pub ordered_ms: u64,
+}
+
+/// map corpus onto call table and schedule it for replay
+///
+/// - returns states to be created, as poc::replay::schedule does
+/// - replayed states continue by regular fuzzing afterwards
+pub fn schedule(cfg: &SyzkallerConfig) -> Result<Vec<(StateTableId, Fd)>, io::Error> {
+    let table: Description = match toml::from_str(&generic::read_file(&cfg.table)?) {
+        Ok(table) => table,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} : {}", cfg.table, e))),
+    };
+    let (mut progs, warnings) = corpus::load(&cfg.corpus)?;
+    if 0 != cfg.limit {
+        progs.truncate(cfg.limit);
+    }
+
+    let mut seeder = seed::Seeder::new(&table);
+    for prog in progs.iter() {
+        seeder.prog(prog);
+    }
+    let seeded = seeder.seeded;
+    let (trace, more) = seeder.finish();
+    for warning in warnings.iter().chain(more.iter()) {
+        println!("[syzkaller] {}", warning);
+    }
+    println!("[syzkaller] seeded {} / {} programs, {} calls", seeded, progs.len(), trace.calls.len());
+
+    let ordered = match cfg.ordered_ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    };
+    Ok(replay::schedule(&trace, true, ordered))
+}
+
+static SEEDER: Once = Once::new();
+
+/// what module plugs into fuzzer, by plugs crate
+pub type Observers = (
+    Option<Box<dyn IStateObserver>>,
+    Option<Box<dyn ICallObserver>>,
+);
+
+/// seeds states trough push_state, once per process
+///
+/// - fuzzer push_state creates new state for invalid fd, which takes its replay plan at creation
+/// - no observers, module only feeds queue
+pub fn observers(
+    cfg: &Option<SyzkallerConfig>,
+    callbacks: Box<dyn ModuleCallbacks>,
+) -> Observers {
+    let cfg = match *cfg {
+        Some(ref cfg) => cfg.clone(),
+        None => return (None, None),
+    };
+    SEEDER.call_once(|| {
+        let spawns = match schedule(&cfg) {
+            Ok(spawns) => spawns,
+            Err(e) => return println!("[syzkaller] corpus {} not seeded : {}", cfg.corpus, e),
+        };
+        let push_state = callbacks.push_state();
+        let ctx = context::current();
+        bananaq::adopt(thread::spawn(move || {
+            context::enter(ctx);
+            for (id, fd) in spawns {
+                if bananaq::stopping() {
+                    break
+                }
+                push_state(id, &fd);
+            }
+        }));
+    });
+    (None, None)
+}
@@ -0,0 +1,517 @@
+use std::io;
+
+/// argument of call in syz program, as syzkaller serialize it
+///
+/// - 0x10, r0, <r1=>0x0, &(0x7f0000000000)={0x2, @loopback, "0a0b"}, [0x1, 0x2], AUTO, nil
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(u64),
+    /// use of resource variable, r0
+    Res(String),
+    /// resource variable written by kernel trough pointer, <r0=>0x0
+    Out(String),
+    /// pointed value, None for &(0x7f..) without it or for vma
+    Ptr(Option<Box<Value>>),
+    /// struct, {..}
+    Group(Vec<Value>),
+    /// array, [..]
+    Array(Vec<Value>),
+    /// @field=value
+    Union(String, Option<Box<Value>>),
+    /// "hex" or 'escaped' data, out buffers ( ""/0x10 ) are zeroed
+    Data(Vec<u8>),
+    /// len, csum, .. computed by syzkaller, we take ours
+    Auto,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProgCall {
+    /// r0 = ..
+    pub ret: Option<String>,
+    /// with variant, socket$inet
+    pub name: String,
+    pub args: Vec<Value>,
+    /// async, fail_nth: 1, ..
+    pub props: Vec<String>,
+}
+
+impl ProgCall {
+    /// resource variables defined by call, returned one and written trough pointers
+    pub fn defines(&self) -> Vec<String> {
+        let mut vars = self.ret.iter().cloned().collect::< Vec<String> >();
+        for arg in self.args.iter() {
+            arg.outs(&mut vars);
+        }
+        vars
+    }
+    /// resource variables used by call
+    pub fn uses(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        for arg in self.args.iter() {
+            arg.uses(&mut vars);
+        }
+        vars
+    }
+}
+
+impl Value {
+    pub fn outs(&self, vars: &mut Vec<String>) {
+        match *self {
+            Value::Out(ref var) => vars.push(var.clone()),
+            Value::Ptr(Some(ref val)) | Value::Union(_, Some(ref val)) => val.outs(vars),
+            Value::Group(ref vals) | Value::Array(ref vals) => vals.iter().for_each(|val| val.outs(vars)),
+            _ => (),
+        }
+    }
+    pub fn uses(&self, vars: &mut Vec<String>) {
+        match *self {
+            Value::Res(ref var) => vars.push(var.clone()),
+            Value::Ptr(Some(ref val)) | Value::Union(_, Some(ref val)) => val.uses(vars),
+            Value::Group(ref vals) | Value::Array(ref vals) => vals.iter().for_each(|val| val.uses(vars)),
+            _ => (),
+        }
+    }
+}
+
+/// one syz program, sequence of calls connected by resource variables
+#[derive(Debug, Clone, Default)]
+pub struct Prog {
+    pub calls: Vec<ProgCall>,
+}
+
+fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+fn hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|ind| u8::from_str_radix(text.get(ind..ind + 2)?, 16).ok())
+        .collect()
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(line: &'a str) -> Parser<'a> {
+        Parser { chars : line.chars().collect(), pos : 0, line }
+    }
+
+    fn error(&self, what: &str) -> io::Error {
+        invalid(format!("{} at {} of : {}", what, self.pos, self.line))
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if Some(c) != self.peek() {
+            return false
+        }
+        self.pos += 1;
+        true
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), io::Error> {
+        if self.eat(c) {
+            return Ok(())
+        }
+        Err(self.error(&format!("expected '{}'", c)))
+    }
+
+    fn ident(&mut self) -> String {
+        self.skip_ws();
+        let start = self.pos;
+        while self.pos < self.chars.len() && (self.chars[self.pos].is_ascii_alphanumeric() || "_$".contains(self.chars[self.pos])) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn int(&mut self) -> Result<u64, io::Error> {
+        let text = self.ident();
+        let val = match text.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => text.parse::<u64>(),
+        };
+        val.map_err(|_| self.error(&format!("bad number {}", text)))
+    }
+
+    /// skip ( .. ) with nesting, used for pointer address and call properties
+    fn skip_parens(&mut self) -> Result<(), io::Error> {
+        self.expect('(')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.chars.get(self.pos) {
+                Some('(') => depth += 1,
+                Some(')') => depth -= 1,
+                Some(_) => (),
+                None => return Err(self.error("unterminated (")),
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    /// "0a0b" is hex, 'abc\x00' is escaped text, both may be followed by /size for out buffers
+    fn data(&mut self) -> Result<Vec<u8>, io::Error> {
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+        let mut text = String::new();
+        let mut data = Vec::new();
+        loop {
+            let c = match self.chars.get(self.pos) {
+                Some(&c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            if c == quote {
+                break
+            }
+            if '"' == quote {
+                text.push(c);
+                continue
+            }
+            if '\\' != c {
+                let mut buf = [0; 4];
+                data.extend(c.encode_utf8(&mut buf).bytes());
+                continue
+            }
+            match self.chars.get(self.pos).cloned() {
+                Some('x') => {
+                    let esc = self.chars.get(self.pos + 1..self.pos + 3).map(|esc| esc.iter().collect::<String>());
+                    match esc.as_ref().and_then(|esc| hex(esc)) {
+                        Some(byte) => data.extend(byte),
+                        None => return Err(self.error("bad \\x escape")),
+                    }
+                    self.pos += 3;
+                },
+                Some(c) => {
+                    data.push(match c { 'n' => b'\n', 't' => b'\t', 'r' => b'\r', '0' => 0, c => c as u8 });
+                    self.pos += 1;
+                },
+                None => return Err(self.error("unterminated escape")),
+            }
+        }
+        if '"' == quote {
+            data = match hex(&text) {
+                Some(data) => data,
+                None => return Err(self.error("bad hex data")),
+            };
+        }
+        if self.eat('/') {
+            let size = self.int()? as usize;
+            data.resize(size, 0);
+        }
+        Ok(data)
+    }
+
+    fn group(&mut self, close: char) -> Result<Vec<Value>, io::Error> {
+        let mut vals = Vec::new();
+        if self.eat(close) {
+            return Ok(vals)
+        }
+        loop {
+            vals.push(self.value()?);
+            if self.eat(close) {
+                return Ok(vals)
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, io::Error> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.error("expected value")),
+        };
+        Ok(match c {
+            '&' => {
+                self.pos += 1;
+                if '(' == self.peek().unwrap_or(' ') {
+                    self.skip_parens()?;
+                } else {
+                    self.ident();//AUTO
+                }
+                if !self.eat('=') {
+                    return Ok(Value::Ptr(None))
+                }
+                let save = self.pos;
+                if "ANY" == self.ident() {
+                    // squashed, raw bytes of pointee
+                    self.expect('=')?;
+                    self.expect('[')?;
+                    let data = self.group(']')?
+                        .into_iter()
+                        .flat_map(|val| match val {
+                            Value::Data(data) => data,
+                            Value::Int(val) => val.to_le_bytes().to_vec(),
+                            _ => Vec::new(),
+                        })
+                        .collect();
+                    return Ok(Value::Ptr(Some(Box::new(Value::Data(data)))))
+                }
+                self.pos = save;
+                match self.value()? {
+                    Value::Auto => Value::Ptr(None),//nil
+                    val => Value::Ptr(Some(Box::new(val))),
+                }
+            },
+            '{' => { self.pos += 1; Value::Group(self.group('}')?) },
+            '[' => { self.pos += 1; Value::Array(self.group(']')?) },
+            '"' | '\'' | '`' => Value::Data(self.data()?),
+            '@' => {
+                self.pos += 1;
+                let field = self.ident();
+                let val = if self.eat('=') { Some(Box::new(self.value()?)) } else { None };
+                Value::Union(field, val)
+            },
+            '<' => {
+                self.pos += 1;
+                let var = self.ident();
+                self.expect('=')?;
+                self.expect('>')?;
+                self.value()?;
+                Value::Out(var)
+            },
+            _ => self.scalar()?,
+        })
+    }
+
+    /// number, resource variable, AUTO or nil
+    fn scalar(&mut self) -> Result<Value, io::Error> {
+        match self.peek() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                let save = self.pos;
+                let ident = self.ident();
+                match ident.as_str() {
+                    "AUTO" | "nil" => Ok(Value::Auto),
+                    var if var.starts_with('r') && var[1..].parse::<usize>().is_ok() => {
+                        // r0/1000+0x10 : proc arithmetic, we keep variable only
+                        while let Some(c) = self.peek() {
+                            if "/+".contains(c) { self.pos += 1; self.int()?; } else { break }
+                        }
+                        Ok(Value::Res(ident))
+                    },
+                    _ => {
+                        self.pos = save;
+                        Err(self.error(&format!("unexpected {}", ident)))
+                    },
+                }
+            },
+            Some(c) if c.is_ascii_digit() => Ok(Value::Int(self.int()?)),
+            _ => Err(self.error("expected value")),
+        }
+    }
+
+    fn call(&mut self) -> Result<ProgCall, io::Error> {
+        let mut name = self.ident();
+        let mut ret = None;
+        if self.eat('=') {
+            if name.is_empty() {
+                return Err(self.error("expected variable"))
+            }
+            ret = Some(name);
+            name = self.ident();
+        }
+        if name.is_empty() {
+            return Err(self.error("expected call"))
+        }
+        self.expect('(')?;
+        let args = self.group(')')?;
+        let mut props = Vec::new();
+        if Some('(') == self.peek() {
+            let start = self.pos + 1;
+            self.skip_parens()?;
+            props = self.chars[start..self.pos - 1]
+                .iter()
+                .collect::<String>()
+                .split(',')
+                .map(|prop| prop.trim().to_string())
+                .filter(|prop| !prop.is_empty())
+                .collect();
+        }
+        if self.peek().is_some() {
+            return Err(self.error("trailing data"))
+        }
+        Ok(ProgCall { ret, name, args, props })
+    }
+}
+
+impl Prog {
+    /// program in syzkaller text format, one call per line, # comments
+    pub fn parse(data: &str) -> Result<Prog, io::Error> {
+        let mut prog = Prog::default();
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            prog.calls.push(Parser::new(line).call()?);
+        }
+        Ok(prog)
+    }
+}
+
+/// syz programs start to place data here, as syz-executor maps it
+const DATA_OFFSET: u64 = 0x7f00_0000_0000;
+
+/// text of value, pointed data are placed one after another from DATA_OFFSET
+fn write(val: &Value, addr: &mut u64) -> String {
+    match *val {
+        Value::Int(val) => format!("0x{:x}", val),
+        Value::Res(ref var) => var.clone(),
+        Value::Out(ref var) => format!("<{}=>0x0", var),
+        Value::Ptr(None) => String::from("0x0"),
+        Value::Ptr(Some(ref val)) => {
+            let at = *addr;
+            *addr += (val.size() as u64).div_ceil(0x40).max(1) * 0x40;
+            format!("&(0x{:x})={}", at, write(val, addr))
+        },
+        Value::Group(ref vals) => format!("{{{}}}", vals.iter().map(|val| write(val, addr)).collect::< Vec<String> >().join(", ")),
+        Value::Array(ref vals) => format!("[{}]", vals.iter().map(|val| write(val, addr)).collect::< Vec<String> >().join(", ")),
+        Value::Union(ref field, None) => format!("@{}", field),
+        Value::Union(ref field, Some(ref val)) => format!("@{}={}", field, write(val, addr)),
+        Value::Data(ref data) => format!("\"{}\"", data.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
+        Value::Auto => String::from("AUTO"),
+    }
+}
+
+impl Value {
+    /// upper estimate of memory value takes, only to not overlap pointed data
+    pub fn size(&self) -> usize {
+        match *self {
+            Value::Data(ref data) => data.len(),
+            Value::Group(ref vals) | Value::Array(ref vals) => vals.iter().map(|val| val.size()).sum(),
+            Value::Union(_, Some(ref val)) => val.size(),
+            _ => 8,
+        }
+    }
+}
+
+impl Prog {
+    /// program in syzkaller text format, as syz-prog2c / syz-execprog eat it
+    pub fn serialize(&self) -> String {
+        let mut addr = DATA_OFFSET;
+        let mut text = String::new();
+        for call in self.calls.iter() {
+            if let Some(ref ret) = call.ret {
+                text += &format!("{} = ", ret);
+            }
+            let args = call.args
+                .iter()
+                .map(|arg| write(arg, &mut addr))
+                .collect::< Vec<String> >();
+            text += &format!("{}({})", call.name, args.join(", "));
+            if !call.props.is_empty() {
+                text += &format!(" ({})", call.props.join(", "));
+            }
+            text += "\n";
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROG: &str = "# comment
+r0 = socket$inet_tcp(0x2, 0x1, 0x0)
+bind$inet(r0, &(0x7f0000000000)={0x2, 0x4e20, @loopback}, 0x10)
+pipe(&(0x7f0000000040)={<r1=>0xffffffffffffffff, <r2=>0xffffffffffffffff})
+write(r1, &(0x7f0000000080)=\"0a0b\", 0x2) (async, fail_nth: 1)
+setsockopt(r0, 0x6, 0x1, &(0x7f00000000c0)='ab\\x00\\n', AUTO)
+read(r2, &(0x7f0000000100)=\"\"/4, 0x4)
+ioctl(r0/1000+0x10, 0x0, &AUTO=ANY=[\"0102\", 0x3], nil, &(0x7f0000000140))
+";
+
+    #[test]
+    fn text_to_calls() {
+        let prog = Prog::parse(PROG).unwrap();
+        let names = prog.calls.iter().map(|call| call.name.as_str()).collect::< Vec<&str> >();
+        assert_eq!(vec!["socket$inet_tcp", "bind$inet", "pipe", "write", "setsockopt", "read", "ioctl"], names);
+
+        assert_eq!(Some(String::from("r0")), prog.calls[0].ret);
+        assert_eq!(vec![Value::Int(2), Value::Int(1), Value::Int(0)], prog.calls[0].args);
+
+        assert_eq!(vec![
+            Value::Res(String::from("r0")),
+            Value::Ptr(Some(Box::new(Value::Group(vec![
+                Value::Int(2),
+                Value::Int(0x4e20),
+                Value::Union(String::from("loopback"), None)])))),
+            Value::Int(0x10),
+        ], prog.calls[1].args);
+
+        assert_eq!(vec![String::from("r1"), String::from("r2")], prog.calls[2].defines());
+        assert!(prog.calls[2].uses().is_empty());
+
+        assert_eq!(Value::Ptr(Some(Box::new(Value::Data(vec![0x0a, 0x0b])))), prog.calls[3].args[1]);
+        assert_eq!(vec![String::from("async"), String::from("fail_nth: 1")], prog.calls[3].props);
+
+        assert_eq!(Value::Ptr(Some(Box::new(Value::Data(b"ab\x00\n".to_vec())))), prog.calls[4].args[3]);
+        assert_eq!(Value::Auto, prog.calls[4].args[4]);
+
+        assert_eq!(Value::Ptr(Some(Box::new(Value::Data(vec![0; 4])))), prog.calls[5].args[1]);
+        assert_eq!(vec![String::from("r2")], prog.calls[5].uses());
+
+        let ioctl = &prog.calls[6];
+        assert_eq!(Value::Res(String::from("r0")), ioctl.args[0]);
+        let mut squashed = vec![1, 2];
+        squashed.extend(3u64.to_le_bytes());
+        assert_eq!(Value::Ptr(Some(Box::new(Value::Data(squashed)))), ioctl.args[2]);
+        assert_eq!(Value::Auto, ioctl.args[3]);
+        assert_eq!(Value::Ptr(None), ioctl.args[4]);
+    }
+
+    #[test]
+    fn serialize_parses_back() {
+        let prog = Prog::parse(PROG).unwrap();
+        let again = Prog::parse(&prog.serialize()).unwrap();
+        assert_eq!(prog.calls.len(), again.calls.len());
+        for (call, again) in prog.calls.iter().zip(again.calls.iter()) {
+            assert_eq!(call.ret, again.ret);
+            assert_eq!(call.name, again.name);
+            assert_eq!(call.props, again.props);
+            assert_eq!(call.defines(), again.defines());
+            assert_eq!(call.uses(), again.uses());
+        }
+        assert_eq!(prog.calls[1].args, again.calls[1].args);
+        assert_eq!(prog.calls[3].args, again.calls[3].args);
+        assert!(prog.serialize().contains("&(0x7f0000000000)={0x2, 0x4e20, @loopback}"));
+    }
+
+    #[test]
+    fn malformed_is_refused() {
+        for line in [
+            "socket(0x2",
+            "socket(0x2) trailing",
+            "write(r0, \"0a0\", 0x1)",
+            "write(r0, 'ab\\xZZ', 0x1)",
+            "write(r0, \"0a0b, 0x1)",
+            "call(0xZZ)",
+            "call(bogus)",
+            "= call()",
+            "call(&(0x7f0000000000, 0x1)",
+        ] {
+            let err = Prog::parse(line).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use core::exec::id::CallTableId;
+use core::poc::trace::{ArgRecord, CallRecord, Trace, TraceHeader, TRACE_VERSION};
+use core::state::id::StateTableId;
+
+use desc::format::{ArgDesc, CallDesc, Description, Leaf, StateDesc};
+
+use prog::{Prog, ProgCall, Value};
+
+/// recorded fd we make up for resource variable of program
+///
+/// - live one is learned ( poc::replay::learn ) once state owning variable replays its next call
+/// - never all 00 / FF, as those are invalid fds for core
+/// - unique per thread of trace, programs are replayed in parallel
+fn fake_fd(tid: u64, size: usize) -> Vec<u8> {
+    fit((0x5eed_0000u64 | (tid + 1)).to_le_bytes().to_vec(), size)
+}
+
+fn fit(mut data: Vec<u8>, size: usize) -> Vec<u8> {
+    data.resize(size, 0);
+    data
+}
+
+fn int(value: u64, size: usize) -> Vec<u8> {
+    fit(value.to_le_bytes().to_vec(), size)
+}
+
+fn wide(text: &[u8]) -> Vec<u8> {
+    text.iter().flat_map(|&c| vec![c, 0]).collect()
+}
+
+/// one state of program, fed by calls over its resource variable
+struct Thread<'a> {
+    tid: u64,
+    state: &'a StateDesc,
+    var: String,
+    closed: bool,
+}
+
+/// syz programs -> trace of calls of our call table, to be replayed trough poc::replay
+///
+/// - resource variable returned by ctor of our state ( r0 = socket$inet(..) ) starts new thread of trace
+///     - call producing resource used later by program is taken as ctor of its state, even if it
+///       is worker of other state too ( accept4 ), to not lose rest of program
+/// - calls over that variable are workers of its state, matched by deref arg
+/// - dtor of state is dropped, state must live on for regular fuzzing after replay
+/// - arguments are encoded by our leafs, values of syz program fill them where shape matches,
+///   defaults of leaf elsewhere
+/// - big endian ints ( int16be ) are taken as they are, our leafs do not know endianness
+pub struct Seeder<'a> {
+    desc: &'a Description,
+    trace: Trace,
+    /// resource variable -> made up fd, per program
+    fds: HashMap<String, Vec<u8>>,
+    warnings: Vec<String>,
+    unknown: Vec<String>,
+    tids: u64,
+    /// programs mapped at least partially
+    pub seeded: usize,
+}
+
+impl<'a> Seeder<'a> {
+    pub fn new(desc: &'a Description) -> Seeder<'a> {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        Seeder {
+            desc,
+            trace : Trace::new(TraceHeader { version : TRACE_VERSION, created, seed : 0 }),
+            fds : HashMap::new(),
+            warnings : Vec::new(),
+            unknown : Vec::new(),
+            tids : 0,
+            seeded : 0,
+        }
+    }
+
+    /// trace for poc::replay::schedule + what did not map
+    pub fn finish(mut self) -> (Trace, Vec<String>) {
+        if !self.unknown.is_empty() {
+            self.warnings.push(format!("calls not in call table : {}", self.unknown.join(", ")));
+        }
+        (self.trace, self.warnings)
+    }
+
+    fn candidates(&self, name: &str) -> Vec<&'a CallDesc> {
+        let variant = format!("{}#", name);
+        self.desc.call
+            .iter()
+            .filter(|cdesc| cdesc.name == name || cdesc.name.starts_with(&variant))
+            .collect()
+    }
+
+    /// syscall symbol has NR as first arg, syz program does not
+    fn skip(cdesc: &CallDesc) -> usize {
+        ("syscall" == cdesc.symbol) as usize
+    }
+
+    fn slot(sdesc: &StateDesc, name: &str) -> Option<(usize, usize)> {
+        sdesc.groups
+            .iter()
+            .enumerate()
+            .filter_map(|(level, group)| group
+                .iter()
+                .position(|call| call == name)
+                .map(|index| (level, index)))
+            .next()
+    }
+
+    /// variable call creates state for : returned one or written to fd_arg
+    fn produced(call: &ProgCall, cdesc: &CallDesc) -> Option<String> {
+        if cdesc.fd_ret {
+            return call.ret.clone()
+        }
+        let ind = cdesc.fd_arg?.checked_sub(Seeder::skip(cdesc))?;
+        let mut outs = Vec::new();
+        call.args.get(ind)?.outs(&mut outs);
+        outs.into_iter().next()
+    }
+
+    fn ctor(&self, call: &ProgCall, later: &[String]) -> Option<(&'a CallDesc, &'a StateDesc, String)> {
+        for cdesc in self.candidates(&call.name) {
+            let var = match Seeder::produced(call, cdesc) {
+                Some(ref var) if later.contains(var) => var.clone(),
+                _ => continue,
+            };
+            let sdesc = self.desc.state
+                .iter()
+                .filter_map(|sdesc| Seeder::slot(sdesc, &cdesc.name).map(|slot| (slot, sdesc)))
+                .min_by_key(|&(slot, _)| slot)
+                .map(|(_, sdesc)| sdesc);
+            if let Some(sdesc) = sdesc {
+                return Some((cdesc, sdesc, var))
+            }
+        }
+        None
+    }
+
+    /// call over variable of alive thread, matched by deref arg of call in its state
+    fn worker(&self, call: &ProgCall, threads: &[Thread]) -> Option<(&'a CallDesc, usize)> {
+        for cdesc in self.candidates(&call.name) {
+            let ind = match cdesc.args.iter().position(|arg| matches!(arg.leaf, Leaf::Deref { .. })) {
+                Some(ind) => ind,
+                None => continue,
+            };
+            let var = match ind.checked_sub(Seeder::skip(cdesc)).and_then(|ind| call.args.get(ind)) {
+                Some(Value::Res(var)) => var,
+                _ => continue,
+            };
+            let thread = threads
+                .iter()
+                .position(|thread| !thread.closed &&
+                          thread.var == *var &&
+                          (thread.state.dtor == cdesc.name || Seeder::slot(thread.state, &cdesc.name).is_some()));
+            if let Some(thread) = thread {
+                return Some((cdesc, thread))
+            }
+        }
+        None
+    }
+
+    fn record(&mut self, thread: &Thread, fd: Vec<u8>, call: &ProgCall, cdesc: &CallDesc) {
+        let (level, index) = Seeder::slot(thread.state, &cdesc.name).unwrap_or((!0, !0));
+        let skip = Seeder::skip(cdesc);
+        let fd_size = thread.state.fd_size;
+        let args = cdesc.args
+            .iter()
+            .enumerate()
+            .map(|(ind, arg)| self.arg(arg, ind.checked_sub(skip).and_then(|ind| call.args.get(ind)), fd_size))
+            .collect();
+        let seq = self.trace.calls.len() as u64;
+        self.trace.calls.push(CallRecord {
+            seq,
+            tid : thread.tid,
+            stream : 0,
+            state : thread.state.name.clone(),
+            sid : StateTableId::Id(thread.state.id),
+            fd,
+            level,
+            index,
+            cid : CallTableId::Id(cdesc.id),
+            call : cdesc.name.clone(),
+            args,
+            poc : String::new(),
+        });
+    }
+
+    /// add calls of program to trace
+    pub fn prog(&mut self, prog: &Prog) {
+        self.fds.clear();
+        let mut threads: Vec<Thread> = Vec::new();
+        let mut mapped = 0;
+        for (ind, call) in prog.calls.iter().enumerate() {
+            let later = prog.calls[ind + 1..]
+                .iter()
+                .flat_map(|call| call.uses())
+                .collect::< Vec<String> >();
+
+            if let Some((cdesc, sdesc, var)) = self.ctor(call, &later) {
+                let tid = self.tids;
+                self.tids += 1;
+                let fd = fake_fd(tid, sdesc.fd_size);
+                self.fds.insert(var.clone(), fd);
+                let thread = Thread { tid, state : sdesc, var, closed : false };
+                self.record(&thread, Vec::new(), call, cdesc);
+                threads.push(thread);
+                mapped += 1;
+                continue
+            }
+
+            match self.worker(call, &threads) {
+                Some((cdesc, ind)) if threads[ind].state.dtor == cdesc.name => threads[ind].closed = true,
+                Some((cdesc, ind)) => {
+                    let fd = self.fds.get(&threads[ind].var).cloned().unwrap_or_default();
+                    self.record(&threads[ind], fd, call, cdesc);
+                    mapped += 1;
+                },
+                None if self.candidates(&call.name).is_empty()
+                    && !self.unknown.contains(&call.name) => {
+                        self.unknown.push(call.name.clone());
+                    },
+                None => (),//over variable of state we did not map
+            }
+        }
+        if 0 != mapped {
+            self.seeded += 1;
+        }
+    }
+
+    fn arg(&self, desc: &ArgDesc, val: Option<&Value>, fd_size: usize) -> ArgRecord {
+        // memory arg is pointer at syz side, but we load its memory directly
+        let val = match (desc.memory, val) {
+            (true, Some(Value::Ptr(val))) => val.as_ref().map(|val| &**val),
+            (_, val) => val,
+        };
+        let (data, extra) = encode(&desc.leaf, val, fd_size, &self.fds);
+        ArgRecord { data, extra }
+    }
+}
+
+/// data of leaf + its extra ( see IArgLeaf::dump ), as if recorded by poclog
+///
+/// - fds : resource variable -> fd its fd leafs are filled with
+pub fn encode(leaf: &Leaf, val: Option<&Value>, fd_size: usize, fds: &HashMap<String, Vec<u8>>) -> (Vec<u8>, Vec<u8>) {
+    let data = match (leaf, val) {
+        (&Leaf::Const { size, .. }, Some(&Value::Int(val))) |
+        (&Leaf::Flag { size, .. }, Some(&Value::Int(val))) |
+        (&Leaf::Bounded { size, .. }, Some(&Value::Int(val))) |
+        (&Leaf::Rnd { size }, Some(&Value::Int(val))) |
+        (&Leaf::Pattern { size, .. }, Some(&Value::Int(val))) => int(val, size),
+        (&Leaf::Rnd { size }, Some(&Value::Data(ref data))) |
+        (&Leaf::Pattern { size, .. }, Some(&Value::Data(ref data))) => fit(data.clone(), size),
+
+        (&Leaf::Const { size, value }, _) => int(value as u64, size),
+        (&Leaf::Flag { size, always, .. }, _) => int(always as u64, size),
+        (&Leaf::Bounded { size, ref ranges, .. }, _) => int(ranges.first().map_or(0, |range| range[0]) as u64, size),
+        (&Leaf::Rnd { size }, _) | (&Leaf::Phantom { size }, _) => vec![0; size],
+        (&Leaf::Pattern { byte, size }, _) => vec![byte; size],
+        (Leaf::Bytes { data }, Some(Value::Data(val))) => fit(val.clone(), data.len()),
+        (Leaf::Bytes { data }, _) => data.clone(),
+
+        (Leaf::Ptr { to }, val) => {
+            let val = match val {
+                Some(&Value::Ptr(Some(ref val))) => Some(&**val),
+                _ => None,
+            };
+            let (data, extra) = encode(to, val, fd_size, fds);
+            return (vec![0; mem::size_of::<usize>()], [data, extra].concat())
+        },
+        // live fd of state is used at replay, whatever we put there
+        (&Leaf::Deref { size, .. }, _) => vec![0; size.unwrap_or(fd_size)],
+        (&Leaf::Fd { size, .. }, val) | (&Leaf::RndFd { size, .. }, val) => {
+            let size = size.unwrap_or(fd_size);
+            match val {
+                Some(&Value::Res(ref var)) | Some(&Value::Out(ref var)) => match fds.get(var) {
+                    Some(fd) => fit(fd.clone(), size),
+                    None => vec![0xFF; size],
+                },
+                Some(&Value::Int(val)) => int(val, size),
+                _ => vec![0xFF; size],
+            }
+        },
+
+        (&Leaf::Astr { ref prefix, count, nz }, val) => {
+            let size = prefix.len() + count + nz.map_or(1, |_| 0);
+            match val {
+                Some(Value::Data(data)) => fit(data.clone(), size),
+                _ => fit([prefix.as_bytes(), &vec![b'a'; count]].concat(), size),
+            }
+        },
+        (&Leaf::Wstr { ref prefix, count, nz }, val) => {
+            let size = 2 * (prefix.len() + count + if nz { 0 } else { 1 });
+            match val {
+                Some(Value::Data(data)) => fit(wide(data), size),
+                _ => fit(wide(&[prefix.as_bytes(), &vec![b'a'; count]].concat()), size),
+            }
+        },
+
+        (&Leaf::Array { count, ref of, .. }, val) => {
+            let vals = match val {
+                Some(Value::Array(vals)) | Some(Value::Group(vals)) => vals.iter().collect(),
+                _ => Vec::new(),
+            };
+            let (data, extra): (Vec<Vec<u8>>, Vec<Vec<u8>>) = (0..count)
+                .map(|ind| encode(of, vals.get(ind).cloned(), fd_size, fds))
+                .unzip();
+            let data = data.concat();
+            return match val {
+                // int8 arrays are serialized as strings by syzkaller
+                Some(Value::Data(raw)) => (fit(raw.clone(), data.len()), extra.concat()),
+                _ => (data, extra.concat()),
+            }
+        },
+        (&Leaf::Struct { size, ref fields, .. }, val) => {
+            let vals = match val {
+                Some(Value::Group(vals)) => vals.iter().collect(),
+                // our union is its first field sized to the biggest one
+                Some(&Value::Union(_, Some(ref val))) => vec![&**val],
+                _ => Vec::new(),
+            };
+            let mut end = 0;
+            let mut placed = Vec::new();
+            let mut extra = Vec::new();
+            for (ind, field) in fields.iter().enumerate() {
+                let offset = field.offset.unwrap_or(end);
+                let (data, more) = encode(&field.leaf, vals.get(ind).cloned(), fd_size, fds);
+                end = offset + data.len();
+                placed.push((offset, data));
+                extra.extend(more);
+            }
+            let max = placed.iter().map(|&(offset, ref data)| offset + data.len()).max().unwrap_or(0);
+            let mut data = vec![0; size.unwrap_or(max).max(max)];
+            if let Some(Value::Data(raw)) = val {
+                return (fit(raw.clone(), data.len()), extra)
+            }
+            for (offset, field) in placed {
+                data[offset..offset + field.len()].copy_from_slice(&field);
+            }
+            return (data, extra)
+        },
+    };
+    (data, Vec::new())
+}
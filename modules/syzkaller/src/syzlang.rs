@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+use std::io;
+
+/// type expression of syzlang, name[args..] or its arguments
+///
+/// - int32[0:10], ptr[in, array[int8]], const[AF_INET, int16], string["abc"], ..
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Ident(String, Vec<Ty>),
+    Int(i64),
+    Range(i64, i64),
+    Str(String),
+    /// bitfield, int32:3
+    Bits(Box<Ty>, usize),
+}
+
+impl Ty {
+    pub fn name(&self) -> &str {
+        match *self {
+            Ty::Ident(ref name, _) => name,
+            _ => "",
+        }
+    }
+    pub fn args(&self) -> &[Ty] {
+        match *self {
+            Ty::Ident(_, ref args) => args,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Syscall {
+    /// with variant, socket$inet
+    pub name: String,
+    pub args: Vec<(String, Ty)>,
+    pub ret: Option<Ty>,
+    pub attrs: Vec<Ty>,
+}
+
+impl Syscall {
+    /// socket$inet -> socket
+    pub fn base(&self) -> &str {
+        self.name.split('$').next().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<(String, Ty)>,
+    pub union: bool,
+    /// packed, size[N], align[N], varlen
+    pub attrs: Vec<Ty>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Resource {
+    pub name: String,
+    pub base: Ty,
+    /// special values ( -1, AT_FDCWD, .. )
+    pub values: Vec<Ty>,
+}
+
+/// all we know from .txt descriptions + .const files
+#[derive(Debug, Default)]
+pub struct Syzlang {
+    pub syscalls: Vec<Syscall>,
+    pub structs: HashMap<String, Struct>,
+    pub resources: Vec<Resource>,
+    /// int flags, or string flags
+    pub flags: HashMap<String, Vec<Ty>>,
+    /// type alias = type
+    pub types: HashMap<String, Ty>,
+    /// templates are not supported, only remembered to report them
+    pub templates: Vec<String>,
+    pub consts: HashMap<String, i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    Punct(char),
+    Newline,
+}
+
+fn invalid(what: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+fn int(text: &str) -> Option<i64> {
+    let (neg, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let val = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()? as i64
+    } else {
+        text.parse::<u64>().ok()? as i64
+    };
+    Some(if neg { val.wrapping_neg() } else { val })
+}
+
+fn ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_$.?".contains(c)
+}
+
+fn lex(data: &str) -> Result<Vec<(usize, Tok)>, io::Error> {
+    let mut toks = Vec::new();
+    for (line, text) in data.lines().enumerate() {
+        let line = line + 1;
+        let trimmed = text.trim_start();
+        if ["include ", "incdir ", "meta ", "define "].iter().any(|kw| trimmed.starts_with(kw)) {
+            toks.push((line, Tok::Newline));
+            continue
+        }
+        let chars = text.chars().collect::< Vec<char> >();
+        let mut ind = 0;
+        while ind < chars.len() {
+            let c = chars[ind];
+            if '#' == c {
+                break
+            }
+            if c.is_whitespace() {
+                ind += 1;
+                continue
+            }
+            if '"' == c || '`' == c {
+                let end = match chars[ind + 1..].iter().position(|&e| e == c) {
+                    Some(end) => ind + 1 + end,
+                    None => return Err(invalid(format!("line {} : unterminated string", line))),
+                };
+                toks.push((line, Tok::Str(chars[ind + 1..end].iter().collect())));
+                ind = end + 1;
+                continue
+            }
+            if '\'' == c && ind + 2 < chars.len() && '\'' == chars[ind + 2] {
+                toks.push((line, Tok::Int(chars[ind + 1] as i64)));
+                ind += 3;
+                continue
+            }
+            if c.is_ascii_digit() || ('-' == c && ind + 1 < chars.len() && chars[ind + 1].is_ascii_digit()) {
+                let end = ind + 1 + chars[ind + 1..].iter().take_while(|&&c| c.is_ascii_alphanumeric()).count();
+                let text = chars[ind..end].iter().collect::<String>();
+                match int(&text) {
+                    Some(val) => toks.push((line, Tok::Int(val))),
+                    None => return Err(invalid(format!("line {} : bad number {}", line, text))),
+                }
+                ind = end;
+                continue
+            }
+            if ident_char(c) {
+                let end = ind + chars[ind..].iter().take_while(|&&c| ident_char(c)).count();
+                toks.push((line, Tok::Ident(chars[ind..end].iter().collect())));
+                ind = end;
+                continue
+            }
+            toks.push((line, Tok::Punct(c)));
+            ind += 1;
+        }
+        toks.push((line, Tok::Newline));
+    }
+    Ok(toks)
+}
+
+struct Parser {
+    toks: Vec<(usize, Tok)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos).map(|(_, tok)| tok)
+    }
+    fn peek_at(&self, off: usize) -> Option<&Tok> {
+        self.toks.get(self.pos + off).map(|(_, tok)| tok)
+    }
+    fn line(&self) -> usize {
+        self.toks.get(self.pos).or_else(|| self.toks.last()).map_or(0, |&(line, _)| line)
+    }
+    fn next(&mut self) -> Option<Tok> {
+        let tok = self.toks.get(self.pos).map(|(_, tok)| tok.clone());
+        self.pos += 1;
+        tok
+    }
+    fn error(&self, what: &str) -> io::Error {
+        invalid(format!("line {} : {}, found {:?}", self.line(), what, self.peek()))
+    }
+    fn punct(&mut self, c: char) -> bool {
+        if Some(&Tok::Punct(c)) == self.peek() {
+            self.pos += 1;
+            return true
+        }
+        false
+    }
+    fn expect(&mut self, c: char) -> Result<(), io::Error> {
+        if self.punct(c) {
+            return Ok(())
+        }
+        Err(self.error(&format!("expected '{}'", c)))
+    }
+    fn ident(&mut self) -> Result<String, io::Error> {
+        match self.peek() {
+            Some(&Tok::Ident(_)) => match self.next() {
+                Some(Tok::Ident(name)) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => Err(self.error("expected identifier")),
+        }
+    }
+    fn newlines(&mut self) {
+        while Some(&Tok::Newline) == self.peek() {
+            self.pos += 1;
+        }
+    }
+    fn skip_line(&mut self) {
+        while let Some(tok) = self.next() {
+            if Tok::Newline == tok {
+                break
+            }
+        }
+    }
+
+    /// int32[0:10], array[int8, 4], "lit", -1, ident:bits
+    fn ty(&mut self) -> Result<Ty, io::Error> {
+        match self.next() {
+            Some(Tok::Int(lo)) => {
+                if self.punct(':') {
+                    return match self.next() {
+                        Some(Tok::Int(hi)) => Ok(Ty::Range(lo, hi)),
+                        _ => Err(self.error("expected end of range")),
+                    }
+                }
+                Ok(Ty::Int(lo))
+            },
+            Some(Tok::Str(text)) => Ok(Ty::Str(text)),
+            Some(Tok::Ident(name)) => {
+                let mut args = Vec::new();
+                if self.punct('[') {
+                    loop {
+                        self.newlines();
+                        if self.punct(']') {
+                            break
+                        }
+                        args.push(self.ty()?);
+                        self.newlines();
+                        if !self.punct(',') {
+                            self.expect(']')?;
+                            break
+                        }
+                    }
+                }
+                if Some(&Tok::Punct(':')) == self.peek() {
+                    if let Some(&Tok::Int(bits)) = self.peek_at(1) {
+                        self.pos += 2;
+                        return Ok(Ty::Bits(Box::new(Ty::Ident(name, args)), bits as usize))
+                    }
+                }
+                Ok(Ty::Ident(name, args))
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected type"))
+            },
+        }
+    }
+
+    /// (in), (out), (if[..]), .. we dont care
+    fn skip_parens(&mut self) {
+        if Some(&Tok::Punct('(')) != self.peek() {
+            return
+        }
+        let mut depth = 0;
+        while let Some(tok) = self.peek().cloned() {
+            match tok {
+                Tok::Punct('(') => depth += 1,
+                Tok::Punct(')') => depth -= 1,
+                Tok::Newline => return,
+                _ => {},
+            }
+            self.pos += 1;
+            if 0 == depth {
+                return
+            }
+        }
+    }
+
+    fn attrs(&mut self) -> Result<Vec<Ty>, io::Error> {
+        let mut attrs = Vec::new();
+        if self.punct('[') {
+            loop {
+                if self.punct(']') {
+                    break
+                }
+                attrs.push(self.ty()?);
+                if !self.punct(',') {
+                    self.expect(']')?;
+                    break
+                }
+            }
+        }
+        Ok(attrs)
+    }
+
+    /// name(a type, b type) ret (attrs)
+    fn syscall(&mut self, name: String) -> Result<Syscall, io::Error> {
+        self.expect('(')?;
+        let mut args = Vec::new();
+        loop {
+            if self.punct(')') {
+                break
+            }
+            let arg = self.ident()?;
+            args.push((arg, self.ty()?));
+            if !self.punct(',') {
+                self.expect(')')?;
+                break
+            }
+        }
+        let mut ret = None;
+        if let Some(&Tok::Ident(_)) = self.peek() {
+            ret = Some(self.ty()?);
+        }
+        let mut attrs = Vec::new();
+        if self.punct('(') {
+            loop {
+                if self.punct(')') {
+                    break
+                }
+                attrs.push(self.ty()?);
+                if !self.punct(',') {
+                    self.expect(')')?;
+                    break
+                }
+            }
+        }
+        Ok(Syscall { name, args, ret, attrs })
+    }
+
+    /// name { fields } [attrs], or name [ fields ] [attrs] for union
+    fn structure(&mut self, name: String, union: bool) -> Result<Struct, io::Error> {
+        let close = if union { ']' } else { '}' };
+        let mut fields = Vec::new();
+        loop {
+            self.newlines();
+            if self.punct(close) {
+                break
+            }
+            let field = self.ident()?;
+            let ty = self.ty()?;
+            self.skip_parens();
+            fields.push((field, ty));
+            if Some(&Tok::Newline) != self.peek() && Some(&Tok::Punct(close)) != self.peek() {
+                return Err(self.error("expected end of field"))
+            }
+        }
+        let attrs = self.attrs()?;
+        Ok(Struct { name, fields, union, attrs })
+    }
+
+    fn list(&mut self) -> Result<Vec<Ty>, io::Error> {
+        let mut values = Vec::new();
+        loop {
+            if let None | Some(&Tok::Newline) = self.peek() {
+                break
+            }
+            values.push(self.ty()?);
+            if !self.punct(',') {
+                break
+            }
+            self.newlines();
+        }
+        Ok(values)
+    }
+
+    fn statement(&mut self, syz: &mut Syzlang) -> Result<(), io::Error> {
+        let name = self.ident()?;
+        match (name.as_str(), self.peek().cloned()) {
+            ("resource", _) => {
+                let name = self.ident()?;
+                self.expect('[')?;
+                let base = self.ty()?;
+                self.expect(']')?;
+                let values = if self.punct(':') { self.list()? } else { Vec::new() };
+                syz.resources.push(Resource { name, base, values });
+            },
+            ("type", _) => {
+                let name = self.ident()?;
+                if Some(&Tok::Punct('[')) == self.peek() {
+                    syz.templates.push(name);
+                    let mut depth = 0;
+                    while let Some(tok) = self.peek().cloned() {
+                        match tok {
+                            Tok::Punct('{') | Tok::Punct('[') => depth += 1,
+                            Tok::Punct('}') | Tok::Punct(']') => depth -= 1,
+                            Tok::Newline if 0 == depth => return Ok(()),
+                            _ => {},
+                        }
+                        self.pos += 1;
+                    }
+                    return Ok(())
+                }
+                if self.punct('{') {
+                    let st = self.structure(name.clone(), false)?;
+                    syz.structs.insert(name, st);
+                } else if Some(&Tok::Punct('[')) == self.peek() && Some(&Tok::Newline) == self.peek_at(1) {
+                    self.pos += 1;
+                    let st = self.structure(name.clone(), true)?;
+                    syz.structs.insert(name, st);
+                } else {
+                    let ty = self.ty()?;
+                    syz.types.insert(name, ty);
+                }
+            },
+            (_, Some(Tok::Punct('='))) => {
+                self.pos += 1;
+                let values = self.list()?;
+                syz.flags.insert(name, values);
+            },
+            (_, Some(Tok::Punct('('))) => {
+                let call = self.syscall(name)?;
+                syz.syscalls.push(call);
+            },
+            (_, Some(Tok::Punct('{'))) => {
+                self.pos += 1;
+                let st = self.structure(name.clone(), false)?;
+                syz.structs.insert(name, st);
+            },
+            (_, Some(Tok::Punct('['))) => {
+                self.pos += 1;
+                let st = self.structure(name.clone(), true)?;
+                syz.structs.insert(name, st);
+            },
+            _ => return Err(self.error(&format!("unknown statement {}", name))),
+        }
+        match self.peek() {
+            None | Some(&Tok::Newline) => Ok(()),
+            _ => Err(self.error("expected end of line")),
+        }
+    }
+}
+
+impl Syzlang {
+    /// parse one .txt description, can be called for more files ( they refer each other )
+    pub fn parse(&mut self, data: &str) -> Result<(), io::Error> {
+        let mut parser = Parser { toks: lex(data)?, pos: 0 };
+        loop {
+            parser.newlines();
+            if parser.peek().is_none() {
+                return Ok(())
+            }
+            parser.statement(self)?;
+            parser.skip_line();
+        }
+    }
+
+    /// .const file : NAME = 123, or NAME = 386:1, amd64:2 ( arch specific )
+    pub fn consts(&mut self, data: &str, arch: &str) {
+        for line in data.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let (name, value) = match line.find('=') {
+                Some(ind) => (line[..ind].trim(), line[ind + 1..].trim()),
+                None => continue,
+            };
+            if name.is_empty() || "arches" == name {
+                continue
+            }
+            let value = if value.contains(':') {
+                value
+                    .split(',')
+                    .filter_map(|pair| {
+                        let mut pair = pair.splitn(2, ':');
+                        match (pair.next(), pair.next()) {
+                            (Some(a), Some(v)) if a.trim() == arch => Some(v.trim()),
+                            _ => None,
+                        }
+                    })
+                    .next()
+            } else {
+                Some(value)
+            };
+            if let Some(value) = value.and_then(int) {
+                self.consts.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// int literal, char, or named constant
+    pub fn value(&self, ty: &Ty) -> Option<i64> {
+        match *ty {
+            Ty::Int(val) => Some(val),
+            Ty::Ident(ref name, _) => self.consts.get(name).cloned(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(name: &str, args: Vec<Ty>) -> Ty {
+        Ty::Ident(name.to_string(), args)
+    }
+
+    fn parse(data: &str) -> Syzlang {
+        let mut syz = Syzlang::default();
+        syz.parse(data).unwrap();
+        syz
+    }
+
+    #[test]
+    fn structs_and_unions() {
+        let syz = parse("
+sockaddr_in {
+	family	const[AF_INET, int16]
+	port	int16be[0:65535]
+	addr	array[int8, 4]	(in)
+	flags	int32:3
+} [packed]
+
+type alias_struct {
+	a	int8
+}
+
+sockaddr [
+	in	sockaddr_in
+	raw	array[int8]
+] [varlen]
+");
+        let st = &syz.structs["sockaddr_in"];
+        assert!(!st.union);
+        assert_eq!(vec![ident("packed", vec![])], st.attrs);
+        assert_eq!(vec![
+            (String::from("family"), ident("const", vec![ident("AF_INET", vec![]), ident("int16", vec![])])),
+            (String::from("port"), ident("int16be", vec![Ty::Range(0, 65535)])),
+            (String::from("addr"), ident("array", vec![ident("int8", vec![]), Ty::Int(4)])),
+            (String::from("flags"), Ty::Bits(Box::new(ident("int32", vec![])), 3)),
+        ], st.fields);
+
+        assert_eq!(1, syz.structs["alias_struct"].fields.len());
+
+        let union = &syz.structs["sockaddr"];
+        assert!(union.union);
+        assert_eq!(vec!["in", "raw"], union.fields.iter().map(|(name, _)| name.as_str()).collect::< Vec<&str> >());
+        assert_eq!(vec![ident("varlen", vec![])], union.attrs);
+    }
+
+    #[test]
+    fn flags_int_and_string() {
+        let syz = parse("
+open_flags = O_RDONLY, O_WRONLY, 0x80,
+	-1, 'a'
+fs_names = \"ext4\", `tmpfs`
+");
+        assert_eq!(vec![
+            ident("O_RDONLY", vec![]),
+            ident("O_WRONLY", vec![]),
+            Ty::Int(0x80),
+            Ty::Int(-1),
+            Ty::Int('a' as i64),
+        ], syz.flags["open_flags"]);
+        assert_eq!(vec![Ty::Str(String::from("ext4")), Ty::Str(String::from("tmpfs"))], syz.flags["fs_names"]);
+    }
+
+    #[test]
+    fn resources_and_syscalls() {
+        let syz = parse("
+include <sys/socket.h>
+resource sock[fd]
+resource sock_tcp[sock]: -1, AT_FDCWD
+type sock_alias sock_tcp
+socket$inet_tcp(domain const[AF_INET], type const[SOCK_STREAM], proto const[0]) sock_tcp
+close(fd fd) (disabled)
+");
+        assert_eq!(2, syz.resources.len());
+        assert_eq!("sock", syz.resources[0].name);
+        assert_eq!(ident("fd", vec![]), syz.resources[0].base);
+        assert!(syz.resources[0].values.is_empty());
+        assert_eq!(ident("sock", vec![]), syz.resources[1].base);
+        assert_eq!(vec![Ty::Int(-1), ident("AT_FDCWD", vec![])], syz.resources[1].values);
+        assert_eq!(ident("sock_tcp", vec![]), syz.types["sock_alias"]);
+
+        let socket = &syz.syscalls[0];
+        assert_eq!("socket$inet_tcp", socket.name);
+        assert_eq!("socket", socket.base());
+        assert_eq!(vec!["domain", "type", "proto"], socket.args.iter().map(|(name, _)| name.as_str()).collect::< Vec<&str> >());
+        assert_eq!(Some(ident("sock_tcp", vec![])), socket.ret);
+
+        let close = &syz.syscalls[1];
+        assert_eq!(None, close.ret);
+        assert_eq!(vec![ident("disabled", vec![])], close.attrs);
+    }
+
+    #[test]
+    fn templates_are_remembered_only() {
+        let syz = parse("
+type tmpl[TYPE] {
+	a	TYPE
+}
+x {
+	b	tmpl[int8]
+}
+");
+        assert_eq!(vec![String::from("tmpl")], syz.templates);
+        assert!(!syz.structs.contains_key("tmpl"));
+        assert!(syz.structs.contains_key("x"));
+    }
+
+    #[test]
+    fn consts_of_arch() {
+        let mut syz = Syzlang::default();
+        syz.consts("
+# comment
+arches = 386, amd64
+AF_INET = 2
+O_WRONLY = 0x1
+SYS_x = 386:10, amd64:20
+SYS_only_386 = 386:30
+", "amd64");
+        assert_eq!(Some(&2), syz.consts.get("AF_INET"));
+        assert_eq!(Some(&1), syz.consts.get("O_WRONLY"));
+        assert_eq!(Some(&20), syz.consts.get("SYS_x"));
+        assert!(!syz.consts.contains_key("SYS_only_386"));
+        assert!(!syz.consts.contains_key("arches"));
+
+        assert_eq!(Some(2), syz.value(&ident("AF_INET", vec![])));
+        assert_eq!(Some(7), syz.value(&Ty::Int(7)));
+        assert_eq!(None, syz.value(&ident("unknown", vec![])));
+        assert_eq!(None, syz.value(&Ty::Str(String::from("x"))));
+    }
+
+    #[test]
+    fn malformed_is_refused_with_line() {
+        for (data, line) in [
+            ("x {\n\ta int8\n", 2),
+            ("ok = 1\nfoo bar\n", 2),
+            ("call(a int32[0:]) \n", 1),
+            ("s \"unterminated\n", 1),
+            ("n = 0xZZ\n", 1),
+            ("resource r[fd\n", 1),
+            ("call(a int8) ret extra\n", 1),
+            ("x {\n\ta int8 b\n}\n", 2),
+        ] {
+            let err = Syzlang::default().parse(data).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            assert!(err.to_string().starts_with(&format!("line {} :", line)), "{:?} : {}", data, err);
+        }
+    }
+}
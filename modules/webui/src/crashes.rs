@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::Path;
+
+use core::poc::trace::Trace;
+
+use libpoclog::emit;
+
+/// what can be downloaded per crash
+pub const FILES: &[&str] = &["crash.txt", "trace.jsonl", "poc.c", "banana.h"];
+
+/// one crash as stored by exec::forkserver
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CrashEntry {
+    /// folder name, crash_<time>_<count>
+    pub name: String,
+    /// content of crash.txt
+    pub info: String,
+    /// recorded context available, therefore PoC as well
+    pub trace: bool,
+}
+
+/// folders of crashes, newest first
+pub fn list(dir: &str) -> Vec<CrashEntry> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut crashes = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| valid(name))
+        .map(|name| {
+            let path = Path::new(dir).join(&name);
+            CrashEntry {
+                info : generic::read_file(&path.join("crash.txt").to_string_lossy()).unwrap_or_default(),
+                trace : path.join("trace.jsonl").exists(),
+                name,
+            }
+        })
+        .collect::< Vec<CrashEntry> >();
+    crashes.sort_by(|a, b| b.name.cmp(&a.name));
+    crashes
+}
+
+/// no way out of crash_dir
+fn valid(name: &str) -> bool {
+    name.starts_with("crash_") && name.chars().all(|c| c.is_ascii_alphanumeric() || '_' == c)
+}
+
+/// content of crash file, PoC is emitted from recorded context when it was not stored
+pub fn file(dir: &str, name: &str, file: &str) -> Option<Vec<u8>> {
+    if !valid(name) || !FILES.contains(&file) {
+        return None
+    }
+    let path = Path::new(dir).join(name);
+    if let Ok(data) = generic::read_file_raw(&path.join(file).to_string_lossy()) {
+        return Some(data)
+    }
+    match file {
+        "banana.h" => Some(emit::HEADER.as_bytes().to_vec()),
+        "poc.c" => Trace::load(&path.join("trace.jsonl").to_string_lossy())
+            .ok()
+            .map(|trace| emit::program(&trace).into_bytes()),
+        _ => None,
+    }
+}
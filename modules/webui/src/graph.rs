@@ -0,0 +1,62 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use core::state::id::StateTableId;
+
+/// level of dtor, as in Call::level
+pub const DTOR: usize = !0;
+
+lazy_static! {
+    static ref GRAPH: Mutex<Graph> = Mutex::new(Graph::default());
+}
+
+/// one observed move of state between levels
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// result of call at from level, which slope was applied
+    pub ok: bool,
+    pub count: u64,
+}
+
+#[derive(Default)]
+struct Graph {
+    /// (sid, from, to, ok) -> count
+    edges: BTreeMap<(u64, usize, usize, bool), u64>,
+    /// per thread : state, level and result of its last call
+    last: HashMap<u64, (u64, usize, bool)>,
+}
+
+/// account call of thread, edge goes from level of previous call to level of this one
+///
+/// - State slopes are not visible to modules, however level of every call is
+///     - therefore graph is slopes as they were really applied during fuzzing
+pub fn call(tid: u64, sid: &StateTableId, level: usize, ok: bool) {
+    let StateTableId::Id(sid) = *sid;
+    let mut graph = match GRAPH.lock() {
+        Ok(graph) => graph,
+        Err(_) => return,
+    };
+    if let Some((last_sid, from, last_ok)) = graph.last.insert(tid, (sid, level, ok)) {
+        if last_sid == sid && DTOR != from {
+            *graph.edges.entry((sid, from, level, last_ok)).or_insert(0) += 1;
+        }
+    }
+    if DTOR == level {
+        graph.last.remove(&tid);
+    }
+}
+
+/// transitions observed so far, per state kind
+pub fn edges() -> BTreeMap<u64, Vec<Edge>> {
+    let graph = match GRAPH.lock() {
+        Ok(graph) => graph,
+        Err(_) => return BTreeMap::new(),
+    };
+    let mut edges: BTreeMap<u64, Vec<Edge>> = BTreeMap::new();
+    for (&(sid, from, to, ok), &count) in graph.edges.iter() {
+        edges.entry(sid).or_default().push(Edge { from, to, ok, count });
+    }
+    edges
+}
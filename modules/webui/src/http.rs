@@ -0,0 +1,127 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use serde_json;
+
+use core::banana::bananaq;
+use core::context;
+use libstats::ipc;
+
+use crashes;
+use graph;
+use page;
+
+static SERVER: Once = Once::new();
+
+#[derive(Serialize)]
+struct ApiStatus {
+    status: ipc::Status,
+    transitions: Vec<(String, Vec<graph::Edge>)>,
+    crashes: Vec<crashes::CrashEntry>,
+}
+
+/// embedded http server, localhost only
+///
+/// - one request per connection, handled in server thread ( it is monitoring, not a web service )
+/// - server ends once fuzzing stops, it is joined at shutdown
+pub fn serve(listen: &str, crash_dir: &str) {
+    let addr: SocketAddr = match listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => return println!("[webui] bad listen address {} : {}", listen, e),
+    };
+    if !addr.ip().is_loopback() {
+        return println!("[webui] refusing to listen on {}, only localhost is allowed ( use ssh tunnel )", addr);
+    }
+    let crash_dir = crash_dir.to_string();
+    let ctx = context::current();
+    SERVER.call_once(|| { bananaq::adopt(thread::spawn(move || {
+        context::enter(ctx);
+        let listener = match TcpListener::bind(addr) {
+            Ok(listener) => listener,
+            Err(e) => return println!("[webui] unable to listen on {} : {}", addr, e),
+        };
+        for client in ipc::incoming(&listener) {
+            handle(client, &crash_dir);
+        }
+    })); });
+}
+
+fn transitions(status: &ipc::Status) -> Vec<(String, Vec<graph::Edge>)> {
+    graph::edges()
+        .into_iter()
+        .map(|(sid, edges)| (
+            status.stats.states
+                .iter()
+                .find(|state| state.id == sid)
+                .map_or(format!("state {}", sid), |state| state.name.clone()),
+            edges))
+        .collect()
+}
+
+fn route(path: &str, crash_dir: &str) -> (&'static str, &'static str, Vec<u8>) {
+    const HTML: &str = "text/html; charset=utf-8";
+    const TEXT: &str = "text/plain; charset=utf-8";
+    const JSON: &str = "application/json";
+
+    let parts = path
+        .trim_start_matches('/')
+        .split('/')
+        .collect::< Vec<&str> >();
+    match parts[..] {
+        [""] => {
+            let status = ipc::status();
+            let edges = transitions(&status);
+            ("200 OK", HTML, page::index(&status, &edges, &crashes::list(crash_dir)).into_bytes())
+        },
+        ["api", "status"] => {
+            let status = ipc::status();
+            let api = ApiStatus {
+                transitions : transitions(&status),
+                crashes : crashes::list(crash_dir),
+                status,
+            };
+            ("200 OK", JSON, serde_json::to_string(&api).unwrap_or_default().into_bytes())
+        },
+        ["call", id] => {
+            let call = id.parse::<u64>()
+                .ok()
+                .and_then(|id| ipc::status().stats.calls.into_iter().find(|call| call.id == id));
+            match call {
+                Some(call) => ("200 OK", HTML, page::call(&call).into_bytes()),
+                None => ("404 Not Found", HTML, page::not_found().into_bytes()),
+            }
+        },
+        ["crash", name, file] => match crashes::file(crash_dir, name, file) {
+            Some(data) => ("200 OK", TEXT, data),
+            None => ("404 Not Found", HTML, page::not_found().into_bytes()),
+        },
+        _ => ("404 Not Found", HTML, page::not_found().into_bytes()),
+    }
+}
+
+fn handle(mut client: TcpStream, crash_dir: &str) {
+    let _ = client.set_read_timeout(Some(Duration::from_secs(2)));
+    let _ = client.set_write_timeout(Some(Duration::from_secs(2)));
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 0x2000 {
+        match client.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(size) => request.extend_from_slice(&buf[..size]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut line = request.lines().next().unwrap_or("").split_whitespace();
+    let (code, kind, body) = match (line.next(), line.next()) {
+        (Some("GET"), Some(path)) => route(path.split('?').next().unwrap_or("/"), crash_dir),
+        _ => ("405 Method Not Allowed", "text/plain", Vec::new()),
+    };
+    let head = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        code, kind, body.len());
+    let _ = client.write_all(head.as_bytes());
+    let _ = client.write_all(&body);
+}
@@ -0,0 +1,63 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate core;
+extern crate generic;
+extern crate libpoclog;
+extern crate libstats;
+
+use std::time::Duration;
+
+use core::exec::call::Call;
+use core::exec::fd_info::CallInfo;
+use core::banana::observer::{ICallObserver, IStateObserver};
+use core::state::state::StateInfo;
+
+pub mod crashes;
+pub mod graph;
+pub mod http;
+mod page;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebUiConfig {
+    /// 127.0.0.1:PORT, other than loopback addresses are refused
+    listen: String,
+    /// crash_dir of [forkserver] in config.toml, empty = no crash list
+    #[serde(default)]
+    crash_dir: String,
+}
+
+/// browser monitoring : heatmap of calls, transitions between levels of states, crashes + PoCs
+///
+/// - metrics per call / state are taken from libstats, put it online as well
+/// - transitions are observed here, by post-callback
+struct WebUi { }
+
+impl ICallObserver for WebUi {
+    fn notify(&self, _: &StateInfo, _: &Call) -> bool {
+        true
+    }
+    fn notify_post(&self, state: &StateInfo, call: &Call, result: &CallInfo, _: Duration) {
+        graph::call(generic::get_tid(), &state.id, call.level(), result.success());
+    }
+}
+
+pub fn observers(
+    cfg: &Option<WebUiConfig>,
+) -> (
+    Option<Box<dyn IStateObserver>>,
+    Option<Box<dyn ICallObserver>>,
+) {
+    match *cfg {
+        Some(ref cfg) => {
+            http::serve(&cfg.listen, &cfg.crash_dir);
+            (None, Some(Box::new(WebUi { })))
+        },
+        _ => (None, None),
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+
+use libstats::collector::{CallSnapshot, LATENCY_BUCKETS};
+use libstats::ipc::Status;
+
+use crashes::CrashEntry;
+use graph::{Edge, DTOR};
+
+const STYLE: &str = "body{font-family:monospace;background:#111;color:#ddd;margin:20px}\
+    a{color:#8cf}h2{border-bottom:1px solid #444}\
+    .heat{display:flex;flex-wrap:wrap;gap:3px}\
+    .cell{width:110px;height:42px;padding:3px;font-size:11px;overflow:hidden;color:#000;text-decoration:none}\
+    table{border-collapse:collapse}td,th{padding:2px 10px;text-align:right}td:first-child{text-align:left}\
+    pre{background:#222;padding:8px}";
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn layout(title: &str, refresh: bool, body: &str) -> String {
+    String::from("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>") + &escape(title) + "</title>" +
+        if refresh { "<meta http-equiv=\"refresh\" content=\"2\">" } else { "" } +
+        "<style>" + STYLE + "</style></head><body>" +
+        "<a href=\"/\">banana fuzzer</a> | <a href=\"/api/status\">json</a>" +
+        body + "</body></html>"
+}
+
+/// red ( fails ) -> green ( succeeds ), brighter for more frequent calls
+fn heat(call: &CallSnapshot, max: u64) -> String {
+    let freq = ((1 + call.total) as f64).ln() / ((1 + max) as f64).ln().max(1.0);
+    format!("hsl({},70%,{}%)", (120.0 * call.ratio) as u32, (25.0 + 45.0 * freq) as u32)
+}
+
+fn heatmap(calls: &[CallSnapshot]) -> String {
+    let max = calls.iter().map(|call| call.total).max().unwrap_or(0);
+    let mut out = String::from("<h2>calls</h2><p>color = success ratio, brightness = frequency</p><div class=\"heat\">");
+    for call in calls.iter() {
+        out += &format!("<a class=\"cell\" href=\"/call/{}\" style=\"background:{}\" title=\"{} : {} calls, {:.1}% ok\">\
+            {}<br>{} / {:.0}%{}</a>",
+            call.id, heat(call, max), escape(&call.name), call.total, 100.0 * call.ratio,
+            escape(&call.name), call.total, 100.0 * call.ratio,
+            if call.dead { " DEAD" } else { "" });
+    }
+    out + "</div>"
+}
+
+/// levels as nodes in a row, dtor last; ok edges curve above, fail edges below
+fn transitions(name: &str, edges: &[Edge]) -> String {
+    let levels = edges
+        .iter()
+        .flat_map(|edge| vec![edge.from, edge.to])
+        .collect::< BTreeSet<usize> >()
+        .into_iter()
+        .collect::< Vec<usize> >();
+    let x = |level: usize| 60 + 160 * levels.iter().position(|&l| l == level).unwrap_or(0);
+    let max = edges.iter().map(|edge| edge.count).max().unwrap_or(1) as f64;
+
+    let mut svg = format!("<h3>{}</h3><svg width=\"{}\" height=\"220\">", escape(name), 120 + 160 * levels.len());
+    for edge in edges.iter() {
+        let (from, to) = (x(edge.from) as i64, x(edge.to) as i64);
+        let bend = if edge.ok { -1 } else { 1 };
+        let width = 1.0 + 5.0 * (edge.count as f64).ln() / max.ln().max(1.0);
+        let color = if edge.ok { "#4c4" } else { "#c44" };
+        let path = if from == to {
+            format!("M {} 110 C {} {} {} {} {} 110", from - 10, from - 40, 110 + bend * 70, from + 40, 110 + bend * 70, from + 10)
+        } else {
+            format!("M {} 110 Q {} {} {} 110", from, (from + to) / 2, 110 + bend * (30 + (to - from).abs() / 4), to)
+        };
+        let label_y = 110 + bend * if from == to { 55 } else { 15 + (to - from).abs() / 8 };
+        svg += &format!("<path d=\"{}\" stroke=\"{}\" stroke-width=\"{:.1}\" fill=\"none\" opacity=\"0.8\"/>\
+            <text x=\"{}\" y=\"{}\" fill=\"{}\" font-size=\"11\">{}</text>",
+            path, color, width, (from + to) / 2, label_y, color, edge.count);
+    }
+    for &level in levels.iter() {
+        let label = if DTOR == level { String::from("dtor") } else { level.to_string() };
+        svg += &format!("<circle cx=\"{}\" cy=\"110\" r=\"18\" fill=\"#335\" stroke=\"#aaf\"/>\
+            <text x=\"{}\" y=\"114\" fill=\"#fff\" font-size=\"12\" text-anchor=\"middle\">{}</text>",
+            x(level), x(level), label);
+    }
+    svg + "</svg>"
+}
+
+fn crash_list(crashes: &[CrashEntry]) -> String {
+    let mut out = format!("<h2>crashes ({})</h2><table><tr><th>crash</th><th>info</th><th>files</th></tr>", crashes.len());
+    for crash in crashes.iter() {
+        out += &format!("<tr><td>{}</td><td>{}</td><td>", escape(&crash.name), escape(&crash.info).replace('\n', "<br>"));
+        out += &format!("<a href=\"/crash/{}/crash.txt\">info</a>", crash.name);
+        if crash.trace {
+            out += &format!(" <a href=\"/crash/{0}/trace.jsonl\">trace</a> <a href=\"/crash/{0}/poc.c\">poc.c</a> \
+                <a href=\"/crash/{0}/banana.h\">banana.h</a>", crash.name);
+        }
+        out += "</td></tr>";
+    }
+    out + "</table>"
+}
+
+pub fn index(status: &Status, edges: &[(String, Vec<Edge>)], crashes: &[CrashEntry]) -> String {
+    let stats = &status.stats;
+    let mut body = format!("<h2>session</h2><p>uptime {}s / {}s, {} calls, {:.1}% ok, {:.1} exec/s<br>\
+        queue {}/{}, racers {}, reclaimed {}, hangs {}, crashes {}</p>",
+        stats.uptime_ms / 1000, status.active_seconds, stats.total, 100.0 * stats.ratio, stats.exec_per_sec,
+        status.queue.states, status.queue.max, status.queue.racers, status.queue.reclaimed, status.hangs, status.crashes);
+    body += &heatmap(&stats.calls);
+
+    body += "<h2>states</h2><table><tr><th>state</th><th>alive</th><th>created</th><th>calls</th><th>ok%</th></tr>";
+    for state in stats.states.iter() {
+        body += &format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td></tr>",
+            escape(&state.name), state.alive, state.created, state.calls, 100.0 * state.ratio);
+    }
+    body += "</table><h2>transitions</h2><p>levels of state, green = slope after successful call, red = after failed one</p>";
+    for (name, edges) in edges.iter() {
+        body += &transitions(name, edges);
+    }
+    body += &crash_list(crashes);
+    layout("banana fuzzer", true, &body)
+}
+
+pub fn call(call: &CallSnapshot) -> String {
+    let max = call.latency.iter().cloned().max().unwrap_or(0).max(1);
+    let mut body = format!("<h2>{} ({})</h2><p>{} calls, {} ok ({:.1}%), {:.1} exec/s, avg {} us{}</p>\
+        <h3>latency</h3><table><tr><th>us</th><th>calls</th><th></th></tr>",
+        escape(&call.name), call.id, call.total, call.success, 100.0 * call.ratio, call.exec_per_sec, call.avg_us,
+        if call.dead { ", DEAD" } else { "" });
+    for (ind, &count) in call.latency.iter().enumerate().take(LATENCY_BUCKETS) {
+        if 0 == count {
+            continue
+        }
+        let range = if 0 == ind { String::from("< 1") } else { format!("{} - {}", 1u64 << (ind - 1), 1u64 << ind) };
+        body += &format!("<tr><td>{}</td><td>{}</td><td><div style=\"background:#8cf;height:10px;width:{}px\"></div></td></tr>",
+            range, count, 1 + 400 * count / max);
+    }
+    layout(&call.name, true, &(body + "</table>"))
+}
+
+pub fn not_found() -> String {
+    layout("not found", false, "<h2>not found</h2>")
+}
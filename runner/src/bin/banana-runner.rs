@@ -0,0 +1,139 @@
+extern crate core;
+extern crate desc;
+extern crate librunner;
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use core::context::{self, FuzzerContext};
+use core::poc::trace::Trace;
+
+use desc::Desc;
+
+use core::config::{self, Override};
+use librunner::run;
+use librunner::Registry;
+
+const USAGE: &str = "usage : banana-runner <command> [--config config.toml] [--desc table.toml] [--set field=value] [--field value]
+  fuzz                                              fuzz states of table, modules by modules.toml
+  replay [--ordered ms] [--fuzz-after] [--modules] trace.jsonl
+  validate [trace.jsonl ..]                         check config, table and traces, exit 1 on problems
+  stats --listen 127.0.0.1:port                     status of running fuzzer ( stats module )
+
+  any config.toml field can be overridden : --set coverage.source=kcov, --max-queue-size 20";
+
+fn fail(what: String) -> ! {
+    eprintln!("[banana-runner] {}", what);
+    process::exit(1)
+}
+
+struct Args {
+    command: String,
+    config: Option<String>,
+    desc: Option<String>,
+    overrides: Vec<Override>,
+    ordered: Option<Duration>,
+    fuzz_after: bool,
+    modules: bool,
+    listen: Option<String>,
+    traces: Vec<String>,
+}
+
+fn parse() -> Args {
+    let mut args = env::args().skip(1);
+    let command = match args.next() {
+        Some(ref arg) if "-h" == arg || "--help" == arg => { println!("{}", USAGE); process::exit(0) },
+        Some(command) => command,
+        None => fail(String::from(USAGE)),
+    };
+    let mut parsed = Args {
+        command,
+        config : None,
+        desc : None,
+        overrides : Vec::new(),
+        ordered : None,
+        fuzz_after : false,
+        modules : false,
+        listen : None,
+        traces : Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(format!("{} needs value", arg)));
+        match arg.as_str() {
+            "--config" => parsed.config = Some(value()),
+            "--desc" => parsed.desc = Some(value()),
+            "--set" => parsed.overrides.push(Override::parse(&value()).unwrap_or_else(|e| fail(e.to_string()))),
+            "--ordered" => parsed.ordered = Some(Duration::from_millis(
+                    value().parse::<u64>().unwrap_or_else(|e| fail(format!("--ordered : {}", e))))),
+            "--fuzz-after" => parsed.fuzz_after = true,
+            "--modules" => parsed.modules = true,
+            "--listen" => parsed.listen = Some(value()),
+            "-h" | "--help" => { println!("{}", USAGE); process::exit(0) },
+            flag if flag.starts_with("--") => {
+                let over = Override::new(&flag[2..], &value());
+                parsed.overrides.push(over)
+            },
+            _ => parsed.traces.push(arg),
+        }
+    }
+    parsed
+}
+
+fn registry(args: &Args) -> Registry {
+    match args.desc {
+        Some(ref fname) => Registry::from_desc(Desc::load(fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)))),
+        None => fail(String::from("no states to fuzz : --desc table.toml needed")),
+    }
+}
+
+fn load(fname: &str) -> Trace {
+    Trace::load(fname).unwrap_or_else(|e| fail(format!("{} : {}", fname, e)))
+}
+
+/// generic fuzzer driver : states from desc table, modules from modules.toml, config.toml + overrides
+///
+/// - config is loaded into own context : --config, or $BANANA_CONFIG, or config.toml, or defaults
+/// - replay takes seed of trace, unless overridden
+fn main() {
+    let args = parse();
+    if "stats" == args.command {
+        let addr = args.listen.clone().unwrap_or_else(|| fail(String::from("stats : --listen needed")));
+        return println!("{}", run::stats(&addr).unwrap_or_else(|e| fail(format!("{} : {}", addr, e))))
+    }
+
+    let traces = args.traces
+        .iter()
+        .map(|fname| (fname.clone(), load(fname)))
+        .collect::< Vec<(String, Trace)> >();
+    let mut cfg = config::load(args.config.as_deref(), &args.overrides).unwrap_or_else(|e| fail(e.to_string()));
+    if "replay" == args.command && !args.overrides.iter().any(|over| "seed" == over.path) {
+        cfg.seed = traces.first().map(|(_, trace)| trace.header.seed).or(cfg.seed);
+    }
+    let ctx = FuzzerContext::new(cfg);
+    context::enter(ctx.clone());
+
+    match args.command.as_str() {
+        "fuzz" => process::exit(run::fuzz(registry(&args))),
+        "replay" => {
+            let trace = match traces.as_slice() {
+                [(_, trace)] => trace,
+                _ => fail(String::from("replay : exactly one trace needed")),
+            };
+            if let Err(e) = run::replay(registry(&args), trace, args.ordered, args.fuzz_after, args.modules) {
+                fail(e.to_string())
+            }
+        },
+        "validate" => {
+            let problems = run::validate(&registry(&args), &traces);
+            for problem in problems.iter() {
+                println!("[banana-runner] {}", problem);
+            }
+            if !problems.is_empty() {
+                process::exit(1)
+            }
+            println!("[banana-runner] {} + {} traces ok", config::locate(args.config.as_deref()).unwrap_or_else(|| String::from("defaults")), traces.len());
+        },
+        _ => fail(String::from(USAGE)),
+    }
+}
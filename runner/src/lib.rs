@@ -0,0 +1,13 @@
+extern crate serde_json;
+extern crate rand;
+
+extern crate core;
+extern crate desc;
+extern crate generic;
+extern crate plugs;
+extern crate libstats;
+
+pub mod registry;
+pub mod run;
+
+pub use registry::Registry;
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::thread;
+
+use rand::Rng;
+
+use core::banana::looper::FuzzyState;
+use core::exec::fd_info::Fd;
+use core::rng;
+use core::state::id::StateTableId;
+use core::state::state::IFuzzyObj;
+
+use desc::Desc;
+
+type Ctor = Box<dyn Fn() -> Option<Box<dyn IFuzzyObj>> + Send + Sync>;
+type Dup = Box<dyn Fn(&Fd) -> Option<Box<dyn IFuzzyObj>> + Send + Sync>;
+
+struct Entry {
+    id: StateTableId,
+    name: String,
+    /// new object, by its ctor
+    ctor: Ctor,
+    /// already existing object ( fd of alive state ), for races
+    dup: Dup,
+}
+
+/// state constructors per StateTableId, what push_state of fuzzer needs
+///
+/// - filled by hand for hand written states, or from desc table
+/// - id is matched exactly first, then by bits ( as queue does )
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Default for Registry {
+    fn default() -> Registry { Registry::new() }
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            entries : Vec::new(),
+        }
+    }
+
+    pub fn register<C, D>(&mut self, id: StateTableId, name: &str, ctor: C, dup: D)
+        where
+            C: Fn() -> Option<Box<dyn IFuzzyObj>> + Send + Sync + 'static,
+            D: Fn(&Fd) -> Option<Box<dyn IFuzzyObj>> + Send + Sync + 'static,
+    {
+        self.entries.push(Entry {
+            id,
+            name : name.to_string(),
+            ctor : Box::new(ctor),
+            dup : Box::new(dup),
+        })
+    }
+
+    /// every state described in table
+    pub fn from_desc(desc: Desc) -> Registry {
+        let states = desc.description().state
+            .iter()
+            .map(|sdesc| (StateTableId::Id(sdesc.id), sdesc.name.clone()))
+            .collect::< Vec<(StateTableId, String)> >();
+        let desc = Arc::new(desc);
+        let mut registry = Registry::new();
+        for (id, name) in states {
+            let (ctor_desc, ctor_name) = (desc.clone(), name.clone());
+            let (dup_desc, dup_name) = (desc.clone(), name.clone());
+            registry.register(
+                id,
+                &name,
+                move || ctor_desc.state(&ctor_name),
+                move |fd| dup_desc.duped(&dup_name, fd));
+        }
+        registry
+    }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// (id, name) of registered states
+    pub fn states(&self) -> Vec<(StateTableId, &str)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.id.clone(), entry.name.as_str()))
+            .collect()
+    }
+
+    fn find(&self, id: &StateTableId) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == *id)
+            .or_else(|| self.entries
+                .iter()
+                .find(|entry| entry.id.clone() & id.clone()))
+    }
+
+    pub fn contains(&self, id: &StateTableId) -> bool { self.find(id).is_some() }
+
+    /// object for state of id, invalid fd == new one, otherwise dup of alive one
+    pub fn create(&self, id: &StateTableId, fd: &Fd) -> Option<Box<dyn IFuzzyObj>> {
+        let entry = self.find(id)?;
+        if fd.is_invalid() {
+            (entry.ctor)()
+        } else {
+            (entry.dup)(fd)
+        }
+    }
+
+    /// create state and fuzz it in its own thread, None if there is no such state
+    pub fn push(&self, id: &StateTableId, fd: &Fd) -> Option< thread::JoinHandle<()> > {
+        self.create(id, fd).map(FuzzyState::fuzz)
+    }
+
+    /// id of random registered state
+    pub fn random(&self) -> Option<StateTableId> {
+        if self.entries.is_empty() {
+            return None
+        }
+        let ind = rng::thread_rng().gen_range(0..self.entries.len());
+        Some(self.entries[ind].id.clone())
+    }
+}
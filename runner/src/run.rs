@@ -0,0 +1,168 @@
+use std::io;
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use serde_json;
+
+use core::banana::bananaq;
+use core::context::{self, FuzzerContext};
+use core::exec::fd_info::Fd;
+use core::exec::forkserver;
+use core::poc::replay;
+use core::poc::trace::Trace;
+use core::state::id::StateTableId;
+
+use libstats::ipc;
+
+use registry::Registry;
+
+/// how long dup of replayed state waits for its recorded fd to come alive
+const DUP_WAIT: Duration = Duration::from_secs(5);
+
+/// push_state for modules ( racers, seeds, .. ), so registry must live till the end of process
+///
+/// - threads of pushed states are adopted, so shutdown joins them
+fn leak(registry: Registry) -> (&'static Registry, &'static (impl Fn(StateTableId, &Fd) + Sync + Send)) {
+    let registry: &'static Registry = Box::leak(Box::new(registry));
+    let push_state = Box::leak(Box::new(move |id: StateTableId, fd: &Fd| {
+        if let Some(state) = registry.push(&id, fd) {
+            bananaq::adopt(state)
+        }
+    }));
+    (registry, push_state)
+}
+
+/// install observers of modules online in modules.toml, fuzzing without them if there is none
+pub fn plug<F>(push_state: &'static F)
+    where F: Fn(StateTableId, &Fd) + Sync + Send
+{
+    let observers = match plugs::plug(push_state) {
+        Ok(observers) => observers,
+        Err(e) => return println!("[runner] no modules plugged, {}", e),
+    };
+    for mut observer in observers {
+        if context::config().noisy {
+            observer.stats();
+        }
+        if let Some(obs) = observer.state_obs().take() {
+            bananaq::attach_state_observer(obs);
+        }
+        if let Some(obs) = observer.call_obs().take() {
+            bananaq::attach_call_observer(obs);
+        }
+    }
+}
+
+/// main fuzzing loop, what fuzzer main.rs used to do
+///
+/// - push_count new states every push_sleep ms, random ones from registry
+/// - queue decides if they make it in ( max_queue_size, ratio, .. )
+/// - for active_seconds ( 0 = forever ) or till some module stops fuzzing ( limiter, .. ), then
+///   fuzzing is stopped gracefully trough plugs ( states closed, modules flushed, threads joined )
+/// - in forkserver worker when [forkserver] is configured, so call it first thing in main
+pub fn fuzz(registry: Registry) -> i32 {
+    let (registry, push_state) = leak(registry);
+    forkserver::serve(
+        || {
+            plug(push_state);
+            let config = context::config();
+            let start = Instant::now();
+            while !bananaq::stopping() && (0 == config.active_seconds || start.elapsed().as_secs() < config.active_seconds) {
+                for _ in 0..config.push_count {
+                    if let Some(id) = registry.random() {
+                        push_state(id, &Fd::empty());
+                    }
+                }
+                thread::sleep(Duration::from_millis(config.push_sleep));
+            }
+            plugs::stop_fuzzing()
+        },
+        |crash| println!("[runner] crash #{} : {:?} at {:?} after {} calls, context in {}",
+            crash.count, crash.reason, crash.last, crash.calls, crash.dir))
+}
+
+/// replay recorded trace, states are created by registry
+///
+/// - ordered : keep recorded order of calls across threads, waiting at most given time per call
+/// - fuzz_after : replayed states continue by regular fuzzing, otherwise they are closed
+/// - modules : plug modules as fuzzing does, off by default so only recorded calls are made
+/// - returns once all replayed states are done, modules are flushed then
+pub fn replay(registry: Registry, trace: &Trace, ordered: Option<Duration>, fuzz_after: bool, modules: bool) -> Result<(), io::Error> {
+    let (registry, push_state) = leak(registry);
+    if modules {
+        plug(push_state);
+    }
+    let mut states = Vec::new();
+    for (id, fd) in replay::schedule(trace, fuzz_after, ordered) {
+        let fd = if fd.is_invalid() {
+            fd
+        } else {
+            let start = Instant::now();
+            loop {
+                match replay::live_fd(fd.data()) {
+                    Some(live) => break live,
+                    None if start.elapsed() < DUP_WAIT => thread::sleep(Duration::from_millis(10)),
+                    None => break fd,
+                }
+            }
+        };
+        match registry.push(&id, &fd) {
+            Some(state) => states.push(state),
+            None => println!("[runner] state {:?} is not registered, its calls are not replayed", id),
+        }
+    }
+    for state in states {
+        let _ = state.join();
+    }
+    plugs::stop_fuzzing();
+    match replay::pending() {
+        0 => Ok(()),
+        pending => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} recorded states were not replayed", pending))),
+    }
+}
+
+/// what would break fuzzing later on, empty if nothing
+///
+/// - config is validated already by loading it ( core::config::load )
+/// - registered states must build, in scratch context without corpus : building state takes
+///   replay plan / corpus prefix of its id and draws rng, none of that is for validation
+/// - every state of traces must be registered
+pub fn validate(registry: &Registry, traces: &[(String, Trace)]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if registry.is_empty() {
+        problems.push(String::from("no states registered"))
+    }
+    let ctx = context::current();
+    let mut scratch = (*ctx.config()).clone();
+    scratch.corpus = None;
+    context::enter(FuzzerContext::new(scratch));
+    for (id, name) in registry.states() {
+        if registry.create(&id, &Fd::empty()).is_none() {
+            problems.push(format!("state {} ( {:?} ) can not be built", name, id))
+        }
+    }
+    context::enter(ctx);
+
+    for (fname, trace) in traces.iter() {
+        let mut missing = trace.calls
+            .iter()
+            .filter(|call| !registry.contains(&call.sid))
+            .map(|call| format!("{} ( {:?} )", call.state, call.sid))
+            .collect::< Vec<String> >();
+        missing.sort();
+        missing.dedup();
+        for state in missing {
+            problems.push(format!("trace {} : state {} is not registered", fname, state))
+        }
+    }
+    problems
+}
+
+/// status of running fuzzer ( stats module listening on addr ), as pretty json
+pub fn stats(addr: &str) -> Result<String, io::Error> {
+    let status = ipc::fetch(addr)?;
+    serde_json::to_string_pretty(&status).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}